@@ -0,0 +1,113 @@
+//! End-to-end timings by flag set: the real binary over synthetic
+//! directories of 10k and 100k entries. Every run goes through the whole
+//! listing; the groups only name the stage their flag sets lean on
+//! (collecting, sorting, formatting cells, rendering), so compare
+//! variants within a group rather than reading one as that stage alone.
+//!
+//! Save a baseline before a performance change and compare against it after:
+//!
+//!     cargo bench --bench listing -- --save-baseline before
+//!     cargo bench --bench listing -- --baseline before
+
+use std::fs::{self, File};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::{Duration, SystemTime};
+
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use tempfile::TempDir;
+
+const SIZES: [usize; 2] = [10_000, 100_000];
+const NOW: &str = "1600000000";
+
+/// `count` entries directly in one directory: mostly files of assorted
+/// sizes, extensions, and ages, with a directory every 50 entries.
+fn synthetic_dir(count: usize) -> TempDir {
+    const EXTENSIONS: [&str; 6] = ["rs", "md", "txt", "json", "png", ""];
+    let tmp = TempDir::new().expect("tempdir");
+    for idx in 0..count {
+        let ext = EXTENSIONS[idx % EXTENSIONS.len()];
+        let name = format!("entry-{:06}{}{ext}", (idx * 7_919) % count, if ext.is_empty() { "" } else { "." });
+        let path = tmp.path().join(name);
+        if idx % 50 == 0 {
+            fs::create_dir(&path).expect("mkdir");
+            continue;
+        }
+        let file = File::create(&path).expect("create");
+        file.set_len((idx as u64 * 37) % 65_536).expect("set_len");
+        let age = Duration::from_secs((idx as u64 * 3_571) % 40_000_000);
+        file.set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(1_600_000_000) - age)
+            .expect("set mtime");
+    }
+    tmp
+}
+
+/// A named set of flags, e.g. `("table", &["--color", "always"])`.
+type Variant = (&'static str, &'static [&'static str]);
+
+/// Lists `dir` with `args`, discarding the output.
+fn run(dir: &Path, args: &[&str]) {
+    let status = Command::new(env!("CARGO_BIN_EXE_nuls"))
+        .args(["--lang", "en", "--now", NOW])
+        .args(args)
+        .arg(dir)
+        .env_remove("NULS_CONFIG")
+        .env_remove("NULS_DEFAULT_ARGS")
+        .env("COLUMNS", "120")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .expect("run nuls");
+    assert!(status.success());
+}
+
+fn bench_listing(c: &mut Criterion) {
+    let stages: [(&str, &[Variant]); 4] = [
+        (
+            "collect",
+            &[
+                ("bare-names", &["-1", "--color", "never"]),
+                ("statx", &["-1", "--color", "never", "--statx"]),
+            ],
+        ),
+        (
+            "sort",
+            &[
+                ("name", &["-1", "--color", "never", "--sort", "name"]),
+                ("modified", &["-1", "--color", "never", "--sort", "modified"]),
+                ("size", &["-1", "--color", "never", "--sort", "size"]),
+            ],
+        ),
+        (
+            "format",
+            &[
+                ("plain", &["--tabs", "--color", "never"]),
+                ("colored", &["--tabs", "--color", "always"]),
+            ],
+        ),
+        (
+            "render",
+            &[
+                ("table", &["--color", "always"]),
+                ("commas", &["-m", "--color", "always"]),
+                ("json", &["--json"]),
+            ],
+        ),
+    ];
+    for count in SIZES {
+        let dir = synthetic_dir(count);
+        for (stage, variants) in stages {
+            let mut group = c.benchmark_group(stage);
+            group.sample_size(if count >= 100_000 { 10 } else { 30 });
+            for (variant, args) in variants {
+                group.bench_with_input(BenchmarkId::new(*variant, count), args, |b, args| {
+                    b.iter(|| run(dir.path(), args))
+                });
+            }
+            group.finish();
+        }
+    }
+}
+
+criterion_group!(benches, bench_listing);
+criterion_main!(benches);
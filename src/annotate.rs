@@ -0,0 +1,156 @@
+//! `--annotate 'CMD {}'`: runs a user command for each listed entry, in
+//! parallel and with a timeout, and keeps the first line of its stdout for
+//! an extra column.
+
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// What one command run produced.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Annotation {
+    Text(String),
+    TimedOut,
+    Failed(String),
+}
+
+/// Runs `template` once per path, with `{}` replaced by the shell-quoted
+/// path, on a small worker pool. Results come back in input order.
+pub fn run_all(template: &str, paths: &[PathBuf], timeout: Duration) -> Vec<Annotation> {
+    let results: Vec<Mutex<Option<Annotation>>> = paths.iter().map(|_| Mutex::new(None)).collect();
+    let next = AtomicUsize::new(0);
+    let workers = thread::available_parallelism()
+        .map(|count| count.get())
+        .unwrap_or(4)
+        .min(paths.len());
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| {
+                loop {
+                    let idx = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(path) = paths.get(idx) else {
+                        break;
+                    };
+                    let annotation = run_one(&expand(template, path), timeout);
+                    *results[idx].lock().expect("annotation slot") = Some(annotation);
+                }
+            });
+        }
+    });
+    results
+        .into_iter()
+        .map(|slot| {
+            slot.into_inner()
+                .expect("annotation slot")
+                .unwrap_or(Annotation::TimedOut)
+        })
+        .collect()
+}
+
+/// Substitutes every `{}` with the quoted path, or appends it when the
+/// template has no placeholder (like `xargs`).
+fn expand(template: &str, path: &Path) -> String {
+    let quoted = shell_quote(&path.display().to_string());
+    if template.contains("{}") {
+        template.replace("{}", &quoted)
+    } else {
+        format!("{template} {quoted}")
+    }
+}
+
+#[cfg(unix)]
+fn shell_quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', r"'\''"))
+}
+
+#[cfg(not(unix))]
+fn shell_quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('"', "\"\""))
+}
+
+#[cfg(unix)]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("sh");
+    shell.arg("-c").arg(command);
+    shell
+}
+
+#[cfg(not(unix))]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("cmd");
+    shell.arg("/C").arg(command);
+    shell
+}
+
+fn run_one(command: &str, timeout: Duration) -> Annotation {
+    let child = shell(command)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn();
+    let mut child = match child {
+        Ok(child) => child,
+        Err(err) => return Annotation::Failed(err.to_string()),
+    };
+    // Drain stdout on its own thread so a chatty command cannot fill the pipe and stall.
+    let mut stdout = child.stdout.take().expect("piped stdout");
+    let reader = thread::spawn(move || {
+        let mut buf = Vec::new();
+        stdout.read_to_end(&mut buf).map(|_| buf)
+    });
+    if !wait_with_deadline(&mut child, Instant::now() + timeout) {
+        let _ = child.kill();
+        let _ = child.wait();
+        return Annotation::TimedOut;
+    }
+    match reader.join() {
+        Ok(Ok(buf)) => {
+            let text = String::from_utf8_lossy(&buf);
+            Annotation::Text(text.lines().next().unwrap_or("").trim().to_string())
+        }
+        Ok(Err(err)) => Annotation::Failed(err.to_string()),
+        Err(_) => Annotation::Failed("reader panicked".to_string()),
+    }
+}
+
+/// Polls until the child exits (true) or the deadline passes (false).
+pub fn wait_with_deadline(child: &mut Child, deadline: Instant) -> bool {
+    loop {
+        match child.try_wait() {
+            Ok(Some(_)) => return true,
+            Ok(None) if Instant::now() < deadline => thread::sleep(Duration::from_millis(10)),
+            Ok(None) | Err(_) => return false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn placeholder_is_quoted_or_appended() {
+        let path = Path::new("it's here.txt");
+        assert_eq!(expand("wc -c < {}", path), r"wc -c < 'it'\''s here.txt'");
+        assert_eq!(expand("file -b", path), r"file -b 'it'\''s here.txt'");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn runs_in_order_and_times_out() {
+        let paths = [PathBuf::from("a"), PathBuf::from("b")];
+        assert_eq!(
+            run_all("printf '%s\\nsecond line' {}", &paths, Duration::from_secs(5)),
+            [Annotation::Text("a".into()), Annotation::Text("b".into())]
+        );
+        assert_eq!(
+            run_all("sleep 5; echo {}", &paths[..1], Duration::from_millis(100)),
+            [Annotation::TimedOut]
+        );
+    }
+}
@@ -0,0 +1,145 @@
+//! `--peek-archives`: the members of zip and tar files, read from their
+//! indexes and headers without extracting anything.
+
+use chrono::{Local, TimeZone};
+use flate2::read::GzDecoder;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+/// One path stored inside an archive.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Member {
+    pub path: String,
+    pub size: u64,
+    pub modified: Option<SystemTime>,
+    pub is_dir: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Format {
+    Zip,
+    Tar,
+    TarGz,
+}
+
+/// Picks the reader from the file name; other files are not peeked into.
+fn format_of(name: &str) -> Option<Format> {
+    let lower = name.to_ascii_lowercase();
+    if [".zip", ".jar", ".whl"].iter().any(|ext| lower.ends_with(ext)) {
+        Some(Format::Zip)
+    } else if lower.ends_with(".tar") {
+        Some(Format::Tar)
+    } else if lower.ends_with(".tar.gz") || lower.ends_with(".tgz") {
+        Some(Format::TarGz)
+    } else {
+        None
+    }
+}
+
+pub fn is_archive(name: &str) -> bool {
+    format_of(name).is_some()
+}
+
+/// Members of the archive at `path`, in stored order.
+pub fn members(path: &Path) -> Result<Vec<Member>, String> {
+    let name = path.file_name().map(|name| name.to_string_lossy()).unwrap_or_default();
+    let format = format_of(&name).ok_or_else(|| format!("{} is not a known archive type", path.display()))?;
+    let file = File::open(path).map_err(|err| format!("cannot open {}: {err}", path.display()))?;
+    let result = match format {
+        Format::Zip => zip_members(BufReader::new(file)),
+        Format::Tar => tar_members(BufReader::new(file)),
+        Format::TarGz => tar_members(GzDecoder::new(BufReader::new(file))),
+    };
+    result.map_err(|err| format!("cannot read archive {}: {err}", path.display()))
+}
+
+/// Reads only the central directory, so compressed data is never inflated.
+fn zip_members(reader: BufReader<File>) -> Result<Vec<Member>, String> {
+    let mut archive = zip::ZipArchive::new(reader).map_err(|err| err.to_string())?;
+    (0..archive.len())
+        .map(|idx| {
+            let entry = archive.by_index_raw(idx).map_err(|err| err.to_string())?;
+            // Zip timestamps carry no zone; they are written in the creator's local time.
+            let modified = entry.last_modified().and_then(|time| {
+                Local
+                    .with_ymd_and_hms(
+                        time.year().into(),
+                        time.month().into(),
+                        time.day().into(),
+                        time.hour().into(),
+                        time.minute().into(),
+                        time.second().into(),
+                    )
+                    .single()
+                    .map(SystemTime::from)
+            });
+            Ok(Member {
+                path: entry.name().map_err(|err| err.to_string())?.trim_end_matches('/').to_string(),
+                size: entry.size(),
+                modified,
+                is_dir: entry.is_dir(),
+            })
+        })
+        .collect()
+}
+
+fn tar_members(reader: impl Read) -> Result<Vec<Member>, String> {
+    let mut archive = tar::Archive::new(reader);
+    let entries = archive.entries().map_err(|err| err.to_string())?;
+    entries
+        .map(|entry| {
+            let entry = entry.map_err(|err| err.to_string())?;
+            let header = entry.header();
+            let path = entry.path().map_err(|err| err.to_string())?;
+            Ok(Member {
+                path: path.to_string_lossy().trim_end_matches('/').to_string(),
+                size: header.size().map_err(|err| err.to_string())?,
+                modified: header
+                    .mtime()
+                    .ok()
+                    .map(|secs| SystemTime::UNIX_EPOCH + Duration::from_secs(secs)),
+                is_dir: header.entry_type().is_dir(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_follow_extensions() {
+        assert_eq!(format_of("release.ZIP"), Some(Format::Zip));
+        assert_eq!(format_of("app.jar"), Some(Format::Zip));
+        assert_eq!(format_of("src.tar"), Some(Format::Tar));
+        assert_eq!(format_of("src.tar.gz"), Some(Format::TarGz));
+        assert_eq!(format_of("src.tgz"), Some(Format::TarGz));
+        assert_eq!(format_of("notes.txt"), None);
+        assert_eq!(format_of("tar"), None);
+    }
+
+    #[test]
+    fn tar_members_report_paths_sizes_and_times() {
+        let mut builder = tar::Builder::new(Vec::new());
+        let mut header = tar::Header::new_gnu();
+        header.set_size(5);
+        header.set_mtime(1_000);
+        header.set_mode(0o644);
+        builder.append_data(&mut header, "docs/readme.md", &b"hello"[..]).expect("append");
+        let bytes = builder.into_inner().expect("finish");
+
+        let members = tar_members(&bytes[..]).expect("readable tar");
+        assert_eq!(
+            members,
+            vec![Member {
+                path: "docs/readme.md".to_string(),
+                size: 5,
+                modified: Some(SystemTime::UNIX_EPOCH + Duration::from_secs(1_000)),
+                is_dir: false,
+            }]
+        );
+    }
+}
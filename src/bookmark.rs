@@ -0,0 +1,172 @@
+//! Named directory shortcuts: `nuls bookmark add NAME [PATH]` saves one to a
+//! small TOML state file, and `@NAME` (or `@NAME/sub/dir`) works anywhere a
+//! path is expected.
+
+use crate::width::display_width;
+use crate::{BookmarkAction, long_path, palette};
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Bookmarks keyed by name, plus the file they were loaded from.
+#[derive(Debug, Default)]
+pub struct Bookmarks {
+    file: PathBuf,
+    entries: BTreeMap<String, PathBuf>,
+}
+
+impl Bookmarks {
+    /// Reads `$NULS_BOOKMARKS`, or `$XDG_STATE_HOME/nuls/bookmarks.toml`
+    /// (falling back to `~/.local/state`); a missing file means no bookmarks.
+    pub fn load() -> Result<Bookmarks, String> {
+        let file = state_path().ok_or("cannot locate the bookmark file: set $HOME or $NULS_BOOKMARKS")?;
+        let entries = match fs::read_to_string(&file) {
+            Ok(text) => toml::from_str(&text)
+                .map_err(|err| format!("invalid bookmark file {}: {}", file.display(), err.message()))?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(err) => return Err(format!("cannot read bookmark file {}: {err}", file.display())),
+        };
+        Ok(Bookmarks { file, entries })
+    }
+
+    fn save(&self) -> Result<(), String> {
+        if let Some(parent) = self.file.parent() {
+            fs::create_dir_all(parent).map_err(|err| format!("cannot create {}: {err}", parent.display()))?;
+        }
+        let text = toml::to_string(&self.entries).map_err(|err| format!("cannot encode bookmarks: {err}"))?;
+        fs::write(&self.file, text).map_err(|err| format!("cannot write bookmark file {}: {err}", self.file.display()))
+    }
+
+    /// Replaces `@NAME` or `@NAME/rest` with the bookmarked directory. Paths
+    /// that exist as written, or do not start with `@`, are returned as is.
+    pub fn expand(&self, path: &Path) -> Result<PathBuf, String> {
+        let Some(text) = path.to_str().and_then(|text| text.strip_prefix('@')) else {
+            return Ok(path.to_path_buf());
+        };
+        if path.exists() {
+            return Ok(path.to_path_buf());
+        }
+        let (name, rest) = text.split_once(['/', '\\']).unwrap_or((text, ""));
+        let base = self
+            .entries
+            .get(name)
+            .ok_or_else(|| format!("unknown bookmark '@{name}' (see `nuls bookmark list`)"))?;
+        Ok(if rest.is_empty() { base.clone() } else { base.join(rest) })
+    }
+}
+
+/// Resolves `@NAME` paths for any subcommand; plain paths skip the state file.
+pub fn expand(path: &Path) -> Result<PathBuf, String> {
+    if path.to_str().is_some_and(|text| text.starts_with('@')) {
+        Bookmarks::load()?.expand(path)
+    } else {
+        Ok(path.to_path_buf())
+    }
+}
+
+pub fn run(action: &BookmarkAction) -> Result<String, String> {
+    let mut bookmarks = Bookmarks::load()?;
+    match action {
+        BookmarkAction::Add { name, path } => {
+            check_name(name)?;
+            let target = path
+                .canonicalize()
+                .map(|target| long_path::friendly(&target).into_owned())
+                .map_err(|err| format!("cannot resolve {}: {err}", path.display()))?;
+            if !target.is_dir() {
+                return Err(format!("{} is not a directory", path.display()));
+            }
+            let line = format!("@{name} -> {}\n", target.display());
+            bookmarks.entries.insert(name.clone(), target);
+            bookmarks.save()?;
+            Ok(line)
+        }
+        BookmarkAction::Remove { name } => {
+            if bookmarks.entries.remove(name).is_none() {
+                return Err(format!("unknown bookmark '@{name}'"));
+            }
+            bookmarks.save()?;
+            Ok(format!("removed @{name}\n"))
+        }
+        BookmarkAction::List => Ok(render_list(&bookmarks)),
+    }
+}
+
+/// `@name  path` lines with the names padded to one column.
+fn render_list(bookmarks: &Bookmarks) -> String {
+    if bookmarks.entries.is_empty() {
+        return format!(
+            "{}\n",
+            palette::paint("no bookmarks (add one with `nuls bookmark add NAME`)", palette::GIT_CLEAN)
+        );
+    }
+    let width = bookmarks
+        .entries
+        .keys()
+        .map(|name| display_width(name) + 1)
+        .max()
+        .unwrap_or(0);
+    bookmarks
+        .entries
+        .iter()
+        .map(|(name, target)| {
+            let label = format!("@{name}{}", " ".repeat(width - 1 - display_width(name)));
+            format!(
+                "{}  {}\n",
+                palette::paint(label, palette::HEADER),
+                palette::paint(target.display().to_string(), palette::DIR)
+            )
+        })
+        .collect()
+}
+
+/// Names become the first component of `@NAME/...`, so separators and
+/// whitespace are rejected.
+fn check_name(name: &str) -> Result<(), String> {
+    let valid = !name.is_empty()
+        && !name.starts_with('@')
+        && !name.chars().any(|c| c == '/' || c == '\\' || c.is_whitespace());
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("invalid bookmark name '{name}' (no spaces, slashes, or leading @)"))
+    }
+}
+
+fn state_path() -> Option<PathBuf> {
+    if let Some(path) = env::var_os("NULS_BOOKMARKS").filter(|value| !value.is_empty()) {
+        return Some(PathBuf::from(path));
+    }
+    let base = env::var_os("XDG_STATE_HOME")
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local").join("state")))?;
+    Some(base.join("nuls").join("bookmarks.toml"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expand_replaces_the_leading_name() {
+        let bookmarks = Bookmarks {
+            file: PathBuf::new(),
+            entries: BTreeMap::from([("proj".to_string(), PathBuf::from("/work/proj"))]),
+        };
+        assert_eq!(bookmarks.expand(Path::new("@proj")), Ok(PathBuf::from("/work/proj")));
+        assert_eq!(bookmarks.expand(Path::new("@proj/src")), Ok(PathBuf::from("/work/proj/src")));
+        assert_eq!(bookmarks.expand(Path::new("src")), Ok(PathBuf::from("src")));
+        assert!(bookmarks.expand(Path::new("@nope")).is_err());
+    }
+
+    #[test]
+    fn names_cannot_contain_separators() {
+        assert!(check_name("proj").is_ok());
+        assert!(check_name("my proj").is_err());
+        assert!(check_name("a/b").is_err());
+        assert!(check_name("@proj").is_err());
+        assert!(check_name("").is_err());
+    }
+}
@@ -0,0 +1,177 @@
+//! Linux file capabilities, read from the `security.capability` xattr that
+//! `setcap` writes, and spelled the way `getcap` prints them
+//! (`cap_net_raw+ep`). Other platforms have none, and builds without the
+//! `xattr` feature never read them.
+#![cfg_attr(not(all(target_os = "linux", feature = "xattr")), allow(dead_code))]
+
+use std::fmt;
+use std::path::Path;
+
+/// Capability names by bit, as in `<linux/capability.h>`.
+const NAMES: [&str; 41] = [
+    "chown",
+    "dac_override",
+    "dac_read_search",
+    "fowner",
+    "fsetid",
+    "kill",
+    "setgid",
+    "setuid",
+    "setpcap",
+    "linux_immutable",
+    "net_bind_service",
+    "net_broadcast",
+    "net_admin",
+    "net_raw",
+    "ipc_lock",
+    "ipc_owner",
+    "sys_module",
+    "sys_rawio",
+    "sys_chroot",
+    "sys_ptrace",
+    "sys_pacct",
+    "sys_admin",
+    "sys_boot",
+    "sys_nice",
+    "sys_resource",
+    "sys_time",
+    "sys_tty_config",
+    "mknod",
+    "lease",
+    "audit_write",
+    "audit_control",
+    "setfcap",
+    "mac_override",
+    "mac_admin",
+    "syslog",
+    "wake_alarm",
+    "block_suspend",
+    "audit_read",
+    "perfmon",
+    "bpf",
+    "checkpoint_restore",
+];
+
+const REVISION_MASK: u32 = 0xff00_0000;
+const REVISION_1: u32 = 0x0100_0000;
+const REVISION_2: u32 = 0x0200_0000;
+const REVISION_3: u32 = 0x0300_0000;
+const FLAG_EFFECTIVE: u32 = 0x1;
+
+/// The capability sets a file grants when executed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FileCaps {
+    permitted: u64,
+    inheritable: u64,
+    /// Permitted capabilities are raised at exec, not just allowed.
+    effective: bool,
+}
+
+impl FileCaps {
+    /// Parses a `vfs_cap_data` xattr value; `None` when it is malformed or
+    /// grants nothing.
+    pub fn parse(value: &[u8]) -> Option<FileCaps> {
+        let word = |idx: usize| {
+            let bytes = value.get(idx * 4..idx * 4 + 4)?;
+            Some(u32::from_le_bytes(bytes.try_into().ok()?))
+        };
+        let magic = word(0)?;
+        let words = match magic & REVISION_MASK {
+            REVISION_1 => 1,
+            REVISION_2 | REVISION_3 => 2,
+            _ => return None,
+        };
+        let (mut permitted, mut inheritable) = (0, 0);
+        for idx in 0..words {
+            permitted |= u64::from(word(1 + idx * 2)?) << (32 * idx);
+            inheritable |= u64::from(word(2 + idx * 2)?) << (32 * idx);
+        }
+        let caps = FileCaps {
+            permitted,
+            inheritable,
+            effective: magic & FLAG_EFFECTIVE != 0,
+        };
+        (permitted | inheritable != 0).then_some(caps)
+    }
+
+    /// The `e`, `i`, and `p` flags of capability `bit`, in `getcap` order.
+    fn flags(self, bit: usize) -> String {
+        let permitted = self.permitted >> bit & 1 == 1;
+        let inheritable = self.inheritable >> bit & 1 == 1;
+        [
+            (self.effective && (permitted || inheritable), 'e'),
+            (inheritable, 'i'),
+            (permitted, 'p'),
+        ]
+        .into_iter()
+        .filter_map(|(set, flag)| set.then_some(flag))
+        .collect()
+    }
+}
+
+/// Capabilities sharing the same flags are grouped: `cap_net_admin,cap_net_raw+ep`.
+impl fmt::Display for FileCaps {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut groups: Vec<(String, Vec<String>)> = Vec::new();
+        for bit in 0..64 {
+            let flags = self.flags(bit);
+            if flags.is_empty() {
+                continue;
+            }
+            let name = NAMES.get(bit).map_or_else(|| bit.to_string(), |name| format!("cap_{name}"));
+            match groups.iter_mut().find(|(group_flags, _)| *group_flags == flags) {
+                Some((_, names)) => names.push(name),
+                None => groups.push((flags, vec![name])),
+            }
+        }
+        let groups: Vec<String> = groups
+            .into_iter()
+            .map(|(flags, names)| format!("{}+{flags}", names.join(",")))
+            .collect();
+        f.write_str(&groups.join(" "))
+    }
+}
+
+/// Whether this build reads capabilities at all.
+pub const XATTR: bool = cfg!(feature = "xattr");
+
+/// The capabilities set on `path` itself (symlinks are not followed).
+#[cfg(all(target_os = "linux", feature = "xattr"))]
+pub fn read(path: &Path) -> Option<FileCaps> {
+    // Revision 3, the largest, is 24 bytes.
+    let mut value = [0u8; 24];
+    let len = rustix::fs::lgetxattr(path, "security.capability", &mut value[..]).ok()?;
+    FileCaps::parse(&value[..len])
+}
+
+#[cfg(not(all(target_os = "linux", feature = "xattr")))]
+pub fn read(_path: &Path) -> Option<FileCaps> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn xattr(words: &[u32]) -> Vec<u8> {
+        words.iter().flat_map(|word| word.to_le_bytes()).collect()
+    }
+
+    #[test]
+    fn capabilities_read_like_getcap() {
+        let net_raw = 1 << 13;
+        let caps = FileCaps::parse(&xattr(&[REVISION_2 | FLAG_EFFECTIVE, net_raw, 0, 0, 0])).expect("caps");
+        assert_eq!(caps.to_string(), "cap_net_raw+ep");
+
+        let net_admin = 1 << 12;
+        let value = xattr(&[REVISION_3, net_admin | net_raw, net_admin, 1 << (38 - 32), 0, 0]);
+        assert_eq!(
+            FileCaps::parse(&value).expect("caps").to_string(),
+            "cap_net_admin+ip cap_net_raw,cap_perfmon+p"
+        );
+
+        assert_eq!(FileCaps::parse(&xattr(&[REVISION_2, 0, 0, 0, 0])), None);
+        assert_eq!(FileCaps::parse(&xattr(&[REVISION_2, net_raw])), None);
+        assert_eq!(FileCaps::parse(&xattr(&[0x0400_0000, net_raw, 0])), None);
+    }
+}
@@ -0,0 +1,545 @@
+//! The table's columns. Everything about one lives in its `ColumnSpec`: the
+//! config key, the header, the default alignment, and how a row's cell is
+//! drawn. `Column` names a slot in `REGISTRY`, so a new column (owner, inode,
+//! mime) is a variant, a spec, and a registry entry here, plus whatever turns
+//! it on in `run_list`.
+
+use crate::config::TableConfig;
+use crate::delta::SizeChange;
+use crate::i18n::Strings;
+use crate::width::truncate;
+use crate::{Align, CellArena, EntryRow, EntryType, RowFormat, palette, write_size_as};
+use std::fmt::Write as _;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Column {
+    Index,
+    Name,
+    Rename,
+    Type,
+    Size,
+    Delta,
+    Disk,
+    Modified,
+    Entries,
+    Access,
+    Author,
+    OpenBy,
+    Note,
+}
+
+/// One table column.
+pub trait ColumnSpec: Sync {
+    /// The column's name in the `[headers]` and `[table]` config tables.
+    fn key(&self) -> &'static str;
+
+    /// Built-in header text in the active language.
+    fn default_header(&self, strings: &Strings) -> &'static str;
+
+    /// Columns holding numbers line up on the right unless `[table] align`
+    /// says otherwise.
+    fn numeric(&self) -> bool {
+        false
+    }
+
+    fn header_color(&self) -> &'static str {
+        palette::HEADER
+    }
+
+    /// Plain and colored text for `row`, shown at position `idx`. The
+    /// column is as wide as its widest plain text.
+    fn cell(&self, row: &EntryRow, idx: usize, format: &RowFormat) -> (String, String);
+
+    /// Adds the cell for `row` to `arena`. Columns drawn in a single color
+    /// override this to write straight into the arena's buffer.
+    fn push_cell(&self, row: &EntryRow, idx: usize, format: &RowFormat, align: Align, arena: &mut CellArena) {
+        let (plain, colored) = self.cell(row, idx, format);
+        arena.push(&plain, &colored, align);
+    }
+
+    /// What `row` adds to the column's `--totals` lines: `None` for columns
+    /// that do not add up, and for rows with nothing to add.
+    fn quantity(&self, _row: &EntryRow) -> Option<i128> {
+        None
+    }
+
+    /// A total or average of `quantity` values, written the way the column
+    /// writes one row's.
+    fn write_quantity(&self, value: i128, _format: &RowFormat) -> String {
+        value.to_string()
+    }
+}
+
+/// Every column's spec, in `Column` order.
+static REGISTRY: [&dyn ColumnSpec; 13] = [
+    &IndexColumn,
+    &NameColumn,
+    &RenameColumn,
+    &TypeColumn,
+    &SizeColumn,
+    &DeltaColumn,
+    &DiskColumn,
+    &ModifiedColumn,
+    &EntriesColumn,
+    &AccessColumn,
+    &AuthorColumn,
+    &OpenByColumn,
+    &NoteColumn,
+];
+
+impl Column {
+    pub const ALL: [Column; 13] = [
+        Column::Index,
+        Column::Name,
+        Column::Rename,
+        Column::Type,
+        Column::Size,
+        Column::Delta,
+        Column::Disk,
+        Column::Modified,
+        Column::Entries,
+        Column::Access,
+        Column::Author,
+        Column::OpenBy,
+        Column::Note,
+    ];
+
+    /// Shrink steps when `[table] shrink` is unset: drop the type column,
+    /// then shorten times, then cut names.
+    pub const DEFAULT_SHRINK: [Column; 3] = [Column::Type, Column::Modified, Column::Name];
+
+    pub fn spec(self) -> &'static dyn ColumnSpec {
+        REGISTRY[self as usize]
+    }
+
+    pub fn key(self) -> &'static str {
+        self.spec().key()
+    }
+
+    /// The column whose config key is `key`.
+    pub fn from_key(key: &str) -> Option<Column> {
+        Column::ALL.into_iter().find(|column| column.key() == key)
+    }
+
+    /// `[table] shrink` as columns, checked against the known keys.
+    pub fn shrink_order(config: &TableConfig) -> Result<Vec<Column>, String> {
+        let Some(keys) = &config.shrink else {
+            return Ok(Column::DEFAULT_SHRINK.to_vec());
+        };
+        keys.iter()
+            .map(|key| Column::from_key(key).ok_or_else(|| format!("table.shrink: unknown column '{key}'")))
+            .collect()
+    }
+
+    pub fn cell(self, row: &EntryRow, idx: usize, format: &RowFormat) -> (String, String) {
+        self.spec().cell(row, idx, format)
+    }
+
+    /// Adds this column's cell for `row` to `arena`, with names cut to
+    /// `name_limit` cells when set.
+    pub fn push_cell(
+        self,
+        row: &EntryRow,
+        idx: usize,
+        format: &RowFormat,
+        name_limit: Option<usize>,
+        arena: &mut CellArena,
+    ) {
+        let align = format.aligns.get(self);
+        match name_limit {
+            Some(limit) if self == Column::Name => {
+                let (plain, colored) = self.cell(row, idx, format);
+                let (plain, colored) = truncate(&plain, &colored, limit);
+                arena.push(&plain, &colored, align);
+            }
+            _ => self.spec().push_cell(row, idx, format, align, arena),
+        }
+    }
+}
+
+/// The `--totals` lines under the table body: the sum, then the mean, of
+/// each column that adds up, over the listed entries (archive members left
+/// out). The name column labels them, with the git line counts summed into
+/// the total.
+pub fn push_totals(rows: &[EntryRow], columns: &[Column], format: &RowFormat, arena: &mut CellArena) {
+    let entries: Vec<&EntryRow> = rows.iter().filter(|row| row.archive_branch.is_none()).collect();
+    let (added, deleted) = entries
+        .iter()
+        .filter_map(|row| row.git.as_ref())
+        .fold((None, None), |(added, deleted): (Option<u64>, Option<u64>), git| {
+            let sum = |total: Option<u64>, lines: Option<u64>| match (total, lines) {
+                (None, None) => None,
+                _ => Some(total.unwrap_or(0) + lines.unwrap_or(0)),
+            };
+            (sum(added, git.added), sum(deleted, git.deleted))
+        });
+    let total_label = match (added, deleted) {
+        (None, None) => "total".to_string(),
+        _ => format!("total (+{} -{})", added.unwrap_or(0), deleted.unwrap_or(0)),
+    };
+    for (label, mean) in [(total_label.as_str(), false), ("average", true)] {
+        for &column in columns {
+            let align = format.aligns.get(column);
+            if column == Column::Name {
+                arena.push(label, &palette::paint(label, palette::HEADER), align);
+                continue;
+            }
+            let spec = column.spec();
+            let values: Vec<i128> = entries.iter().filter_map(|row| spec.quantity(row)).collect();
+            let text = match values.iter().sum::<i128>() {
+                _ if values.is_empty() => String::new(),
+                sum if mean => spec.write_quantity((sum as f64 / values.len() as f64).round() as i128, format),
+                sum => spec.write_quantity(sum, format),
+            };
+            arena.push(&text, &palette::paint(&text, palette::SIZE), align);
+        }
+    }
+}
+
+/// `--elide-common`: takes out of `columns` each one whose cells read the
+/// same on every row, returning it with that text. The index and names
+/// always stay, as do columns `--totals` adds up, and one row is left alone.
+pub fn elide_common(rows: &[EntryRow], columns: &mut Vec<Column>, format: &RowFormat) -> Vec<(Column, String)> {
+    let mut elided = Vec::new();
+    if rows.len() < 2 {
+        return elided;
+    }
+    columns.retain(|&column| {
+        let spec = column.spec();
+        if matches!(column, Column::Index | Column::Name)
+            || (format.totals && rows.iter().any(|row| spec.quantity(row).is_some()))
+        {
+            return true;
+        }
+        let first = spec.cell(&rows[0], 0, format).0;
+        if rows[1..].iter().any(|row| spec.cell(row, 0, format).0 != first) {
+            return true;
+        }
+        elided.push((column, first));
+        false
+    });
+    elided
+}
+
+struct IndexColumn;
+
+impl ColumnSpec for IndexColumn {
+    fn key(&self) -> &'static str {
+        "index"
+    }
+
+    fn default_header(&self, strings: &Strings) -> &'static str {
+        strings.header_index
+    }
+
+    fn numeric(&self) -> bool {
+        true
+    }
+
+    fn header_color(&self) -> &'static str {
+        palette::INDEX
+    }
+
+    /// Archive members are not numbered.
+    fn cell(&self, row: &EntryRow, idx: usize, _format: &RowFormat) -> (String, String) {
+        if row.archive_branch.is_some() {
+            return (String::new(), String::new());
+        }
+        (idx.to_string(), palette::paint(idx.to_string(), palette::INDEX))
+    }
+
+    fn push_cell(&self, row: &EntryRow, idx: usize, format: &RowFormat, align: Align, arena: &mut CellArena) {
+        if row.archive_branch.is_some() {
+            let (plain, colored) = self.cell(row, idx, format);
+            return arena.push(&plain, &colored, align);
+        }
+        arena.push_painted(palette::INDEX, align, |text| {
+            let _ = write!(text, "{idx}");
+        });
+    }
+}
+
+struct NameColumn;
+
+impl ColumnSpec for NameColumn {
+    fn key(&self) -> &'static str {
+        "name"
+    }
+
+    fn default_header(&self, strings: &Strings) -> &'static str {
+        strings.header_name
+    }
+
+    fn cell(&self, row: &EntryRow, _idx: usize, _format: &RowFormat) -> (String, String) {
+        row.annotated_name_cell()
+    }
+}
+
+struct RenameColumn;
+
+impl ColumnSpec for RenameColumn {
+    fn key(&self) -> &'static str {
+        "rename"
+    }
+
+    fn default_header(&self, _strings: &Strings) -> &'static str {
+        "renamed to"
+    }
+
+    fn cell(&self, row: &EntryRow, _idx: usize, _format: &RowFormat) -> (String, String) {
+        row.rename_cell()
+    }
+}
+
+struct TypeColumn;
+
+impl ColumnSpec for TypeColumn {
+    fn key(&self) -> &'static str {
+        "type"
+    }
+
+    fn default_header(&self, strings: &Strings) -> &'static str {
+        strings.header_type
+    }
+
+    fn cell(&self, row: &EntryRow, _idx: usize, format: &RowFormat) -> (String, String) {
+        row.type_cell(format.strings)
+    }
+
+    fn push_cell(&self, row: &EntryRow, _idx: usize, format: &RowFormat, align: Align, arena: &mut CellArena) {
+        let label = match row.entry_type {
+            EntryType::Dir => format.strings.dir,
+            EntryType::File => format.strings.file,
+        };
+        arena.push_painted(palette::TYPE, align, |text| text.push_str(label));
+    }
+}
+
+struct SizeColumn;
+
+impl ColumnSpec for SizeColumn {
+    fn key(&self) -> &'static str {
+        "size"
+    }
+
+    fn default_header(&self, strings: &Strings) -> &'static str {
+        strings.header_size
+    }
+
+    fn numeric(&self) -> bool {
+        true
+    }
+
+    fn cell(&self, row: &EntryRow, _idx: usize, format: &RowFormat) -> (String, String) {
+        row.size_cell(format.strings, format.size_style)
+    }
+
+    /// Files only: a directory's own size tracks the filesystem, not what
+    /// it holds.
+    fn quantity(&self, row: &EntryRow) -> Option<i128> {
+        let known = !row.is_dir() && !row.not_checked_out && !row.stat_skipped && !row.broken_link;
+        known.then_some(i128::from(row.size_bytes))
+    }
+
+    fn write_quantity(&self, value: i128, format: &RowFormat) -> String {
+        let mut text = String::new();
+        write_size_as(&mut text, u64::try_from(value).unwrap_or(0), format.size_style);
+        text
+    }
+
+    fn push_cell(&self, row: &EntryRow, idx: usize, format: &RowFormat, align: Align, arena: &mut CellArena) {
+        if row.not_checked_out || row.stat_skipped || row.broken_link {
+            let (plain, colored) = self.cell(row, idx, format);
+            return arena.push(&plain, &colored, align);
+        }
+        arena.push_painted(palette::SIZE, align, |text| write_size_as(text, row.size_bytes, format.size_style));
+    }
+}
+
+struct DeltaColumn;
+
+impl ColumnSpec for DeltaColumn {
+    fn key(&self) -> &'static str {
+        "delta"
+    }
+
+    fn default_header(&self, _strings: &Strings) -> &'static str {
+        "delta"
+    }
+
+    fn numeric(&self) -> bool {
+        true
+    }
+
+    fn cell(&self, row: &EntryRow, _idx: usize, format: &RowFormat) -> (String, String) {
+        row.delta_cell(format.size_style)
+    }
+
+    /// Files that changed size; new ones have no earlier size to differ from.
+    fn quantity(&self, row: &EntryRow) -> Option<i128> {
+        match row.size_change? {
+            SizeChange::By(bytes) => Some(bytes),
+            SizeChange::New => None,
+        }
+    }
+
+    fn write_quantity(&self, value: i128, format: &RowFormat) -> String {
+        match value {
+            0 => "0 B".to_string(),
+            _ => SizeChange::By(value).text(format.size_style),
+        }
+    }
+}
+
+struct DiskColumn;
+
+impl ColumnSpec for DiskColumn {
+    fn key(&self) -> &'static str {
+        "disk"
+    }
+
+    fn default_header(&self, strings: &Strings) -> &'static str {
+        strings.header_disk
+    }
+
+    fn numeric(&self) -> bool {
+        true
+    }
+
+    fn cell(&self, row: &EntryRow, _idx: usize, format: &RowFormat) -> (String, String) {
+        row.disk_cell(format.size_style)
+    }
+
+    fn quantity(&self, row: &EntryRow) -> Option<i128> {
+        row.disk_bytes.filter(|_| !row.is_dir()).map(i128::from)
+    }
+
+    fn write_quantity(&self, value: i128, format: &RowFormat) -> String {
+        let mut text = String::new();
+        write_size_as(&mut text, u64::try_from(value).unwrap_or(0), format.size_style);
+        text
+    }
+}
+
+struct ModifiedColumn;
+
+impl ColumnSpec for ModifiedColumn {
+    fn key(&self) -> &'static str {
+        "modified"
+    }
+
+    fn default_header(&self, strings: &Strings) -> &'static str {
+        strings.header_modified
+    }
+
+    fn cell(&self, row: &EntryRow, _idx: usize, format: &RowFormat) -> (String, String) {
+        row.modified_cell(format)
+    }
+
+    fn push_cell(&self, row: &EntryRow, _idx: usize, format: &RowFormat, align: Align, arena: &mut CellArena) {
+        let (text, color) = row.modified_text(format);
+        arena.push_painted(color, align, |buffer| buffer.push_str(&text));
+    }
+}
+
+struct EntriesColumn;
+
+impl ColumnSpec for EntriesColumn {
+    fn key(&self) -> &'static str {
+        "entries"
+    }
+
+    fn default_header(&self, _strings: &Strings) -> &'static str {
+        "entries"
+    }
+
+    fn numeric(&self) -> bool {
+        true
+    }
+
+    fn cell(&self, row: &EntryRow, _idx: usize, _format: &RowFormat) -> (String, String) {
+        row.entries_cell()
+    }
+
+    fn quantity(&self, row: &EntryRow) -> Option<i128> {
+        row.child_count.map(i128::from)
+    }
+}
+
+struct AccessColumn;
+
+impl ColumnSpec for AccessColumn {
+    fn key(&self) -> &'static str {
+        "access"
+    }
+
+    fn default_header(&self, _strings: &Strings) -> &'static str {
+        "access"
+    }
+
+    fn cell(&self, row: &EntryRow, _idx: usize, _format: &RowFormat) -> (String, String) {
+        row.access_cell()
+    }
+}
+
+struct AuthorColumn;
+
+impl ColumnSpec for AuthorColumn {
+    fn key(&self) -> &'static str {
+        "author"
+    }
+
+    fn default_header(&self, _strings: &Strings) -> &'static str {
+        "author"
+    }
+
+    fn cell(&self, row: &EntryRow, _idx: usize, _format: &RowFormat) -> (String, String) {
+        row.author_cell()
+    }
+}
+
+struct OpenByColumn;
+
+impl ColumnSpec for OpenByColumn {
+    fn key(&self) -> &'static str {
+        "open_by"
+    }
+
+    fn default_header(&self, _strings: &Strings) -> &'static str {
+        "open by"
+    }
+
+    fn cell(&self, row: &EntryRow, _idx: usize, _format: &RowFormat) -> (String, String) {
+        row.open_by_cell()
+    }
+}
+
+struct NoteColumn;
+
+impl ColumnSpec for NoteColumn {
+    fn key(&self) -> &'static str {
+        "note"
+    }
+
+    fn default_header(&self, _strings: &Strings) -> &'static str {
+        "note"
+    }
+
+    fn cell(&self, row: &EntryRow, _idx: usize, _format: &RowFormat) -> (String, String) {
+        row.note_cell()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn registry_follows_column_order_with_unique_keys() {
+        for (slot, column) in Column::ALL.into_iter().enumerate() {
+            assert_eq!(column as usize, slot);
+            assert_eq!(Column::from_key(column.key()), Some(column));
+        }
+        assert_eq!(Column::from_key("colour"), None);
+    }
+}
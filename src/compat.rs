@@ -0,0 +1,260 @@
+//! `ls` compatibility: rewrites coreutils-style arguments into nuls flags so
+//! `alias ls=nuls` keeps muscle memory and scripts working. Active when the
+//! binary is invoked as `ls` or given `--compat ls`.
+
+use crate::Cli;
+use clap::CommandFactory;
+use std::ffi::{OsStr, OsString};
+use std::path::Path;
+
+/// ls long options that take a value nuls has no use for; dropped along with it.
+const IGNORED_VALUE_OPTIONS: &[&str] = &[
+    "block-size",
+    "hide",
+    "ignore",
+    "indicator-style",
+    "quoting-style",
+    "tabsize",
+    "time",
+    "time-style",
+    "width",
+];
+
+/// ls short options whose value follows in the same cluster or the next argument.
+const IGNORED_VALUE_SHORTS: &[char] = &['I', 'T', 'w'];
+
+/// Returns `args` unchanged unless compat mode is requested, in which case the
+/// `--compat` marker is normalized and every argument is translated. The
+/// result always runs the `list` subcommand, so `ls stat` lists a directory
+/// named `stat`.
+pub fn translate_args(args: impl IntoIterator<Item = OsString>) -> Vec<OsString> {
+    let mut args: Vec<OsString> = args.into_iter().collect();
+    let Some(program) = args.first().cloned() else {
+        return args;
+    };
+
+    let invoked_as_ls = Path::new(&program)
+        .file_stem()
+        .is_some_and(|stem| stem == OsStr::new("ls"));
+    let flag_at = compat_flag_position(&args);
+    if !invoked_as_ls && flag_at.is_none() {
+        return args;
+    }
+    if let Some((index, len)) = flag_at {
+        args.drain(index..index + len);
+    }
+
+    let mut translated = vec![program, OsString::from("--compat=ls"), OsString::from("list")];
+    translated.extend(translate_ls(&args[1..]));
+    translated
+}
+
+/// Finds `--compat ls` or `--compat=ls` before any `--`, as (index, arg count).
+fn compat_flag_position(args: &[OsString]) -> Option<(usize, usize)> {
+    for (index, arg) in args.iter().enumerate().skip(1) {
+        match arg.to_str() {
+            Some("--") => return None,
+            Some("--compat=ls") => return Some((index, 1)),
+            Some("--compat") if args.get(index + 1).and_then(|next| next.to_str()) == Some("ls") => {
+                return Some((index, 2));
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+fn translate_ls(args: &[OsString]) -> Vec<OsString> {
+    let command = Cli::command();
+    let mut out = Vec::new();
+    let mut iter = args.iter();
+
+    while let Some(arg) = iter.next() {
+        let Some(text) = arg.to_str() else {
+            out.push(arg.clone());
+            continue;
+        };
+
+        if text == "--" {
+            out.push(arg.clone());
+            out.extend(iter.by_ref().cloned());
+            break;
+        }
+
+        if let Some(long) = text.strip_prefix("--") {
+            let (name, inline_value) = match long.split_once('=') {
+                Some((name, value)) => (name, Some(value)),
+                None => (long, None),
+            };
+            let mut take_value = || {
+                inline_value
+                    .map(str::to_string)
+                    .or_else(|| iter.next().and_then(|value| value.to_str().map(str::to_string)))
+            };
+            match name {
+                "all" | "almost-all" => out.push("-a".into()),
+                "reverse" => out.push("-r".into()),
+                "dereference" => out.push("-L".into()),
+                "directory" => out.push("-d".into()),
+                "help" | "version" => out.push(arg.clone()),
+                "color" | "colour" => {
+                    let when = inline_value.map(map_color).unwrap_or("always");
+                    out.push(format!("--color={when}").into());
+                }
+                "format" => match take_value().as_deref() {
+                    Some("single-column") => out.push("-1".into()),
+                    Some("commas") => out.push("-m".into()),
+                    _ => {}
+                },
+                "sort" => match take_value().as_deref() {
+                    Some("time") => out.push("-t".into()),
+                    Some("size") => out.push("-S".into()),
+                    _ => {}
+                },
+                _ if IGNORED_VALUE_OPTIONS.contains(&name) => {
+                    take_value();
+                }
+                _ => pass_nuls_long(&command, name, arg, inline_value.is_some(), &mut iter, &mut out),
+            }
+            continue;
+        }
+
+        match text.strip_prefix('-') {
+            Some(cluster) if !cluster.is_empty() => {
+                let mapped = translate_short_cluster(cluster, &mut iter);
+                if !mapped.is_empty() {
+                    out.push(format!("-{mapped}").into());
+                }
+            }
+            _ => out.push(arg.clone()),
+        }
+    }
+    out
+}
+
+/// Keeps a long flag only if nuls itself defines it, along with its value.
+fn pass_nuls_long<'a>(
+    command: &clap::Command,
+    name: &str,
+    arg: &OsString,
+    has_inline_value: bool,
+    iter: &mut impl Iterator<Item = &'a OsString>,
+    out: &mut Vec<OsString>,
+) {
+    let Some(known) = command
+        .get_arguments()
+        .find(|candidate| candidate.get_long() == Some(name))
+    else {
+        return;
+    };
+    out.push(arg.clone());
+    if !has_inline_value && known.get_action().takes_values() {
+        out.extend(iter.next().cloned());
+    }
+}
+
+/// Maps each ls short flag in a cluster like `lah` to its nuls equivalent,
+/// dropping the ones nuls has no counterpart for.
+fn translate_short_cluster<'a>(cluster: &str, iter: &mut impl Iterator<Item = &'a OsString>) -> String {
+    let mut mapped = String::new();
+    for (offset, flag) in cluster.char_indices() {
+        if IGNORED_VALUE_SHORTS.contains(&flag) {
+            if offset + flag.len_utf8() == cluster.len() {
+                iter.next();
+            }
+            break;
+        }
+        let nuls_flag = match flag {
+            'a' | 'A' => Some('a'),
+            'l' => Some('l'),
+            't' => Some('t'),
+            'S' => Some('S'),
+            'r' => Some('r'),
+            'L' => Some('L'),
+            '1' => Some('1'),
+            'm' => Some('m'),
+            'd' => Some('d'),
+            _ => None,
+        };
+        if let Some(nuls_flag) = nuls_flag.filter(|f| !mapped.contains(*f)) {
+            mapped.push(nuls_flag);
+        }
+    }
+    mapped
+}
+
+fn map_color(when: &str) -> &'static str {
+    match when {
+        "never" | "no" | "none" => "never",
+        "auto" | "tty" | "if-tty" => "auto",
+        _ => "always",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    fn translate(args: &[&str]) -> Vec<String> {
+        translate_args(args.iter().map(OsString::from))
+            .into_iter()
+            .map(|arg| arg.into_string().expect("utf-8"))
+            .collect()
+    }
+
+    #[test]
+    fn untouched_without_compat() {
+        assert_eq!(translate(&["nuls", "-g", "src"]), ["nuls", "-g", "src"]);
+    }
+
+    #[test]
+    fn argv0_ls_maps_common_flags() {
+        assert_eq!(
+            translate(&["/usr/local/bin/ls", "-lahS", "-R", "--color", "src"]),
+            ["/usr/local/bin/ls", "--compat=ls", "list", "-laS", "--color=always", "src"]
+        );
+        assert_eq!(
+            translate(&["ls", "-1A", "--format=single-column"]),
+            ["ls", "--compat=ls", "list", "-1a", "-1"]
+        );
+    }
+
+    #[test]
+    fn compat_flag_drops_unsupported_and_their_values() {
+        assert_eq!(
+            translate(&["nuls", "--compat", "ls", "-g", "--time-style", "long-iso", "-I", "*.o", "-Fi", "x"]),
+            ["nuls", "--compat=ls", "list", "x"]
+        );
+        assert_eq!(
+            translate(&["nuls", "--compat=ls", "--sort=time", "--color=never", "--almost-all"]),
+            ["nuls", "--compat=ls", "list", "-t", "--color=never", "-a"]
+        );
+    }
+
+    #[test]
+    fn nuls_long_flags_and_double_dash_pass_through() {
+        assert_eq!(
+            translate(&["ls", "--git", "--lang", "de", "--", "-weird-name"]),
+            ["ls", "--compat=ls", "list", "--git", "--lang", "de", "--", "-weird-name"]
+        );
+    }
+
+    #[test]
+    fn subcommand_names_are_listed_as_paths() {
+        let args = translate_args(["ls", "stat"].map(OsString::from));
+        let cli = Cli::try_parse_from(args).expect("parse ok");
+        assert!(matches!(cli.command, Some(crate::Commands::List(list)) if list.path.as_os_str() == "stat"));
+    }
+
+    #[test]
+    fn translated_args_parse() {
+        let args = translate_args(["ls", "-lahtr", "--color=auto", "/tmp"].map(OsString::from));
+        let cli = Cli::try_parse_from(args).expect("parse ok");
+        let Some(crate::Commands::List(list)) = cli.command else {
+            panic!("compat mode should run the list subcommand");
+        };
+        assert!(list.include_hidden && list.sort_modified && list.reverse);
+        assert_eq!(list.path, std::path::PathBuf::from("/tmp"));
+    }
+}
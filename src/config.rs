@@ -0,0 +1,470 @@
+//! Optional user configuration, read from `$NULS_CONFIG` or
+//! `$XDG_CONFIG_HOME/nuls/config.toml` (falling back to `~/.config`).
+
+use crate::GitMode;
+use crate::i18n::Lang;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Default for `--git` when the flag is not given.
+    pub git: Option<GitMode>,
+    /// Record each listed directory for `--history`.
+    pub history: bool,
+    pub recency: RecencyConfig,
+    pub headers: HeaderConfig,
+    pub table: TableConfig,
+    /// Flag bundles run as `nuls +NAME`, written as on a command line.
+    pub alias: BTreeMap<String, String>,
+    /// Default flags for listings of a directory or anything beneath it,
+    /// keyed by path (`~/` allowed).
+    pub directories: BTreeMap<String, String>,
+}
+
+/// Upper bounds (exclusive) of each recency bucket, as durations like `"90s"`,
+/// `"1h"`, or `"2w"`. Omitted keys keep their defaults.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RecencyConfig {
+    pub just_now: Option<String>,
+    pub seconds: Option<String>,
+    pub minutes: Option<String>,
+    pub hours: Option<String>,
+    pub days: Option<String>,
+    pub weeks: Option<String>,
+    pub months: Option<String>,
+}
+
+/// How the table fits a narrow terminal.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TableConfig {
+    /// Column keys in the order they give way: `modified` switches to the
+    /// compact time style, `name` is cut short, and any other column is
+    /// dropped. `None` keeps the built-in order.
+    pub shrink: Option<Vec<String>>,
+    /// Alignment per column key; numeric columns default to `right`, the
+    /// rest to `left`.
+    pub align: BTreeMap<String, ColumnAlign>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ColumnAlign {
+    Left,
+    Right,
+    Center,
+}
+
+/// Column header overrides: `names` replaces the built-in labels, each
+/// `translations.<lang>` table replaces them for that language only, and
+/// `case` is applied last.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HeaderConfig {
+    pub case: HeaderCase,
+    pub names: HeaderNames,
+    pub translations: BTreeMap<String, HeaderNames>,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum HeaderCase {
+    #[default]
+    AsIs,
+    Lower,
+    Upper,
+    Title,
+}
+
+/// Replacement text for each column header, keyed like the columns themselves.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HeaderNames {
+    pub index: Option<String>,
+    pub name: Option<String>,
+    pub rename: Option<String>,
+    #[serde(rename = "type")]
+    pub kind: Option<String>,
+    pub size: Option<String>,
+    pub delta: Option<String>,
+    pub disk: Option<String>,
+    pub modified: Option<String>,
+    pub entries: Option<String>,
+    pub access: Option<String>,
+    pub author: Option<String>,
+    pub open_by: Option<String>,
+    pub note: Option<String>,
+}
+
+impl HeaderNames {
+    fn get(&self, key: &str) -> Option<&str> {
+        let value = match key {
+            "index" => &self.index,
+            "name" => &self.name,
+            "rename" => &self.rename,
+            "type" => &self.kind,
+            "size" => &self.size,
+            "delta" => &self.delta,
+            "disk" => &self.disk,
+            "modified" => &self.modified,
+            "entries" => &self.entries,
+            "access" => &self.access,
+            "author" => &self.author,
+            "open_by" => &self.open_by,
+            "note" => &self.note,
+            _ => &None,
+        };
+        value.as_deref()
+    }
+}
+
+impl HeaderConfig {
+    /// The header for the column `key` in `lang`, falling back to `default`
+    /// (the built-in label for that language).
+    pub fn label(&self, key: &str, lang: Lang, default: &str) -> String {
+        let translated = self
+            .translations
+            .iter()
+            .filter(|(code, _)| Lang::from_code(code) == Some(lang))
+            .find_map(|(_, names)| names.get(key));
+        let text = translated.or_else(|| self.names.get(key)).unwrap_or(default);
+        match self.case {
+            HeaderCase::AsIs => text.to_string(),
+            HeaderCase::Lower => text.to_lowercase(),
+            HeaderCase::Upper => text.to_uppercase(),
+            HeaderCase::Title => title_case(text),
+        }
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        match self.translations.keys().find(|code| Lang::from_code(code).is_none()) {
+            Some(code) => Err(format!("headers.translations: unknown language '{code}'")),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Uppercases the first letter of each space-separated word and lowercases the rest.
+fn title_case(text: &str) -> String {
+    text.split(' ')
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars.flat_map(char::to_lowercase)).collect(),
+                None => String::new(),
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// The `NAME` of a `+NAME` argument.
+fn alias_name(arg: &str) -> Option<&str> {
+    arg.strip_prefix('+').filter(|name| !name.is_empty())
+}
+
+/// Whether any argument after the program name looks like `+NAME`, so the
+/// config only has to be read early for invocations that use an alias.
+pub fn uses_alias(args: &[OsString]) -> bool {
+    args.iter()
+        .skip(1)
+        .take_while(|arg| *arg != "--")
+        .any(|arg| arg.to_str().and_then(alias_name).is_some())
+}
+
+/// The words of `$NULS_DEFAULT_ARGS`, split like an alias; empty when unset.
+pub fn default_args() -> Result<Vec<String>, String> {
+    match env::var("NULS_DEFAULT_ARGS") {
+        Ok(text) => split_words(&text).map_err(|err| format!("NULS_DEFAULT_ARGS: {err}")),
+        Err(env::VarError::NotPresent) => Ok(Vec::new()),
+        Err(env::VarError::NotUnicode(_)) => Err("NULS_DEFAULT_ARGS is not valid UTF-8".to_string()),
+    }
+}
+
+/// Splits flags the way a shell would for plain words: on whitespace, with
+/// single or double quotes keeping a word together (`--annotate "wc -l"`).
+fn split_words(text: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut quote = None;
+    for c in text.chars() {
+        match (quote, c) {
+            (Some(open), c) if c == open => quote = None,
+            (Some(_), c) => word.get_or_insert_default().push(c),
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                word.get_or_insert_default();
+            }
+            (None, c) if c.is_whitespace() => words.extend(word.take()),
+            (None, c) => word.get_or_insert_default().push(c),
+        }
+    }
+    if let Some(open) = quote {
+        return Err(format!("unterminated {open} quote"));
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+/// Second thresholds behind the `Recency` buckets used to color the modified column.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RecencyThresholds {
+    pub just_now: u64,
+    pub seconds: u64,
+    pub minutes: u64,
+    pub hours: u64,
+    pub days: u64,
+    pub weeks: u64,
+    pub months: u64,
+}
+
+impl Default for RecencyThresholds {
+    fn default() -> Self {
+        RecencyThresholds {
+            just_now: 5,
+            seconds: 60,
+            minutes: 3_600,
+            hours: 86_400,
+            days: 604_800,
+            weeks: 2_629_746,
+            months: 31_557_600,
+        }
+    }
+}
+
+impl Config {
+    pub fn load() -> Result<Config, String> {
+        let (path, required) = match env::var_os("NULS_CONFIG") {
+            Some(path) => (PathBuf::from(path), true),
+            None => match default_path() {
+                Some(path) => (path, false),
+                None => return Ok(Config::default()),
+            },
+        };
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(err) if !required && err.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(Config::default());
+            }
+            Err(err) => return Err(format!("cannot read config {}: {err}", path.display())),
+        };
+        Config::parse(&text).map_err(|err| format!("invalid config {}: {err}", path.display()))
+    }
+
+    pub fn parse(text: &str) -> Result<Config, String> {
+        let config: Config = toml::from_str(text).map_err(|err| err.message().to_string())?;
+        config.recency_thresholds()?;
+        config.headers.validate()?;
+        for (name, flags) in &config.alias {
+            split_words(flags).map_err(|err| format!("alias.{name}: {err}"))?;
+        }
+        for (prefix, flags) in &config.directories {
+            split_words(flags).map_err(|err| format!("directories.\"{prefix}\": {err}"))?;
+        }
+        Ok(config)
+    }
+
+    /// The `[directories]` flags for listing `dir` (absolute and resolved):
+    /// those of every entry whose path holds `dir`, the shortest path first
+    /// so a deeper entry's flags come later and win.
+    pub fn directory_flags(&self, dir: &Path, home: Option<&Path>) -> Vec<String> {
+        let mut matching: Vec<(PathBuf, &String)> = self
+            .directories
+            .iter()
+            .filter_map(|(prefix, flags)| {
+                let prefix = if prefix == "~" {
+                    home?.to_path_buf()
+                } else if let Some(rest) = prefix.strip_prefix("~/").or_else(|| prefix.strip_prefix("~\\")) {
+                    home?.join(rest)
+                } else {
+                    PathBuf::from(prefix)
+                };
+                let prefix = prefix.canonicalize().unwrap_or(prefix);
+                dir.starts_with(&prefix).then_some((prefix, flags))
+            })
+            .collect();
+        matching.sort_by_key(|(prefix, _)| prefix.components().count());
+        // Validated by `parse`.
+        matching.into_iter().flat_map(|(_, flags)| split_words(flags).unwrap_or_default()).collect()
+    }
+
+    /// Replaces each `+NAME` argument before any `--` with the words of
+    /// `alias.NAME`. Aliases do not nest: a `+word` inside one is passed on
+    /// as is.
+    pub fn expand_aliases(&self, args: Vec<OsString>) -> Result<Vec<OsString>, String> {
+        let mut expanded = Vec::with_capacity(args.len());
+        let mut args = args.into_iter();
+        expanded.extend(args.next());
+        while let Some(arg) = args.next() {
+            if arg == "--" {
+                expanded.push(arg);
+                expanded.extend(args.by_ref());
+                break;
+            }
+            match arg.to_str().and_then(alias_name) {
+                Some(name) => {
+                    let flags = self.alias.get(name).ok_or_else(|| {
+                        format!("unknown alias '+{name}' (define it as alias.{name} in the config, or write ./+{name})")
+                    })?;
+                    expanded.extend(split_words(flags)?.into_iter().map(OsString::from));
+                }
+                None => expanded.push(arg),
+            }
+        }
+        Ok(expanded)
+    }
+
+    pub fn recency_thresholds(&self) -> Result<RecencyThresholds, String> {
+        let defaults = RecencyThresholds::default();
+        let pick = |key: &str, value: &Option<String>, default: u64| match value {
+            Some(text) => parse_duration(text).map_err(|err| format!("recency.{key}: {err}")),
+            None => Ok(default),
+        };
+        let recency = &self.recency;
+        let thresholds = RecencyThresholds {
+            just_now: pick("just_now", &recency.just_now, defaults.just_now)?,
+            seconds: pick("seconds", &recency.seconds, defaults.seconds)?,
+            minutes: pick("minutes", &recency.minutes, defaults.minutes)?,
+            hours: pick("hours", &recency.hours, defaults.hours)?,
+            days: pick("days", &recency.days, defaults.days)?,
+            weeks: pick("weeks", &recency.weeks, defaults.weeks)?,
+            months: pick("months", &recency.months, defaults.months)?,
+        };
+        let ordered = [
+            thresholds.just_now,
+            thresholds.seconds,
+            thresholds.minutes,
+            thresholds.hours,
+            thresholds.days,
+            thresholds.weeks,
+            thresholds.months,
+        ];
+        if ordered.windows(2).any(|pair| pair[0] > pair[1]) {
+            return Err("recency thresholds must not decrease from just_now to months".to_string());
+        }
+        Ok(thresholds)
+    }
+}
+
+fn default_path() -> Option<PathBuf> {
+    let base = env::var_os("XDG_CONFIG_HOME")
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(base.join("nuls").join("config.toml"))
+}
+
+/// Parses durations like `45`, `90s`, `5m`, `1h`, `2d`, `3w`, `6mo`, or `1y` into seconds.
+pub fn parse_duration(text: &str) -> Result<u64, String> {
+    let trimmed = text.trim();
+    let split = trimmed
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(trimmed.len());
+    let (digits, unit) = trimmed.split_at(split);
+    let value = digits
+        .parse::<u64>()
+        .map_err(|_| format!("invalid duration '{text}'"))?;
+    let scale = match unit.trim() {
+        "" | "s" => 1,
+        "m" | "min" => 60,
+        "h" => 3_600,
+        "d" => 86_400,
+        "w" => 604_800,
+        "mo" => 2_629_746,
+        "y" => 31_557_600,
+        other => return Err(format!("unknown duration unit '{other}' in '{text}' (use s, m, h, d, w, mo, y)")),
+    };
+    Ok(value * scale)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn durations_parse_with_units() {
+        assert_eq!(parse_duration("45"), Ok(45));
+        assert_eq!(parse_duration("90s"), Ok(90));
+        assert_eq!(parse_duration("5m"), Ok(300));
+        assert_eq!(parse_duration("1h"), Ok(3_600));
+        assert_eq!(parse_duration("2w"), Ok(1_209_600));
+        assert_eq!(parse_duration("1y"), Ok(31_557_600));
+        assert!(parse_duration("soon").is_err());
+        assert!(parse_duration("3 fortnights").is_err());
+    }
+
+    #[test]
+    fn aliases_expand_in_place_before_the_separator() {
+        let config = Config::parse(
+            "[alias]\nwork = \"-ag --sort modified\"\nnotes = \"--annotate 'wc -l' --hide-junk\"\n",
+        )
+        .expect("valid config");
+        let args = |words: &[&str]| words.iter().map(OsString::from).collect::<Vec<_>>();
+        assert!(uses_alias(&args(&["nuls", "src", "+work"])));
+        assert!(!uses_alias(&args(&["+work", "--", "+work"])));
+        assert_eq!(
+            config.expand_aliases(args(&["nuls", "+work", "src", "+notes", "--", "+work"])),
+            Ok(args(&["nuls", "-ag", "--sort", "modified", "src", "--annotate", "wc -l", "--hide-junk", "--", "+work"]))
+        );
+        let unknown = config.expand_aliases(args(&["nuls", "+play"])).expect_err("unknown alias");
+        assert!(unknown.contains("alias.play"), "{unknown}");
+        assert!(Config::parse("[alias]\nbad = \"--annotate 'wc\"\n").is_err());
+        assert_eq!(split_words(" -a  '' \"x y\"z "), Ok(vec!["-a".into(), String::new(), "x yz".into()]));
+    }
+
+    #[test]
+    fn directory_flags_apply_to_paths_beneath_shortest_first() {
+        let tmp = tempfile::tempdir().expect("tempdir");
+        let home = tmp.path().canonicalize().expect("canonical");
+        fs::create_dir_all(home.join("code/nuls/src")).expect("mkdir");
+        let config = Config::parse(&format!(
+            "[directories]\n\"~/code/nuls\" = \"--sort size\"\n\"~/code\" = \"-g --hide-junk\"\n\"{}\" = \"-a\"\n",
+            home.join("Downloads").display()
+        ))
+        .expect("valid config");
+        let flags = |dir: &str| config.directory_flags(&home.join(dir), Some(&home));
+        assert_eq!(flags("code/nuls/src"), ["-g", "--hide-junk", "--sort", "size"]);
+        assert_eq!(flags("code"), ["-g", "--hide-junk"]);
+        assert_eq!(flags("codex"), Vec::<String>::new());
+        assert_eq!(flags("Downloads/x"), ["-a"]);
+        assert!(config.directory_flags(&home.join("code"), None).is_empty());
+    }
+
+    #[test]
+    fn recency_overrides_keep_unset_defaults() {
+        let config = Config::parse("[recency]\nseconds = \"1h\"\nminutes = \"2h\"\n").expect("valid config");
+        let thresholds = config.recency_thresholds().expect("valid thresholds");
+        assert_eq!(thresholds.seconds, 3_600);
+        assert_eq!(thresholds.minutes, 7_200);
+        assert_eq!(thresholds.just_now, RecencyThresholds::default().just_now);
+    }
+
+    #[test]
+    fn headers_prefer_translations_then_names_then_defaults() {
+        let config = Config::parse(
+            "[headers]\ncase = \"title\"\n[headers.names]\nname = \"FILE\"\ntype = \"kind\"\n\
+             [headers.translations.de]\nname = \"datei\"\n",
+        )
+        .expect("valid config");
+        let headers = &config.headers;
+        assert_eq!(headers.label("name", Lang::En, "name"), "File");
+        assert_eq!(headers.label("name", Lang::De, "name"), "Datei");
+        assert_eq!(headers.label("type", Lang::De, "typ"), "Kind");
+        assert_eq!(headers.label("size", Lang::En, "size"), "Size");
+        assert!(Config::parse("[headers.translations.xx]\nname = \"n\"\n").is_err());
+        assert!(Config::parse("[headers]\ncase = \"shout\"\n").is_err());
+    }
+
+    #[test]
+    fn recency_rejects_decreasing_thresholds() {
+        assert!(Config::parse("[recency]\nseconds = \"2d\"\n").is_err());
+        assert!(Config::parse("[recency]\nsecs = \"1m\"\n").is_err());
+    }
+}
@@ -0,0 +1,160 @@
+//! `--delta`: how much each file grew or shrank since the last `--delta`
+//! listing of the same directory. Every such run records the sizes it saw in
+//! a TOML state file, so the next one has something to compare against.
+
+use crate::{EntryRow, SizeStyle, write_size_as};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// The file sizes one listing saw.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Snapshot {
+    /// When it was taken, in Unix seconds.
+    recorded: u64,
+    sizes: BTreeMap<String, u64>,
+}
+
+impl Snapshot {
+    /// The sizes of the files in `rows`. Directories are left out: their
+    /// size tracks the filesystem, not what they hold.
+    pub fn of(rows: &[EntryRow], now: SystemTime) -> Snapshot {
+        Snapshot {
+            recorded: now.duration_since(UNIX_EPOCH).map_or(0, |since| since.as_secs()),
+            sizes: rows
+                .iter()
+                .filter(|row| !row.is_dir() && !row.stat_skipped && !row.not_checked_out)
+                .map(|row| (row.name_plain.clone(), row.size_bytes))
+                .collect(),
+        }
+    }
+
+    pub fn recorded(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(self.recorded)
+    }
+
+    /// How `row` changed since this snapshot; `None` for directories and
+    /// entries whose size was not read.
+    pub fn change(&self, row: &EntryRow) -> Option<SizeChange> {
+        if row.is_dir() || row.stat_skipped || row.not_checked_out {
+            return None;
+        }
+        Some(match self.sizes.get(&row.name_plain) {
+            Some(&before) => SizeChange::By(i128::from(row.size_bytes) - i128::from(before)),
+            None => SizeChange::New,
+        })
+    }
+
+    /// Files in this snapshot that `now` no longer has.
+    pub fn gone(&self, now: &Snapshot) -> usize {
+        self.sizes.keys().filter(|name| !now.sizes.contains_key(*name)).count()
+    }
+}
+
+/// One entry's change since the snapshot.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SizeChange {
+    By(i128),
+    /// Not in the snapshot.
+    New,
+}
+
+impl SizeChange {
+    /// `+1.5 KB`, `-12 B`, `new`, or nothing when the size held still.
+    pub fn text(self, style: SizeStyle) -> String {
+        match self {
+            SizeChange::By(0) => String::new(),
+            SizeChange::By(bytes) => {
+                let mut text = if bytes > 0 { "+" } else { "-" }.to_string();
+                write_size_as(&mut text, u64::try_from(bytes.unsigned_abs()).unwrap_or(u64::MAX), style);
+                text
+            }
+            SizeChange::New => "new".to_string(),
+        }
+    }
+}
+
+/// Snapshots keyed by directory, plus the file they were loaded from.
+#[derive(Debug, Default)]
+pub struct SizeHistory {
+    file: PathBuf,
+    dirs: BTreeMap<String, Snapshot>,
+}
+
+impl SizeHistory {
+    /// Reads `$NULS_SIZES`, or `$XDG_STATE_HOME/nuls/sizes.toml` (falling
+    /// back to `~/.local/state`); a missing file means no snapshots yet.
+    pub fn load() -> Result<SizeHistory, String> {
+        let file = state_path().ok_or("cannot locate the size snapshot file: set $HOME or $NULS_SIZES")?;
+        let dirs = match fs::read_to_string(&file) {
+            Ok(text) => toml::from_str(&text)
+                .map_err(|err| format!("invalid size snapshot file {}: {}", file.display(), err.message()))?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(err) => return Err(format!("cannot read size snapshot file {}: {err}", file.display())),
+        };
+        Ok(SizeHistory { file, dirs })
+    }
+
+    /// The last snapshot of `dir` (absolute).
+    pub fn get(&self, dir: &Path) -> Option<&Snapshot> {
+        self.dirs.get(&*dir.to_string_lossy())
+    }
+
+    /// Replaces the snapshot of `dir` with `snapshot` and writes the file.
+    pub fn record(&mut self, dir: &Path, snapshot: Snapshot) -> Result<(), String> {
+        self.dirs.insert(dir.to_string_lossy().into_owned(), snapshot);
+        if let Some(parent) = self.file.parent() {
+            fs::create_dir_all(parent).map_err(|err| format!("cannot create {}: {err}", parent.display()))?;
+        }
+        let text = toml::to_string(&self.dirs).map_err(|err| format!("cannot encode size snapshots: {err}"))?;
+        fs::write(&self.file, text)
+            .map_err(|err| format!("cannot write size snapshot file {}: {err}", self.file.display()))
+    }
+}
+
+fn state_path() -> Option<PathBuf> {
+    if let Some(path) = env::var_os("NULS_SIZES").filter(|value| !value.is_empty()) {
+        return Some(PathBuf::from(path));
+    }
+    let base = env::var_os("XDG_STATE_HOME")
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local").join("state")))?;
+    Some(base.join("nuls").join("sizes.toml"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::test_row;
+
+    #[test]
+    fn changes_compare_file_sizes_by_name() {
+        let now = UNIX_EPOCH + Duration::from_secs(1_000);
+        let mut rows = vec![
+            test_row("app.log", false, None),
+            test_row("cache", true, None),
+            test_row("db.sqlite", false, None),
+            test_row("old.txt", false, None),
+        ];
+        rows[0].size_bytes = 2_048;
+        rows[2].size_bytes = 10;
+        let before = Snapshot::of(&rows, now);
+        assert_eq!(before.recorded(), now);
+        assert_eq!(before.sizes.len(), 3);
+
+        rows[0].size_bytes = 3_584;
+        rows[2].size_bytes = 4;
+        rows[3].name_plain = "new.txt".to_string();
+        let changes: Vec<String> = rows
+            .iter()
+            .map(|row| before.change(row).map(|change| change.text(SizeStyle::default())).unwrap_or_default())
+            .collect();
+        assert_eq!(changes, ["+1.5 KB", "", "-6 B", "new"]);
+        assert_eq!(before.gone(&Snapshot::of(&rows, now)), 1);
+        assert_eq!(SizeChange::By(0).text(SizeStyle::default()), "");
+    }
+}
@@ -0,0 +1,146 @@
+//! Diagnostics on stderr, so stdout holds only the listing. Labels take the
+//! palette's colors when stderr is a terminal, whatever stdout is. `--quiet`
+//! drops warnings, notes, and spinners (errors still print); `--verbose`
+//! adds phase timings, and `--timings` a finer breakdown of where a listing
+//! spent its time.
+
+use crate::palette;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+static COLOR: AtomicBool = AtomicBool::new(false);
+static QUIET: AtomicBool = AtomicBool::new(false);
+static VERBOSE: AtomicBool = AtomicBool::new(false);
+
+pub fn set_color(enabled: bool) {
+    COLOR.store(enabled, Ordering::Relaxed);
+}
+
+pub fn set_level(quiet: bool, verbose: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+    VERBOSE.store(verbose, Ordering::Relaxed);
+}
+
+pub fn quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+fn label(text: &str, color: &str) -> String {
+    if COLOR.load(Ordering::Relaxed) {
+        format!("{color}{text}{}", palette::RESET)
+    } else {
+        text.to_string()
+    }
+}
+
+/// A failure that ends the run; printed even with `--quiet`.
+pub fn error(message: &str) {
+    eprintln!("{} {message}", label("error:", palette::WARN));
+}
+
+/// Something went wrong but the run carries on.
+pub fn warn(message: &str) {
+    if !quiet() {
+        eprintln!("{} {message}", label("warning:", palette::WARN));
+    }
+}
+
+/// Why the output looks the way it does, when that may surprise.
+pub fn note(message: &str) {
+    if !quiet() {
+        eprintln!("{} {message}", label("note:", palette::GIT_CLEAN));
+    }
+}
+
+/// What a long-running step is doing (`downloading ...`).
+#[cfg_attr(not(feature = "self-update"), allow(dead_code))]
+pub fn status(message: &str) {
+    if !quiet() {
+        eprintln!("{message}");
+    }
+}
+
+/// One `timing:` line of phase durations, with `--verbose`.
+pub fn timings(phases: &[(&str, Duration)]) {
+    if !VERBOSE.load(Ordering::Relaxed) {
+        return;
+    }
+    let phases: Vec<String> = phases
+        .iter()
+        .map(|(phase, elapsed)| format!("{phase} {:.1} ms", elapsed.as_secs_f64() * 1000.0))
+        .collect();
+    eprintln!("{} {}", label("timing:", palette::GIT_CLEAN), phases.join(", "));
+}
+
+/// A step of a listing that `--timings` reports on its own line.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Phase {
+    /// Opening directories and reading their entries.
+    ReadDir,
+    /// Metadata calls: `lstat`, `stat` through `-L` links, `--statx` batches.
+    Stat,
+    /// Finding the repository and running `git status` and `git ls-files -v`.
+    GitStatus,
+    /// `git diff --numstat` for the line counts.
+    Numstat,
+    Sort,
+    Render,
+}
+
+impl Phase {
+    const ALL: [Phase; 6] = [
+        Phase::ReadDir,
+        Phase::Stat,
+        Phase::GitStatus,
+        Phase::Numstat,
+        Phase::Sort,
+        Phase::Render,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            Phase::ReadDir => "read_dir",
+            Phase::Stat => "stat",
+            Phase::GitStatus => "git status",
+            Phase::Numstat => "numstat",
+            Phase::Sort => "sort",
+            Phase::Render => "render",
+        }
+    }
+}
+
+static TIMINGS: AtomicBool = AtomicBool::new(false);
+/// Nanoseconds spent in each phase, indexed like `Phase::ALL`.
+static SPENT: [AtomicU64; 6] = [const { AtomicU64::new(0) }; 6];
+
+pub fn set_timings(enabled: bool) {
+    TIMINGS.store(enabled, Ordering::Relaxed);
+}
+
+/// Runs `work`, adding the time it takes to `phase` when `--timings` is on.
+pub fn timed<T>(phase: Phase, work: impl FnOnce() -> T) -> T {
+    if !TIMINGS.load(Ordering::Relaxed) {
+        return work();
+    }
+    let started = Instant::now();
+    let result = work();
+    let nanos = u64::try_from(started.elapsed().as_nanos()).unwrap_or(u64::MAX);
+    SPENT[phase as usize].fetch_add(nanos, Ordering::Relaxed);
+    result
+}
+
+/// The `--timings` breakdown: one line per phase, then the `total` wall
+/// time, which also covers what no phase claims (filters, building rows).
+pub fn print_timings(total: Duration) {
+    if !TIMINGS.load(Ordering::Relaxed) {
+        return;
+    }
+    let ms = |duration: Duration| format!("{:>9.2} ms", duration.as_secs_f64() * 1000.0);
+    let mut out = format!("{}\n", label("timings:", palette::GIT_CLEAN));
+    for phase in Phase::ALL {
+        let spent = Duration::from_nanos(SPENT[phase as usize].load(Ordering::Relaxed));
+        out.push_str(&format!("  {:<10} {}\n", phase.label(), ms(spent)));
+    }
+    out.push_str(&format!("  {:<10} {}\n", "total", ms(total)));
+    eprint!("{out}");
+}
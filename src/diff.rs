@@ -0,0 +1,121 @@
+//! `nuls diff LEFT RIGHT`: which entries were added, removed, or changed
+//! between two directories, matched by name (one level, no content compare).
+
+use crate::{Align, CollectOptions, DiffArgs, EntryRow, Session, SizeStyle, collect_entries, palette, render_grid};
+use std::collections::BTreeMap;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Change {
+    Added,
+    Removed,
+    Changed,
+}
+
+impl Change {
+    fn marker(self) -> (&'static str, &'static str) {
+        match self {
+            Change::Added => ("+", palette::GIT_ADDED),
+            Change::Removed => ("-", palette::GIT_REMOVED),
+            Change::Changed => ("~", palette::GIT_DIRTY),
+        }
+    }
+}
+
+pub fn render_diff(args: &DiffArgs, session: &Session) -> Result<String, String> {
+    let options = CollectOptions::plain(args.include_hidden);
+    let left = by_name(collect_entries(&args.left, &options, None)?);
+    let right = by_name(collect_entries(&args.right, &options, None)?);
+    let changes = compare(&left, &right);
+    if changes.is_empty() {
+        return Ok(format!("{}\n", palette::paint("no differences", palette::GIT_CLEAN)));
+    }
+
+    let missing = || ("".to_string(), "".to_string());
+    let header = [
+        ("", Align::Left),
+        ("name", Align::Left),
+        (&args.left.display().to_string(), Align::Right),
+        (&args.right.display().to_string(), Align::Right),
+    ]
+    .map(|(text, align)| (text.to_string(), palette::paint(text, palette::HEADER), align));
+    let mut body = Vec::new();
+    for (name, change) in &changes {
+        let (marker, color) = change.marker();
+        let row = right.get(name).or_else(|| left.get(name)).expect("changed entry exists on one side");
+        let (name_plain, name_colored) = row.name_cell();
+        let side = |rows: &BTreeMap<String, EntryRow>| {
+            rows.get(name)
+                .map(|row| row.size_cell(session.strings, SizeStyle::default()))
+                .unwrap_or_else(missing)
+        };
+        let (left_plain, left_colored) = side(&left);
+        let (right_plain, right_colored) = side(&right);
+        body.push(vec![
+            (marker.to_string(), palette::paint(marker, color), Align::Left),
+            (name_plain, name_colored, Align::Left),
+            (left_plain, left_colored, Align::Right),
+            (right_plain, right_colored, Align::Right),
+        ]);
+    }
+    Ok(render_grid(&header, &body))
+}
+
+fn by_name(rows: Vec<EntryRow>) -> BTreeMap<String, EntryRow> {
+    rows.into_iter().map(|row| (row.name_plain.clone(), row)).collect()
+}
+
+/// Names present on only one side, plus those whose kind, size, or modified
+/// time differ. Directory sizes are ignored since they track the filesystem,
+/// not the contents.
+fn compare(left: &BTreeMap<String, EntryRow>, right: &BTreeMap<String, EntryRow>) -> Vec<(String, Change)> {
+    let mut names: Vec<&String> = left.keys().chain(right.keys()).collect();
+    names.sort();
+    names.dedup();
+    names
+        .into_iter()
+        .filter_map(|name| {
+            let change = match (left.get(name), right.get(name)) {
+                (None, Some(_)) => Change::Added,
+                (Some(_), None) => Change::Removed,
+                (Some(old), Some(new)) if old.is_dir() != new.is_dir() => Change::Changed,
+                (Some(old), Some(new)) if !old.is_dir() && old.size_bytes != new.size_bytes => Change::Changed,
+                (Some(old), Some(new)) if !old.is_dir() && old.modified_time != new.modified_time => Change::Changed,
+                _ => return None,
+            };
+            Some((name.clone(), change))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::test_row;
+    use std::time::{Duration, SystemTime};
+
+    #[test]
+    fn compare_reports_added_removed_and_changed() {
+        let t0 = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000);
+        let t1 = t0 + Duration::from_secs(60);
+        let left = by_name(vec![
+            test_row("same.txt", false, Some(t0)),
+            test_row("gone.txt", false, Some(t0)),
+            test_row("edited.txt", false, Some(t0)),
+            test_row("src", true, Some(t0)),
+        ]);
+        let right = by_name(vec![
+            test_row("same.txt", false, Some(t0)),
+            test_row("new.txt", false, Some(t1)),
+            test_row("edited.txt", false, Some(t1)),
+            test_row("src", true, Some(t1)),
+        ]);
+        assert_eq!(
+            compare(&left, &right),
+            vec![
+                ("edited.txt".to_string(), Change::Changed),
+                ("gone.txt".to_string(), Change::Removed),
+                ("new.txt".to_string(), Change::Added),
+            ]
+        );
+    }
+}
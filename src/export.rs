@@ -0,0 +1,174 @@
+//! `--json` and `--csv`: the listing as data, in the order the table would
+//! show it, with the ordering that produced it recorded alongside.
+
+use crate::{EntryRow, format_git, long_path};
+use chrono::{DateTime, SecondsFormat, Utc};
+use clap::ValueEnum;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// The ordering applied before export, as `--sort`/`--reverse`/`--tie-break`
+/// would spell it; see `SortSpec::meta`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct SortMeta {
+    pub key: String,
+    pub reverse: bool,
+    pub tie_break: String,
+    /// Directories came before files, whatever `reverse` says.
+    pub dirs_first: bool,
+    /// The names in the directory's `.nulsorder` came first.
+    pub explicit_order: bool,
+}
+
+/// How `value` is spelled on the command line.
+pub fn value_name(value: impl ValueEnum) -> String {
+    value
+        .to_possible_value()
+        .map(|value| value.get_name().to_string())
+        .unwrap_or_default()
+}
+
+/// One exported entry. Paths are always absolute, whatever `--full-path`
+/// and `--tilde` do to the displayed name.
+#[derive(Debug, Serialize)]
+struct Record<'a> {
+    name: &'a str,
+    path: String,
+    #[serde(rename = "type")]
+    kind: &'static str,
+    size: u64,
+    disk: Option<u64>,
+    /// RFC 3339 in UTC.
+    modified: Option<String>,
+    executable: bool,
+    entries: Option<u64>,
+    /// `clean`, or the `+added -deleted` / `dirty` / `+?` marker.
+    git: Option<String>,
+}
+
+#[derive(Serialize)]
+struct Listing<'a> {
+    path: String,
+    sort: SortMeta,
+    /// Rows dropped by `--limit` or `--recent`.
+    omitted: usize,
+    entries: Vec<Record<'a>>,
+}
+
+fn records<'a>(rows: &'a [EntryRow], path_of: &impl Fn(&EntryRow) -> PathBuf) -> Vec<Record<'a>> {
+    rows.iter()
+        .map(|row| Record {
+            name: &row.name_plain,
+            path: absolute(&path_of(row)).display().to_string(),
+            kind: if row.is_dir() { "dir" } else { "file" },
+            size: row.size_bytes,
+            disk: row.disk_bytes,
+            modified: row
+                .modified_time
+                .map(|time| DateTime::<Utc>::from(time).to_rfc3339_opts(SecondsFormat::Secs, true)),
+            executable: row.is_executable && !row.is_dir(),
+            entries: row.child_count,
+            git: row.git.as_ref().and_then(format_git).map(|(plain, _)| {
+                if plain.is_empty() {
+                    "clean".to_string()
+                } else {
+                    plain.trim_start_matches('(').trim_end_matches(')').to_string()
+                }
+            }),
+        })
+        .collect()
+}
+
+/// Resolves `.`/`..` and symlinked parents but keeps the entry's own name,
+/// so a symlink is exported where it is rather than where it points.
+fn absolute(path: &Path) -> PathBuf {
+    let abs = match path.file_name() {
+        Some(name) => {
+            let parent = path.parent().filter(|parent| !parent.as_os_str().is_empty()).unwrap_or(Path::new("."));
+            parent.canonicalize().map_or_else(|_| path.to_path_buf(), |parent| parent.join(name))
+        }
+        // `.`, `..` and roots have no name of their own to keep.
+        None => path.canonicalize().unwrap_or_else(|_| path.to_path_buf()),
+    };
+    long_path::friendly(&abs).into_owned()
+}
+
+/// A pretty-printed object: `path`, `sort`, `omitted`, then `entries`.
+pub fn render_json(
+    base: &Path,
+    rows: &[EntryRow],
+    sort: SortMeta,
+    omitted: usize,
+    path_of: impl Fn(&EntryRow) -> PathBuf,
+) -> Result<String, String> {
+    let listing = Listing {
+        path: absolute(base).display().to_string(),
+        sort,
+        omitted,
+        entries: records(rows, &path_of),
+    };
+    serde_json::to_string_pretty(&listing)
+        .map(|text| format!("{text}\n"))
+        .map_err(|err| format!("cannot encode JSON: {err}"))
+}
+
+/// RFC 4180 rows under a header line, preceded by one `# sort=...` comment
+/// line (most CSV readers can skip it, e.g. pandas' `comment="#"`).
+pub fn render_csv(rows: &[EntryRow], sort: SortMeta, path_of: impl Fn(&EntryRow) -> PathBuf) -> String {
+    let mut out = format!(
+        "# sort={} reverse={} tie-break={} dirs-first={} explicit-order={}\n",
+        sort.key, sort.reverse, sort.tie_break, sort.dirs_first, sort.explicit_order
+    );
+    out.push_str("name,path,type,size,disk,modified,executable,entries,git\n");
+    let optional = |value: Option<u64>| value.map(|value| value.to_string()).unwrap_or_default();
+    for record in records(rows, &path_of) {
+        let cells = [
+            record.name.to_string(),
+            record.path,
+            record.kind.to_string(),
+            record.size.to_string(),
+            optional(record.disk),
+            record.modified.unwrap_or_default(),
+            record.executable.to_string(),
+            optional(record.entries),
+            record.git.unwrap_or_default(),
+        ];
+        let quoted: Vec<String> = cells.iter().map(|cell| csv_field(cell)).collect();
+        out.push_str(&quoted.join(","));
+        out.push('\n');
+    }
+    out
+}
+
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sort::{SortOrder, SortSpec};
+    use crate::{SortKey, TieBreak};
+
+    #[test]
+    fn csv_fields_are_quoted_only_when_needed() {
+        assert_eq!(csv_field("notes.txt"), "notes.txt");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+
+    #[test]
+    fn sort_meta_uses_flag_spellings() {
+        let meta = SortSpec::by(SortKey::Modified).reverse(true).tie_break(TieBreak::None).meta();
+        assert_eq!((meta.key.as_str(), meta.reverse, meta.tie_break.as_str()), ("modified", true, "none"));
+        assert!(!meta.dirs_first && !meta.explicit_order);
+        assert!(SortSpec::by(SortKey::Modified).dirs_first(true).meta().dirs_first);
+        let order = SortOrder::parse("size:desc,name").expect("parse ok");
+        assert_eq!(order.spec().meta().key, "size:desc,name");
+        assert!(SortSpec::by(SortKey::Name).explicit_order(&["b".to_string()]).meta().explicit_order);
+    }
+}
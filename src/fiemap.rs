@@ -0,0 +1,149 @@
+//! `--compressed-size` on btrfs, where `st_blocks` counts a compressed
+//! extent at its uncompressed size. The file's extents come from the
+//! `FS_IOC_FIEMAP` ioctl; an extent flagged `FIEMAP_EXTENT_ENCODED`
+//! (compressed) is counted by the room it takes on disk, which is the gap
+//! to the next extent's physical start when btrfs wrote them back to back
+//! (as it does for a file written in one go), and its logical length
+//! otherwise. FIEMAP has no field for an encoded extent's physical length,
+//! so the result is an upper bound, never more than `st_blocks` would say.
+
+use rustix::fs::{Mode, OFlags};
+use rustix::ioctl::{Updater, ioctl, opcode};
+use std::path::Path;
+
+/// `statfs.f_type` of a btrfs filesystem.
+const BTRFS_SUPER_MAGIC: i64 = 0x9123_683e;
+
+const FIEMAP_EXTENT_LAST: u32 = 0x1;
+const FIEMAP_EXTENT_ENCODED: u32 = 0x8;
+const FIEMAP_EXTENT_DATA_INLINE: u32 = 0x200;
+
+/// Extents asked for per ioctl.
+const BATCH: usize = 128;
+
+/// `struct fiemap_extent`.
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+struct RawExtent {
+    logical: u64,
+    physical: u64,
+    length: u64,
+    reserved64: [u64; 2],
+    flags: u32,
+    reserved: [u32; 3],
+}
+
+/// `struct fiemap` without its extents; the ioctl number is sized by it.
+#[repr(C)]
+#[derive(Default)]
+struct Header {
+    start: u64,
+    length: u64,
+    flags: u32,
+    mapped_extents: u32,
+    extent_count: u32,
+    reserved: u32,
+}
+
+/// `struct fiemap` with room for a batch of extents.
+#[repr(C)]
+struct Request {
+    header: Header,
+    extents: [RawExtent; BATCH],
+}
+
+const FS_IOC_FIEMAP: rustix::ioctl::Opcode = opcode::read_write::<Header>(b'f', 11);
+
+/// One extent of a file, as FIEMAP maps it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Extent {
+    pub physical: u64,
+    pub length: u64,
+    pub encoded: bool,
+}
+
+/// Bytes `path` takes on disk if it is a regular file on btrfs; `None`
+/// elsewhere, or when the filesystem will not map it, so the caller falls
+/// back to `st_blocks`.
+pub fn btrfs_disk_size(path: &Path) -> Option<u64> {
+    let flags = OFlags::RDONLY | OFlags::NONBLOCK | OFlags::NOCTTY | OFlags::CLOEXEC;
+    let fd = rustix::fs::open(path, flags, Mode::empty()).ok()?;
+    if rustix::fs::fstatfs(&fd).ok()?.f_type as i64 != BTRFS_SUPER_MAGIC {
+        return None;
+    }
+    let mut extents = Vec::new();
+    let mut request = Request { header: Header::default(), extents: [RawExtent::default(); BATCH] };
+    let mut start = 0;
+    loop {
+        request.header = Header { start, length: u64::MAX - start, extent_count: BATCH as u32, ..Header::default() };
+        // SAFETY: `Request` is `struct fiemap` followed by `extent_count`
+        // extents, which is all the kernel writes.
+        unsafe { ioctl(&fd, Updater::<FS_IOC_FIEMAP, Request>::new(&mut request)) }.ok()?;
+        let mapped = &request.extents[..(request.header.mapped_extents as usize).min(BATCH)];
+        let Some(last) = mapped.last() else { break };
+        extents.extend(mapped.iter().map(|raw| Extent {
+            physical: raw.physical,
+            length: raw.length,
+            // Inline extents live in the metadata tree; their physical
+            // address says nothing about their neighbours.
+            encoded: raw.flags & FIEMAP_EXTENT_ENCODED != 0 && raw.flags & FIEMAP_EXTENT_DATA_INLINE == 0,
+        }));
+        if last.flags & FIEMAP_EXTENT_LAST != 0 {
+            break;
+        }
+        start = last.logical + last.length;
+    }
+    Some(disk_size(&extents))
+}
+
+/// The room `extents` take on disk: plain extents at their length, encoded
+/// ones at the gap to the next extent that starts inside them, and every
+/// physical extent once however many times the file refers to it.
+pub fn disk_size(extents: &[Extent]) -> u64 {
+    let mut by_disk: Vec<Extent> = extents.to_vec();
+    by_disk.sort_by_key(|extent| (extent.physical, std::cmp::Reverse(extent.length)));
+    by_disk.dedup_by_key(|extent| extent.physical);
+    by_disk
+        .iter()
+        .enumerate()
+        .map(|(index, extent)| match by_disk.get(index + 1) {
+            Some(next) if extent.encoded && next.physical < extent.physical + extent.length => {
+                next.physical - extent.physical
+            }
+            _ => extent.length,
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn extent(physical: u64, length: u64, encoded: bool) -> Extent {
+        Extent { physical, length, encoded }
+    }
+
+    #[test]
+    fn plain_extents_count_their_length() {
+        assert_eq!(disk_size(&[extent(0, 8192, false), extent(1 << 20, 4096, false)]), 12288);
+    }
+
+    #[test]
+    fn encoded_extents_written_back_to_back_count_the_gap() {
+        // Three 128 KiB compressed extents packed into 8 + 12 + 4 KiB, then
+        // an unrelated extent far away.
+        let extents = [
+            extent(1 << 20, 128 << 10, true),
+            extent((1 << 20) + 8192, 128 << 10, true),
+            extent((1 << 20) + 20480, 128 << 10, true),
+            extent(1 << 30, 128 << 10, true),
+        ];
+        assert_eq!(disk_size(&extents), 8192 + 12288 + (128 << 10) + (128 << 10));
+    }
+
+    #[test]
+    fn a_shared_physical_extent_counts_once() {
+        let extents = [extent(4096, 65536, false), extent(4096, 65536, false), extent(4096, 8192, false)];
+        assert_eq!(disk_size(&extents), 65536);
+    }
+}
@@ -0,0 +1,100 @@
+//! `--git-age` and `--git-author`: the last commit to touch each listed
+//! entry, read from one streamed `git log --name-only` walk instead of a
+//! `git log` per entry.
+
+use crate::GitEnv;
+use std::collections::{HashMap, HashSet};
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::process::Stdio;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+/// When, and by whom, an entry was last committed to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LastCommit {
+    pub time: SystemTime,
+    pub author: String,
+}
+
+/// The newest commit touching each of `names` (entries directly in `dir`;
+/// for a directory, anything beneath it). Names git never committed are
+/// left out, as is everything when `dir` is not in a work tree. A walk
+/// still running at the git deadline is abandoned as an error.
+pub fn last_commits(
+    git: &GitEnv,
+    dir: &Path,
+    names: &HashSet<&str>,
+) -> Result<HashMap<String, LastCommit>, String> {
+    let mut found = HashMap::new();
+    if names.is_empty() {
+        return Ok(found);
+    }
+    let child = git
+        .command(dir)
+        .args(["-c", "core.quotePath=false", "log", "--format=%x00%ct%x00%an", "--name-only", "--relative"])
+        .args(["--", "."])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn();
+    let Ok(mut child) = child else {
+        return Ok(found);
+    };
+    let Some(stdout) = child.stdout.take() else {
+        return Ok(found);
+    };
+    // Lines arrive over a channel so a silent, stuck git can be given up on.
+    let (sender, lines) = mpsc::channel();
+    thread::spawn(move || {
+        for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+            if sender.send(line).is_err() {
+                break;
+            }
+        }
+    });
+    let deadline = Instant::now() + git.options.timeout;
+    let mut timed_out = false;
+    let mut commit = None;
+    loop {
+        let line = match lines.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+            Ok(line) => line,
+            Err(RecvTimeoutError::Timeout) => {
+                timed_out = true;
+                break;
+            }
+            Err(RecvTimeoutError::Disconnected) => break,
+        };
+        if let Some(header) = line.strip_prefix('\0') {
+            commit = parse_header(header);
+            continue;
+        }
+        let (Some(commit), Some(first)) = (&commit, line.split('/').next().filter(|name| !name.is_empty())) else {
+            continue;
+        };
+        // The log is newest first, so the first sighting of a name wins.
+        if names.contains(first) && !found.contains_key(first) {
+            found.insert(first.to_string(), commit.clone());
+            if found.len() == names.len() {
+                break;
+            }
+        }
+    }
+    // Stop the walk early once every name is dated; history can be long.
+    let _ = child.kill();
+    let _ = child.wait();
+    if timed_out {
+        return Err(format!("git log: timed out after {}s", git.options.timeout.as_secs()));
+    }
+    Ok(found)
+}
+
+/// `<unix time>\0<author>`, the `--format` line minus its leading NUL.
+fn parse_header(header: &str) -> Option<LastCommit> {
+    let (stamp, author) = header.split_once('\0')?;
+    let secs = stamp.trim().parse::<u64>().ok()?;
+    Some(LastCommit {
+        time: SystemTime::UNIX_EPOCH + Duration::from_secs(secs),
+        author: author.to_string(),
+    })
+}
@@ -0,0 +1,183 @@
+//! Directories nuls has listed, for `nuls --history`: with `history = true`
+//! in the config, every directory listing bumps a visit count in a TOML state
+//! file, and `--history` ranks them by frecency (visits weighted by how
+//! recently they happened), or prints the best match for a query so a shell
+//! function can jump there.
+
+use crate::{Align, Session, format_relative_time, long_path, palette, recency_color, render_grid};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Directories kept in the file; the lowest-ranked go first past this.
+const KEPT: usize = 500;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+struct Visits {
+    count: u64,
+    /// Last visit, in Unix seconds.
+    last: u64,
+}
+
+impl Visits {
+    /// The visit count, weighted four times while the last visit is under an
+    /// hour old, twice under a day, half under a week, and a quarter after.
+    fn frecency(self, now: u64) -> f64 {
+        let age = now.saturating_sub(self.last);
+        let weight = match age {
+            _ if age < 3_600 => 4.0,
+            _ if age < 86_400 => 2.0,
+            _ if age < 7 * 86_400 => 0.5,
+            _ => 0.25,
+        };
+        self.count as f64 * weight
+    }
+}
+
+/// Visits keyed by absolute directory, plus the file they were loaded from.
+#[derive(Debug, Default)]
+pub struct History {
+    file: PathBuf,
+    dirs: BTreeMap<String, Visits>,
+}
+
+impl History {
+    /// Reads `$NULS_HISTORY`, or `$XDG_STATE_HOME/nuls/history.toml`
+    /// (falling back to `~/.local/state`); a missing file means no history.
+    pub fn load() -> Result<History, String> {
+        let file = state_path().ok_or("cannot locate the history file: set $HOME or $NULS_HISTORY")?;
+        let dirs = match fs::read_to_string(&file) {
+            Ok(text) => toml::from_str(&text)
+                .map_err(|err| format!("invalid history file {}: {}", file.display(), err.message()))?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(err) => return Err(format!("cannot read history file {}: {err}", file.display())),
+        };
+        Ok(History { file, dirs })
+    }
+
+    /// Counts a listing of `dir` at `now` and writes the file.
+    pub fn record(&mut self, dir: &Path, now: SystemTime) -> Result<(), String> {
+        let dir = dir
+            .canonicalize()
+            .map_err(|err| format!("cannot canonicalize {}: {err}", dir.display()))?;
+        let now = unix_seconds(now);
+        let visits = self.dirs.entry(long_path::friendly(&dir).to_string_lossy().into_owned()).or_default();
+        visits.count += 1;
+        visits.last = now;
+        if self.dirs.len() > KEPT {
+            let ranked = self.ranked(now);
+            let dropped: Vec<String> = ranked[KEPT..].iter().map(|(dir, _)| dir.to_string()).collect();
+            for dir in dropped {
+                self.dirs.remove(&dir);
+            }
+        }
+        if let Some(parent) = self.file.parent() {
+            fs::create_dir_all(parent).map_err(|err| format!("cannot create {}: {err}", parent.display()))?;
+        }
+        let text = toml::to_string(&self.dirs).map_err(|err| format!("cannot encode the history: {err}"))?;
+        fs::write(&self.file, text).map_err(|err| format!("cannot write history file {}: {err}", self.file.display()))
+    }
+
+    /// Directories by frecency, highest first; ties go to the latest visit.
+    fn ranked(&self, now: u64) -> Vec<(&str, Visits)> {
+        let mut ranked: Vec<(&str, Visits)> = self.dirs.iter().map(|(dir, visits)| (dir.as_str(), *visits)).collect();
+        ranked.sort_by(|(_, a), (_, b)| {
+            b.frecency(now)
+                .total_cmp(&a.frecency(now))
+                .then(b.last.cmp(&a.last))
+        });
+        ranked
+    }
+
+    /// The highest-ranked directory that still exists and whose path holds
+    /// every word of `query`, ignoring case.
+    pub fn best_match(&self, query: &str, now: SystemTime) -> Option<&str> {
+        let words: Vec<String> = query.split_whitespace().map(str::to_lowercase).collect();
+        self.ranked(unix_seconds(now))
+            .into_iter()
+            .map(|(dir, _)| dir)
+            .find(|dir| {
+                let lower = dir.to_lowercase();
+                words.iter().all(|word| lower.contains(word.as_str())) && Path::new(dir).is_dir()
+            })
+    }
+
+    /// The ranked table for `nuls --history`; directories that are gone are
+    /// left out.
+    pub fn render(&self, session: &Session) -> String {
+        let ranked: Vec<(&str, Visits)> = self
+            .ranked(unix_seconds(session.now))
+            .into_iter()
+            .filter(|(dir, _)| Path::new(dir).is_dir())
+            .collect();
+        if ranked.is_empty() {
+            let hint = if self.dirs.is_empty() {
+                "no history yet (set `history = true` in the config to record listed directories)"
+            } else {
+                "no recorded directory exists any more"
+            };
+            return format!("{}\n", palette::paint(hint, palette::GIT_CLEAN));
+        }
+        let header = [
+            ("#", Align::Right),
+            ("directory", Align::Left),
+            ("visits", Align::Right),
+            ("last listed", Align::Left),
+        ]
+        .map(|(text, align)| (text.to_string(), palette::paint(text, palette::HEADER), align));
+        let body: Vec<Vec<(String, String, Align)>> = ranked
+            .into_iter()
+            .enumerate()
+            .map(|(idx, (dir, visits))| {
+                let last = UNIX_EPOCH + Duration::from_secs(visits.last);
+                let (when, recency) = format_relative_time(last, session.now, &session.recency, session.strings);
+                vec![
+                    (idx.to_string(), palette::paint(idx.to_string(), palette::INDEX), Align::Right),
+                    (dir.to_string(), palette::paint(dir, palette::DIR), Align::Left),
+                    (visits.count.to_string(), palette::paint(visits.count.to_string(), palette::SIZE), Align::Right),
+                    (when.clone(), palette::paint(when, recency_color(recency)), Align::Left),
+                ]
+            })
+            .collect();
+        render_grid(&header, &body)
+    }
+}
+
+fn unix_seconds(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map_or(0, |since| since.as_secs())
+}
+
+fn state_path() -> Option<PathBuf> {
+    if let Some(path) = env::var_os("NULS_HISTORY").filter(|value| !value.is_empty()) {
+        return Some(PathBuf::from(path));
+    }
+    let base = env::var_os("XDG_STATE_HOME")
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local").join("state")))?;
+    Some(base.join("nuls").join("history.toml"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frequent_and_recent_directories_rank_first() {
+        let now = 100 * 86_400;
+        let history = History {
+            file: PathBuf::new(),
+            dirs: BTreeMap::from([
+                ("/old/favorite".to_string(), Visits { count: 20, last: now - 30 * 86_400 }),
+                ("/today".to_string(), Visits { count: 3, last: now - 7_200 }),
+                ("/just/now".to_string(), Visits { count: 1, last: now - 60 }),
+                ("/last/week".to_string(), Visits { count: 3, last: now - 3 * 86_400 }),
+            ]),
+        };
+        let order: Vec<&str> = history.ranked(now).into_iter().map(|(dir, _)| dir).collect();
+        assert_eq!(order, ["/today", "/old/favorite", "/just/now", "/last/week"]);
+    }
+}
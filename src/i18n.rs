@@ -0,0 +1,324 @@
+//! User-facing labels and relative-time phrases, one table per language.
+
+use std::env;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Lang {
+    #[default]
+    En,
+    Es,
+    Pt,
+    De,
+    Fr,
+}
+
+impl Lang {
+    /// Parses codes like `pt`, `pt_BR`, or `de_DE.UTF-8`.
+    pub fn from_code(code: &str) -> Option<Lang> {
+        let base = code
+            .split(['_', '-', '.', '@'])
+            .next()
+            .unwrap_or("")
+            .to_ascii_lowercase();
+        match base.as_str() {
+            "en" | "c" | "posix" => Some(Lang::En),
+            "es" => Some(Lang::Es),
+            "pt" => Some(Lang::Pt),
+            "de" => Some(Lang::De),
+            "fr" => Some(Lang::Fr),
+            _ => None,
+        }
+    }
+
+    /// Picks the language from `LC_ALL`, `LC_MESSAGES`, then `LANG`, falling back to English.
+    pub fn detect() -> Lang {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|var| env::var(var).ok())
+            .find(|value| !value.is_empty())
+            .and_then(|value| Lang::from_code(&value))
+            .unwrap_or_default()
+    }
+
+    pub fn strings(self) -> &'static Strings {
+        match self {
+            Lang::En => &EN,
+            Lang::Es => &ES,
+            Lang::Pt => &PT,
+            Lang::De => &DE,
+            Lang::Fr => &FR,
+        }
+    }
+}
+
+pub fn parse_lang(value: &str) -> Result<Lang, String> {
+    Lang::from_code(value).ok_or_else(|| format!("unsupported language '{value}' (try en, es, pt, de, fr)"))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimeUnit {
+    Second,
+    Minute,
+    Hour,
+    Day,
+    Week,
+    Month,
+    Year,
+}
+
+#[derive(Debug)]
+pub struct Strings {
+    pub header_index: &'static str,
+    pub header_name: &'static str,
+    pub header_type: &'static str,
+    pub header_size: &'static str,
+    pub header_disk: &'static str,
+    pub header_modified: &'static str,
+    pub dir: &'static str,
+    pub file: &'static str,
+    pub just_now: &'static str,
+    pub unknown: &'static str,
+    /// Size cell for a symlink whose target cannot be read.
+    pub broken_link: &'static str,
+    /// Prefix and suffix wrapped around "N units" for past times.
+    pub ago: (&'static str, &'static str),
+    /// Prefix and suffix wrapped around "N units" for future times.
+    pub later: (&'static str, &'static str),
+    /// Abbreviated weekday names, Monday first.
+    pub weekdays: [&'static str; 7],
+    /// Singular and plural forms, indexed by `TimeUnit`.
+    pub units: [(&'static str, &'static str); 7],
+    /// `--group-by modified-day` headings for the most recent days.
+    pub today: &'static str,
+    pub yesterday: &'static str,
+    pub last_week: &'static str,
+    /// Full month names, January first, for older `--group-by` headings.
+    pub months: [&'static str; 12],
+}
+
+impl Strings {
+    pub fn unit(&self, unit: TimeUnit, value: u64) -> &'static str {
+        let (singular, plural) = self.units[unit as usize];
+        if value == 1 { singular } else { plural }
+    }
+
+    pub fn relative(&self, value: u64, unit: TimeUnit, past: bool) -> String {
+        let (prefix, suffix) = if past { self.ago } else { self.later };
+        format!("{prefix}{value} {}{suffix}", self.unit(unit, value))
+    }
+}
+
+pub static EN: Strings = Strings {
+    header_index: "#",
+    header_name: "name",
+    header_type: "type",
+    header_size: "size",
+    header_disk: "disk",
+    header_modified: "modified",
+    dir: "dir",
+    file: "file",
+    just_now: "just now",
+    unknown: "unknown",
+    broken_link: "broken",
+    ago: ("", " ago"),
+    later: ("in ", ""),
+    weekdays: ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"],
+    units: [
+        ("second", "seconds"),
+        ("minute", "minutes"),
+        ("hour", "hours"),
+        ("day", "days"),
+        ("week", "weeks"),
+        ("month", "months"),
+        ("year", "years"),
+    ],
+    today: "Today",
+    yesterday: "Yesterday",
+    last_week: "Last week",
+    months: [
+        "January",
+        "February",
+        "March",
+        "April",
+        "May",
+        "June",
+        "July",
+        "August",
+        "September",
+        "October",
+        "November",
+        "December",
+    ],
+};
+
+pub static ES: Strings = Strings {
+    header_index: "#",
+    header_name: "nombre",
+    header_type: "tipo",
+    header_size: "tamaño",
+    header_disk: "disco",
+    header_modified: "modificado",
+    dir: "dir",
+    file: "archivo",
+    just_now: "ahora mismo",
+    unknown: "desconocido",
+    broken_link: "rota",
+    ago: ("hace ", ""),
+    later: ("en ", ""),
+    weekdays: ["lun", "mar", "mié", "jue", "vie", "sáb", "dom"],
+    units: [
+        ("segundo", "segundos"),
+        ("minuto", "minutos"),
+        ("hora", "horas"),
+        ("día", "días"),
+        ("semana", "semanas"),
+        ("mes", "meses"),
+        ("año", "años"),
+    ],
+    today: "Hoy",
+    yesterday: "Ayer",
+    last_week: "La semana pasada",
+    months: [
+        "enero",
+        "febrero",
+        "marzo",
+        "abril",
+        "mayo",
+        "junio",
+        "julio",
+        "agosto",
+        "septiembre",
+        "octubre",
+        "noviembre",
+        "diciembre",
+    ],
+};
+
+pub static PT: Strings = Strings {
+    header_index: "#",
+    header_name: "nome",
+    header_type: "tipo",
+    header_size: "tamanho",
+    header_disk: "disco",
+    header_modified: "modificado",
+    dir: "pasta",
+    file: "arquivo",
+    just_now: "agora mesmo",
+    unknown: "desconhecido",
+    broken_link: "quebrado",
+    ago: ("há ", ""),
+    later: ("em ", ""),
+    weekdays: ["seg", "ter", "qua", "qui", "sex", "sáb", "dom"],
+    units: [
+        ("segundo", "segundos"),
+        ("minuto", "minutos"),
+        ("hora", "horas"),
+        ("dia", "dias"),
+        ("semana", "semanas"),
+        ("mês", "meses"),
+        ("ano", "anos"),
+    ],
+    today: "Hoje",
+    yesterday: "Ontem",
+    last_week: "Semana passada",
+    months: [
+        "janeiro",
+        "fevereiro",
+        "março",
+        "abril",
+        "maio",
+        "junho",
+        "julho",
+        "agosto",
+        "setembro",
+        "outubro",
+        "novembro",
+        "dezembro",
+    ],
+};
+
+pub static DE: Strings = Strings {
+    header_index: "#",
+    header_name: "Name",
+    header_type: "Typ",
+    header_size: "Größe",
+    header_disk: "Belegt",
+    header_modified: "Geändert",
+    dir: "Ordner",
+    file: "Datei",
+    just_now: "gerade eben",
+    unknown: "unbekannt",
+    broken_link: "defekt",
+    ago: ("vor ", ""),
+    later: ("in ", ""),
+    weekdays: ["Mo", "Di", "Mi", "Do", "Fr", "Sa", "So"],
+    units: [
+        ("Sekunde", "Sekunden"),
+        ("Minute", "Minuten"),
+        ("Stunde", "Stunden"),
+        ("Tag", "Tagen"),
+        ("Woche", "Wochen"),
+        ("Monat", "Monaten"),
+        ("Jahr", "Jahren"),
+    ],
+    today: "Heute",
+    yesterday: "Gestern",
+    last_week: "Letzte Woche",
+    months: [
+        "Januar",
+        "Februar",
+        "März",
+        "April",
+        "Mai",
+        "Juni",
+        "Juli",
+        "August",
+        "September",
+        "Oktober",
+        "November",
+        "Dezember",
+    ],
+};
+
+pub static FR: Strings = Strings {
+    header_index: "#",
+    header_name: "nom",
+    header_type: "type",
+    header_size: "taille",
+    header_disk: "disque",
+    header_modified: "modifié",
+    dir: "dossier",
+    file: "fichier",
+    just_now: "à l'instant",
+    unknown: "inconnu",
+    broken_link: "cassé",
+    ago: ("il y a ", ""),
+    later: ("dans ", ""),
+    weekdays: ["lun", "mar", "mer", "jeu", "ven", "sam", "dim"],
+    units: [
+        ("seconde", "secondes"),
+        ("minute", "minutes"),
+        ("heure", "heures"),
+        ("jour", "jours"),
+        ("semaine", "semaines"),
+        ("mois", "mois"),
+        ("an", "ans"),
+    ],
+    today: "Aujourd'hui",
+    yesterday: "Hier",
+    last_week: "La semaine dernière",
+    months: [
+        "janvier",
+        "février",
+        "mars",
+        "avril",
+        "mai",
+        "juin",
+        "juillet",
+        "août",
+        "septembre",
+        "octobre",
+        "novembre",
+        "décembre",
+    ],
+};
@@ -0,0 +1,24 @@
+//! Ctrl-C handling: the first interrupt asks collection loops to stop so the
+//! rows gathered so far still render with intact borders; a second one exits
+//! immediately.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// Exit status for a run cut short by SIGINT (128 + 2), as shells report it.
+pub const EXIT_CODE: i32 = 130;
+
+pub fn install() {
+    // Without a handler the default SIGINT behavior (exit) still applies.
+    let _ = ctrlc::set_handler(|| {
+        if REQUESTED.swap(true, Ordering::SeqCst) {
+            std::process::exit(EXIT_CODE);
+        }
+    });
+}
+
+/// True once the user has pressed Ctrl-C; loops check this between entries.
+pub fn requested() -> bool {
+    REQUESTED.load(Ordering::Relaxed)
+}
@@ -0,0 +1,125 @@
+//! Paths past Windows' 260-character `MAX_PATH`. Directories are read and
+//! stat'ed through the extended-length form (`\\?\C:\…`, `\\?\UNC\server\…`),
+//! which the Win32 API takes up to 32k characters long, while anything shown
+//! to the user goes back to the ordinary form. Elsewhere both are no-ops.
+
+use std::borrow::Cow;
+use std::path::Path;
+
+const VERBATIM: &str = r"\\?\";
+const VERBATIM_UNC: &str = r"\\?\UNC\";
+
+/// `path` in the form to hand to the filesystem: absolute and
+/// extended-length on Windows, unchanged elsewhere or when it cannot be
+/// made absolute.
+#[cfg(windows)]
+pub fn extended(path: &Path) -> Cow<'_, Path> {
+    let Ok(abs) = std::path::absolute(path) else {
+        return Cow::Borrowed(path);
+    };
+    match abs.to_str().and_then(extend) {
+        Some(long) => Cow::Owned(long.into()),
+        None => Cow::Owned(abs),
+    }
+}
+
+#[cfg(not(windows))]
+pub fn extended(path: &Path) -> Cow<'_, Path> {
+    Cow::Borrowed(path)
+}
+
+/// `path` as a user would type it: `\\?\C:\x` becomes `C:\x` and
+/// `\\?\UNC\server\share` becomes `\\server\share`. `canonicalize` returns
+/// the long form on Windows, so every resolved path shown passes through here.
+pub fn friendly(path: &Path) -> Cow<'_, Path> {
+    match path.to_str().and_then(shorten) {
+        Some(short) => Cow::Owned(short.into()),
+        None => Cow::Borrowed(path),
+    }
+}
+
+/// The extended-length form of an absolute Windows path, or `None` for one
+/// that already is one (or a device path) or is not absolute.
+#[cfg_attr(not(windows), allow(dead_code))]
+fn extend(abs: &str) -> Option<String> {
+    if abs.starts_with(r"\\?\") || abs.starts_with(r"\\.\") {
+        None
+    } else if let Some(unc) = abs.strip_prefix(r"\\") {
+        Some(format!("{VERBATIM_UNC}{unc}"))
+    } else if abs.as_bytes().get(1..3) == Some(b":\\") {
+        Some(format!("{VERBATIM}{abs}"))
+    } else {
+        None
+    }
+}
+
+/// The ordinary form of an extended-length path, or `None` when there is
+/// nothing to strip. A path only valid in the long form (a component ending
+/// in a dot or space, a reserved name like `CON`) is left as it is.
+fn shorten(long: &str) -> Option<String> {
+    let short = if let Some(unc) = long.strip_prefix(VERBATIM_UNC) {
+        format!(r"\\{unc}")
+    } else {
+        let drive = long.strip_prefix(VERBATIM)?;
+        if drive.as_bytes().get(1..3) != Some(b":\\") {
+            return None;
+        }
+        drive.to_string()
+    };
+    let plain = short.split('\\').filter(|part| !part.is_empty()).all(|part| {
+        let stem = part.split('.').next().unwrap_or_default().to_ascii_uppercase();
+        let reserved = matches!(stem.as_str(), "CON" | "PRN" | "AUX" | "NUL")
+            || (stem.len() == 4
+                && (stem.starts_with("COM") || stem.starts_with("LPT"))
+                && stem.ends_with(|c: char| c.is_ascii_digit()));
+        part == ".." || !(part.ends_with('.') || part.ends_with(' ') || reserved)
+    });
+    plain.then_some(short)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drive_and_unc_paths_round_trip() {
+        assert_eq!(extend(r"C:\Users\me").as_deref(), Some(r"\\?\C:\Users\me"));
+        assert_eq!(extend(r"\\server\share\dir").as_deref(), Some(r"\\?\UNC\server\share\dir"));
+        assert_eq!(extend(r"\\?\C:\Users\me"), None);
+        assert_eq!(extend(r"\\.\pipe\x"), None);
+        assert_eq!(extend("relative"), None);
+        assert_eq!(shorten(r"\\?\C:\Users\me").as_deref(), Some(r"C:\Users\me"));
+        assert_eq!(shorten(r"\\?\UNC\server\share\dir").as_deref(), Some(r"\\server\share\dir"));
+        assert_eq!(shorten(r"C:\Users\me"), None);
+        assert_eq!(shorten("/home/me"), None);
+    }
+
+    #[test]
+    fn names_only_valid_in_long_form_stay_long() {
+        assert_eq!(shorten(r"\\?\C:\dir\trailing."), None);
+        assert_eq!(shorten(r"\\?\C:\dir\space "), None);
+        assert_eq!(shorten(r"\\?\C:\dir\con.txt"), None);
+        assert_eq!(shorten(r"\\?\C:\dir\COM1"), None);
+        assert_eq!(shorten(r"\\?\C:\dir\console\..\x").as_deref(), Some(r"C:\dir\console\..\x"));
+        assert_eq!(friendly(Path::new("/home/me")), Path::new("/home/me"));
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn deep_directories_list_past_max_path() {
+        let tmp = tempfile::tempdir().expect("tempdir");
+        let mut deep = extended(tmp.path()).into_owned();
+        while deep.as_os_str().len() < 300 {
+            deep.push("a-directory-name-of-forty-characters-xxx");
+        }
+        std::fs::create_dir_all(&deep).expect("mkdir");
+        std::fs::write(deep.join("leaf.txt"), "x").expect("write");
+        let shown = friendly(&deep).into_owned();
+        let names: Vec<_> = std::fs::read_dir(extended(&shown))
+            .expect("read_dir")
+            .map(|entry| entry.expect("entry").file_name())
+            .collect();
+        assert_eq!(names, ["leaf.txt"]);
+        assert!(!shown.to_string_lossy().starts_with(r"\\?\"));
+    }
+}
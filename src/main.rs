@@ -1,11 +1,64 @@
 use clap::builder::styling::{AnsiColor, Color, Style, Styles};
-use clap::{ArgAction, ColorChoice, Parser};
+use chrono::{DateTime, Datelike, Local};
+use clap::{ArgAction, ColorChoice, CommandFactory, Parser, Subcommand, ValueEnum};
 use std::cmp::Ordering;
-use std::collections::HashMap;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::ffi::OsString;
+use std::fmt::Write as _;
 use std::fs;
+use std::io::{self, IsTerminal, Read};
+use std::ops::Range;
 use std::path::{Path, PathBuf};
-use std::process::Command;
-use std::time::SystemTime;
+use std::process::{Command, Output, Stdio};
+use std::rc::Rc;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+mod annotate;
+mod archive;
+mod bookmark;
+mod caps;
+mod columns;
+mod compat;
+mod config;
+mod delta;
+mod diag;
+mod diff;
+mod export;
+#[cfg(target_os = "linux")]
+mod fiemap;
+mod git_log;
+mod history;
+mod i18n;
+mod interrupt;
+mod long_path;
+mod mounts;
+mod open_files;
+#[cfg(unix)]
+mod owner;
+mod progress;
+#[cfg(feature = "self-update")]
+mod self_update;
+mod sort;
+mod remote;
+mod render;
+mod project;
+mod rename;
+mod stat;
+#[cfg(target_os = "linux")]
+mod statx;
+mod theme;
+#[cfg(feature = "trash")]
+mod trash;
+mod tree;
+mod width;
+
+use columns::Column;
+use config::{ColumnAlign, Config, HeaderConfig, RecencyThresholds, TableConfig};
+use diag::Phase;
+use i18n::{Lang, Strings, TimeUnit};
+use sort::{SortOrder, SortSpec};
+use width::{display_width, isolate_bidi, needs_isolation};
 
 #[derive(Parser, Debug)]
 #[command(
@@ -13,9 +66,102 @@ use std::time::SystemTime;
     version,
     about = "A NuShell-inspired ls with color.",
     color = ColorChoice::Always,
-    styles = help_styles()
+    styles = help_styles(),
+    args_override_self = true
 )]
 struct Cli {
+    #[command(subcommand)]
+    command: Option<Commands>,
+
+    #[command(flatten)]
+    list: ListArgs,
+
+    /// Language for labels and relative times (en, es, pt, de, fr); defaults to $LANG
+    #[arg(long = "lang", value_name = "LANG", value_parser = i18n::parse_lang, global = true)]
+    lang: Option<Lang>,
+
+    /// When to use ANSI colors in the listing
+    #[arg(long = "color", value_enum, value_name = "WHEN", default_value_t = ColorMode::Auto, global = true)]
+    color: ColorMode,
+
+    /// Print no warnings, notes, or progress spinners on stderr (errors still print)
+    #[arg(short = 'q', long = "quiet", action = ArgAction::SetTrue, global = true, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// Also print how long listing took on stderr: collection, git, and rendering, in ms
+    #[arg(short = 'v', long = "verbose", action = ArgAction::SetTrue, global = true)]
+    verbose: bool,
+
+    /// Print where listing time went on stderr: read_dir, stat, git status, numstat, sort, render
+    #[arg(long = "timings", action = ArgAction::SetTrue, global = true)]
+    timings: bool,
+
+    /// Mark what colors convey in text too: / for dirs, * for executables, M and ? for git changes, (junk) for
+    /// dimmed junk files, and --git-age commit ages
+    #[arg(long = "ascii-status", action = ArgAction::SetTrue, default_value_t = false, global = true)]
+    ascii_status: bool,
+
+    /// Accept coreutils ls flags (-lah, -S, --color, ...), ignoring unsupported ones; implied when run as `ls`
+    #[arg(long = "compat", value_enum, value_name = "MODE")]
+    _compat: Option<CompatMode>,
+
+    /// Print a sample table using every palette color (recency buckets, git states, file classes) and exit
+    #[arg(long = "show-theme", action = ArgAction::SetTrue, default_value_t = false)]
+    show_theme: bool,
+
+    /// Replace this binary with the newest GitHub release for this platform, after checking its
+    /// SHA-256 checksum, and exit
+    #[arg(long = "self-update", action = ArgAction::SetTrue, default_value_t = false)]
+    self_update: bool,
+
+    /// Show the directories listed most often and most recently (recorded with `history = true` in the
+    /// config) and exit; with =QUERY, print only the top one whose path contains every word of QUERY
+    #[arg(long = "history", value_name = "QUERY", num_args = 0..=1, require_equals = true)]
+    history: Option<Option<String>>,
+
+    /// Print the nuls(1) man page and exit (nuls --man > nuls.1); with =DIR, write it and a
+    /// nuls-SUBCOMMAND(1) page per subcommand into DIR instead
+    #[arg(long = "man", value_name = "DIR", num_args = 0..=1, require_equals = true)]
+    man: Option<Option<PathBuf>>,
+
+    /// Give up on git after this long and list without git info (whole seconds: 5s, 1m)
+    #[arg(
+        long = "git-timeout",
+        value_name = "DURATION",
+        default_value = "5s",
+        value_parser = parse_timeout,
+        global = true
+    )]
+    git_timeout: Duration,
+
+    /// Count -g line changes against the merge base of HEAD and REF (e.g. origin/main) instead of HEAD
+    #[arg(long = "git-diff-base", value_name = "REF", global = true)]
+    git_diff_base: Option<String>,
+
+    /// Pretend the current time is this Unix timestamp (also read from $NULS_NOW)
+    #[arg(long = "now", value_name = "TIMESTAMP", hide = true, value_parser = parse_timestamp, global = true)]
+    now: Option<SystemTime>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Commands {
+    /// List a directory as a table (the default when no subcommand is given)
+    #[command(args_override_self = true)]
+    List(Box<ListArgs>),
+    /// Show a directory and its subdirectories as an indented tree
+    Tree(TreeArgs),
+    /// Show a detailed card for a single entry: permissions, owner, times, link target, git status
+    Stat(StatArgs),
+    /// Compare the entries of two directories by name, size, and modified time
+    Diff(DiffArgs),
+    /// Save, list, or remove directory bookmarks; `@NAME` then works as a path
+    Bookmark(BookmarkArgs),
+    /// Print a shell completion script to stdout
+    Completions(CompletionsArgs),
+}
+
+#[derive(clap::Args, Debug)]
+struct ListArgs {
     /// Path to list
     #[arg(default_value = ".")]
     path: PathBuf,
@@ -24,51 +170,969 @@ struct Cli {
     #[arg(short = 'a', long = "all", action = ArgAction::SetTrue, default_value_t = false)]
     include_hidden: bool,
 
+    /// List the directory itself as one row instead of its contents (like ls -d)
+    #[arg(short = 'd', long = "directory", action = ArgAction::SetTrue, default_value_t = false)]
+    directory: bool,
+
+    /// Show the target's type, size, and modified time for symlinks (like ls -L)
+    #[arg(short = 'L', long = "dereference", action = ArgAction::SetTrue, default_value_t = false)]
+    dereference: bool,
+
+    /// Prepend `.` and `..` rows describing the listed directory and its parent
+    #[arg(long = "show-self", action = ArgAction::SetTrue, default_value_t = false, conflicts_with = "directory")]
+    show_self: bool,
+
+    /// Show each entry as an absolute path instead of a bare name
+    #[arg(long = "full-path", action = ArgAction::SetTrue, default_value_t = false)]
+    full_path: bool,
+
+    /// Contract paths under $HOME to ~/... (with --full-path)
+    #[arg(long = "tilde", action = ArgAction::SetTrue, default_value_t = false, requires = "full_path")]
+    tilde: bool,
+
+    /// Print one colored name per line instead of a table (like ls -1)
+    #[arg(
+        short = '1',
+        long = "oneline",
+        action = ArgAction::SetTrue,
+        default_value_t = false,
+        overrides_with = "commas"
+    )]
+    oneline: bool,
+
+    /// Print names as a comma-separated stream wrapped to the terminal width (like ls -m)
+    #[arg(
+        short = 'm',
+        long = "commas",
+        action = ArgAction::SetTrue,
+        default_value_t = false,
+        overrides_with = "oneline"
+    )]
+    commas: bool,
+
+    /// Print plain columns separated by single tabs, with a header line and no padding or borders
+    #[arg(
+        long = "tabs",
+        action = ArgAction::SetTrue,
+        default_value_t = false,
+        conflicts_with_all = ["oneline", "commas"]
+    )]
+    tabs: bool,
+
+    /// Print the listing as a JSON object (absolute paths, RFC 3339 times, and the sort that ordered it)
+    #[arg(
+        long = "json",
+        action = ArgAction::SetTrue,
+        default_value_t = false,
+        conflicts_with_all = ["oneline", "commas", "tabs", "csv", "peek_archives"]
+    )]
+    json: bool,
+
+    /// Print the listing as CSV under a `# sort=...` comment line and a header row
+    #[arg(
+        long = "csv",
+        action = ArgAction::SetTrue,
+        default_value_t = false,
+        conflicts_with_all = ["oneline", "commas", "tabs", "peek_archives"]
+    )]
+    csv: bool,
+
     /// Long listing output (accepted for familiarity; same as default output)
     #[arg(short = 'l', long = "long", action = ArgAction::SetTrue, default_value_t = false)]
     _long: bool,
 
     /// Sort by modified time (newest first), like ls -t
-    #[arg(short = 't', long = "sort-modified", action = ArgAction::SetTrue, default_value_t = false)]
+    #[arg(
+        short = 't',
+        long = "sort-modified",
+        action = ArgAction::SetTrue,
+        default_value_t = false,
+        overrides_with_all = ["sort_size", "sort"]
+    )]
     sort_modified: bool,
 
+    /// Sort by size (largest first), like ls -S
+    #[arg(
+        short = 'S',
+        long = "sort-size",
+        action = ArgAction::SetTrue,
+        default_value_t = false,
+        overrides_with_all = ["sort_modified", "sort"]
+    )]
+    sort_size: bool,
+
+    /// Sort by modified time (newest first) but keep directories ahead of files, even with -r
+    #[arg(
+        long = "dirs-first-with-time",
+        action = ArgAction::SetTrue,
+        default_value_t = false,
+        conflicts_with_all = ["sort_size", "sort", "group_by"]
+    )]
+    dirs_first_with_time: bool,
+
+    /// Sort by these keys, comma-separated: name, modified, size, or entries, each optionally with :asc or :desc
+    /// (e.g. size:desc,name:asc); -t and -S are shorthands for modified and size
+    #[arg(
+        long = "sort",
+        value_name = "KEYS",
+        value_parser = SortOrder::parse,
+        overrides_with_all = ["sort_modified", "sort_size"]
+    )]
+    sort: Option<SortOrder>,
+
+    /// Add an entries column with the number of items directly inside each directory
+    #[arg(long = "dir-counts", action = ArgAction::SetTrue, default_value_t = false)]
+    dir_counts: bool,
+
+    /// Insert heading rows between groups of entries (sorts by modified time)
+    #[arg(
+        long = "group-by",
+        value_enum,
+        value_name = "KEY",
+        conflicts_with_all = ["sort_size", "sort", "commas", "tabs"]
+    )]
+    group_by: Option<GroupBy>,
+
+    /// Show only directories with at least N entries (implies --dir-counts)
+    #[arg(long = "min-entries", value_name = "N", value_parser = clap::value_parser!(u64), conflicts_with = "recent")]
+    min_entries: Option<u64>,
+
+    /// Show only the first N entries in sort order, keeping memory bounded in huge directories
+    #[arg(long = "limit", visible_alias = "top", value_name = "N", value_parser = clap::value_parser!(usize))]
+    limit: Option<usize>,
+
+    /// Move the entries numbered N (as this same listing shows them; comma-separated for several) to the
+    /// trash, then list what is left
+    #[arg(long = "trash", value_name = "N", value_delimiter = ',', value_parser = clap::value_parser!(usize))]
+    trash: Vec<usize>,
+
+    /// After listing, write the listed directory's absolute path to FILE, for a shell function to cd there
+    #[arg(long = "cd-on-exit", value_name = "FILE", conflicts_with = "stdin")]
+    cd_on_exit: Option<PathBuf>,
+
+    /// Read metadata with batched statx calls through io_uring (Linux; ignored elsewhere and with
+    /// flags that need more than type, size, mode, and time)
+    #[arg(long = "statx", action = ArgAction::SetTrue, default_value_t = false)]
+    statx: bool,
+
+    /// Read every entry's size, time, and mode on network filesystems too (NFS, SMB, ...), where
+    /// listings sorted by name otherwise show names and types only
+    #[arg(long = "full", action = ArgAction::SetTrue, default_value_t = false)]
+    full: bool,
+
+    /// List the N most recently modified files anywhere under the path, by relative path (skips VCS dirs)
+    #[arg(
+        long = "recent",
+        value_name = "N",
+        value_parser = clap::value_parser!(usize),
+        conflicts_with_all = ["directory", "limit", "show_self", "git"]
+    )]
+    recent: Option<usize>,
+
+    /// List the paths read from stdin (one per line, or NUL-separated as from find -print0 or fd -0)
+    /// instead of a directory
+    #[arg(
+        long = "stdin",
+        action = ArgAction::SetTrue,
+        conflicts_with_all = ["path", "recent", "directory", "show_self", "git"]
+    )]
+    stdin: bool,
+
+    /// Leave out backup, swap, and OS metadata files (*~, *.bak, *.swp, .DS_Store, Thumbs.db, ...)
+    #[arg(long = "hide-junk", action = ArgAction::SetTrue, default_value_t = false)]
+    hide_junk: bool,
+
+    /// Hide entries last modified longer ago than DURATION (e.g. 30d, 6mo, 1y), going by each entry's own time
+    #[arg(long = "hide-older-than", value_name = "DURATION", value_parser = config::parse_duration)]
+    hide_older_than: Option<u64>,
+
+    /// Only files git tracks, and directories holding them (`git ls-files`), leaving out build output and
+    /// anything else untracked or ignored
+    #[arg(long = "tracked-only", action = ArgAction::SetTrue, default_value_t = false)]
+    tracked_only: bool,
+
+    /// Only entries the current user can write to
+    #[arg(long = "writable", action = ArgAction::SetTrue, default_value_t = false)]
+    writable: bool,
+
+    /// Only entries the current user can read
+    #[arg(long = "readable", action = ArgAction::SetTrue, default_value_t = false)]
+    readable: bool,
+
+    /// Only files the current user can execute
+    #[arg(long = "executable-only", action = ArgAction::SetTrue, default_value_t = false)]
+    executable_only: bool,
+
+    /// Only entries owned by USER (a name or numeric uid; Unix)
+    #[arg(long = "owned-by", value_name = "USER")]
+    owned_by: Option<String>,
+
     /// Reverse sort order (like ls -r)
     #[arg(short = 'r', long = "reverse", action = ArgAction::SetTrue, default_value_t = false)]
     reverse: bool,
 
-    /// Show git status (+added/-deleted) if inside a git repo
-    #[arg(short = 'g', long = "git", action = ArgAction::SetTrue, default_value_t = false)]
-    git: bool,
+    /// How to order entries whose sort keys are equal
+    #[arg(long = "tie-break", value_enum, value_name = "KEY", default_value_t = TieBreak::Name)]
+    tie_break: TieBreak,
+
+    /// How to show modified times
+    #[arg(long = "time-style", value_enum, value_name = "STYLE", default_value_t = TimeStyle::Relative)]
+    time_style: TimeStyle,
+
+    /// Decimal places in sizes of 1 KB and up (by default one below 10, none above)
+    #[arg(long = "size-digits", value_name = "N", value_parser = clap::value_parser!(u8).range(0..=3))]
+    size_digits: Option<u8>,
+
+    /// Pad every size to the same width (`   4.0 KB`, `  12.0 MB`) so the numbers and units line up
+    #[arg(long = "size-fixed-width", action = ArgAction::SetTrue, default_value_t = false)]
+    size_fixed_width: bool,
+
+    /// Show git status (+added/-deleted): auto (the default) inside a work tree only, always (bare -g), or never
+    #[arg(
+        short = 'g',
+        long = "git",
+        value_enum,
+        value_name = "WHEN",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "always"
+    )]
+    git: Option<GitMode>,
+
+    /// Add a disk column with on-disk (allocated) size and its share of the apparent size
+    #[arg(long = "compressed-size", action = ArgAction::SetTrue, default_value_t = false)]
+    compressed_size: bool,
+
+    /// Run CMD for each entry ({} is replaced by its path) and show the first line of output in a note column
+    #[arg(long = "annotate", value_name = "CMD")]
+    annotate: Option<String>,
+
+    /// Give up on an --annotate command after this long (whole seconds: 2s, 30s, 1m)
+    #[arg(long = "annotate-timeout", value_name = "DURATION", default_value = "2s", value_parser = parse_timeout)]
+    annotate_timeout: Duration,
+
+    /// Mark hard-linked files and keep entries sharing an inode next to each other
+    #[arg(long = "link-groups", action = ArgAction::SetTrue, default_value_t = false)]
+    link_groups: bool,
+
+    /// Highlight world-writable, setuid/setgid, root-owned, and differently owned entries, with a summary footer
+    #[arg(long = "security", action = ArgAction::SetTrue, default_value_t = false)]
+    security: bool,
+
+    /// Add an access column: the current user's effective rwx and which class (owner/group/other) grants it
+    #[arg(long = "who-can", action = ArgAction::SetTrue, default_value_t = false)]
+    who_can: bool,
+
+    /// Add an open-by column naming the processes that hold each entry open, such as a busy log or a locked database
+    #[arg(long = "open-by", action = ArgAction::SetTrue, default_value_t = false)]
+    open_by: bool,
+
+    /// Add a column with what each name would become under a sed-style s/PATTERN/REPLACEMENT/ (nothing is renamed)
+    #[arg(
+        long = "rename-preview",
+        value_name = "SED_EXPR",
+        value_parser = rename::Substitution::parse,
+        conflicts_with_all = ["directory", "recent", "show_self", "stdin"]
+    )]
+    rename_preview: Option<rename::Substitution>,
+
+    /// Add a delta column with each file's size change since the last --delta listing of this directory
+    #[arg(long = "delta", action = ArgAction::SetTrue, conflicts_with_all = ["directory", "recent", "stdin", "limit"])]
+    delta: bool,
+
+    /// Add total and average lines under the table for the numeric columns (size, disk, entries, delta)
+    #[arg(long = "totals", action = ArgAction::SetTrue, default_value_t = false)]
+    totals: bool,
+
+    /// Drop columns that read the same on every row, naming the shared value under the table
+    #[arg(long = "elide-common", action = ArgAction::SetTrue, default_value_t = false)]
+    elide_common: bool,
+
+    /// Note under the table when the directory is on an overlay filesystem (containers) or a bind mount (Linux)
+    #[arg(long = "mount-info", action = ArgAction::SetTrue, default_value_t = false)]
+    mount_info: bool,
+
+    /// Print a header naming the enclosing project (Cargo, npm, Python) and its root
+    #[arg(long = "project", action = ArgAction::SetTrue, default_value_t = false)]
+    project: bool,
+
+    /// Color names by the age of their last commit instead of their type
+    #[arg(
+        long = "git-age",
+        action = ArgAction::SetTrue,
+        default_value_t = false,
+        conflicts_with_all = ["directory", "recent"]
+    )]
+    git_age: bool,
+
+    /// Add an author column with the author of each entry's last commit
+    #[arg(
+        long = "git-author",
+        action = ArgAction::SetTrue,
+        default_value_t = false,
+        conflicts_with_all = ["directory", "recent"]
+    )]
+    git_author: bool,
+
+    /// Tag Cargo crates with their package name and version, and show the total size of target/
+    #[arg(long = "cargo", action = ArgAction::SetTrue, default_value_t = false)]
+    cargo: bool,
+
+    /// Tag npm packages with name@version, and show the entry count and total size of node_modules/
+    #[arg(long = "npm", action = ArgAction::SetTrue, default_value_t = false)]
+    npm: bool,
+
+    /// Print a distribution of file sizes (<1 KB, 1–100 KB, 100 KB–10 MB, >10 MB) under the listing
+    #[arg(long = "size-histogram", action = ArgAction::SetTrue, default_value_t = false)]
+    size_histogram: bool,
+
+    /// Append a table of file count and total size per extension
+    #[arg(long = "ext-summary", action = ArgAction::SetTrue, default_value_t = false)]
+    ext_summary: bool,
+
+    /// List the contents of zip and tar archives as indented rows under each archive
+    #[arg(long = "peek-archives", action = ArgAction::SetTrue, default_value_t = false, conflicts_with = "commas")]
+    peek_archives: bool,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(clap::Args, Debug)]
+struct TreeArgs {
+    /// Root of the tree
+    #[arg(default_value = ".")]
+    path: PathBuf,
+
+    /// Include dotfiles
+    #[arg(short = 'a', long = "all", action = ArgAction::SetTrue, default_value_t = false)]
+    include_hidden: bool,
+
+    /// Descend at most this many levels below the root
+    #[arg(short = 'L', long = "level", value_name = "DEPTH")]
+    level: Option<usize>,
+}
+
+#[derive(clap::Args, Debug)]
+struct DiffArgs {
+    /// Directory on the left (old) side
+    left: PathBuf,
+
+    /// Directory on the right (new) side
+    right: PathBuf,
+
+    /// Include dotfiles
+    #[arg(short = 'a', long = "all", action = ArgAction::SetTrue, default_value_t = false)]
+    include_hidden: bool,
+}
+
+#[derive(clap::Args, Debug)]
+struct BookmarkArgs {
+    #[command(subcommand)]
+    action: Option<BookmarkAction>,
+}
+
+#[derive(Subcommand, Debug)]
+enum BookmarkAction {
+    /// Save PATH (default: the current directory) as NAME
+    Add {
+        name: String,
+        #[arg(default_value = ".")]
+        path: PathBuf,
+    },
+    /// Forget a bookmark
+    Remove { name: String },
+    /// Print every bookmark (the default)
+    List,
+}
+
+#[derive(clap::Args, Debug)]
+struct CompletionsArgs {
+    /// Shell to generate completions for
+    #[arg(value_enum)]
+    shell: clap_complete::Shell,
+}
+
+#[derive(clap::Args, Debug)]
+struct StatArgs {
+    /// Entry to describe
+    path: PathBuf,
+
+    /// Also compute the SHA-256 of the file contents
+    #[arg(long = "hash", action = ArgAction::SetTrue, default_value_t = false)]
+    hash: bool,
+}
+
+/// Primary ordering for the listing.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum SortKey {
+    /// Directories first, then by name
+    Name,
+    /// Newest first
+    Modified,
+    /// Largest first
+    Size,
+    /// Directories with the most entries first, then files
+    Entries,
+}
+
+impl ListArgs {
+    fn sort_spec(&self) -> SortSpec {
+        let spec = self
+            .sort_order()
+            .spec()
+            .reverse(self.reverse)
+            .tie_break(self.tie_break)
+            .dirs_first(self.dirs_first_with_time);
+        // A `.nulsorder` file stands in for the default order only; `--sort
+        // name` still sorts by name.
+        if self.sort.is_none()
+            && self.sort_key() == SortKey::Name
+            && !self.directory
+            && !self.stdin
+            && let Some(names) = sort::read_order_file(&self.path)
+        {
+            return spec.explicit_order(&names);
+        }
+        spec
+    }
+
+    /// `--sort`, or the one key the shorthands and defaults pick.
+    fn sort_order(&self) -> SortOrder {
+        match &self.sort {
+            Some(order) => order.clone(),
+            None => SortOrder::of(self.sort_key()),
+        }
+    }
+
+    /// The key consulted first.
+    fn sort_key(&self) -> SortKey {
+        if let Some(order) = &self.sort {
+            order.primary()
+        } else if self.sort_modified
+            || self.dirs_first_with_time
+            || self.group_by.is_some()
+            || (self.recent.is_some() && !self.sort_size)
+        {
+            SortKey::Modified
+        } else if self.sort_size {
+            SortKey::Size
+        } else {
+            SortKey::Name
+        }
+    }
+
+    /// Whether the output orders, sums, or exports sizes and times, so a
+    /// `-` cell cannot stand in for them.
+    fn needs_metadata(&self) -> bool {
+        self.sort_order().keys().any(|key| key != SortKey::Name)
+            || self.recent.is_some()
+            || self.hide_older_than.is_some()
+            || self.json
+            || self.csv
+            || self.size_histogram
+            || self.ext_summary
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum TieBreak {
+    /// Case-insensitive name, then exact byte order
+    Name,
+    /// Keep the order the filesystem returned (the sort is stable)
+    None,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum GroupBy {
+    /// Today, Yesterday, Last week, then one heading per month
+    ModifiedDay,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum TimeStyle {
+    /// "3 days ago", "in 2 hours"
+    Relative,
+    /// "14:32" today, "Tue 14:32" this week, "2024-03-05" otherwise
+    Hybrid,
+    /// "3d", "2mo", "+1h": the largest whole unit, for narrow terminals
+    Compact,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum CompatMode {
+    Ls,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum ColorMode {
+    Auto,
+    Always,
+    Never,
+}
+
+/// When to run git for the status column; also the `git` config key.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum GitMode {
+    /// Only when a `.git` sits in the listed path or above it
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+/// Whether this build runs git at all (the `git` feature). Without it,
+/// `auto` never turns git on and explicit requests get a warning.
+const GIT_SUPPORT: bool = cfg!(feature = "git");
+
+impl GitMode {
+    /// `auto` looks for `.git` on disk, so listings outside a repository
+    /// never spawn git; `$GIT_DIR` counts as being inside one.
+    fn enabled(self, path: &Path) -> bool {
+        match self {
+            GitMode::Always => true,
+            GitMode::Never => false,
+            GitMode::Auto if !GIT_SUPPORT => false,
+            GitMode::Auto => {
+                if std::env::var_os("GIT_DIR").is_some_and(|dir| !dir.is_empty()) {
+                    return true;
+                }
+                let abs = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+                // Inside `.git` itself there is no work tree to show.
+                let in_git_dir = abs.components().any(|part| part.as_os_str() == ".git");
+                !in_git_dir && abs.ancestors().any(|dir| dir.join(".git").exists())
+            }
+        }
+    }
+}
+
+impl ColorMode {
+    /// Whether to color what is written to `stream`.
+    fn enabled(self, stream: impl IsTerminal) -> bool {
+        match self {
+            ColorMode::Always => true,
+            ColorMode::Never => false,
+            ColorMode::Auto => std::env::var_os("NO_COLOR").is_none() && stream.is_terminal(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
 enum EntryType {
     Dir,
+    #[default]
     File,
 }
 
-#[derive(Debug)]
+/// Raw facts about one entry. Text and colors are produced only when a
+/// column is rendered (see `RowFormat`), so hidden columns and `--color never`
+/// cost nothing.
+#[derive(Debug, Default)]
 struct EntryRow {
+    /// Bare file name, used for sorting and hidden-file checks.
     name_plain: String,
-    entry_type_plain: String,
-    entry_type_colored: String,
-    size_plain: String,
-    size_colored: String,
-    modified_plain: String,
-    modified_colored: String,
+    /// Text for the name column when it differs from `name_plain` (`--full-path`).
+    display_name: Option<String>,
+    entry_type: EntryType,
+    is_executable: bool,
+    /// A `-L` symlink whose target could not be read.
+    broken_link: bool,
+    size_bytes: u64,
+    /// Allocated bytes, when the disk column was requested and the platform reports it.
+    disk_bytes: Option<u64>,
     modified_time: Option<SystemTime>,
-    name_with_git_colored: String,
-    name_with_git_plain: String,
-    is_dir: bool,
+    git: Option<GitStatus>,
+    security: SecurityFlags,
+    hard_link: Option<HardLink>,
+    /// `[hardlink #N · M links]` annotation set by `group_hard_links`.
+    link_tag: Option<String>,
+    /// Package tag set by `--cargo` (`[crate name version]`, `[build output]`)
+    /// or `--npm` (`[name@version]`, `[dependencies · N entries]`).
+    project_tag: Option<String>,
+    /// Output of the `--annotate` command for this entry.
+    annotation: Option<annotate::Annotation>,
+    /// Tree branch drawn before a `--peek-archives` member; `None` for real entries.
+    archive_branch: Option<&'static str>,
+    /// The current user's rights, when `--who-can` asked for them.
+    access: Option<EffectiveAccess>,
+    /// Items directly inside a directory, when `--dir-counts` (or a sort or
+    /// filter on it) asked for them.
+    child_count: Option<u64>,
+    /// A directory with nothing in it, tagged `(empty)`.
+    empty: bool,
+    /// A path git tracks but sparse checkout left off disk; it has no size
+    /// or time of its own.
+    not_checked_out: bool,
+    /// Listed from its name and type alone on a network filesystem (see
+    /// `--full`): size, time, and mode were never read.
+    stat_skipped: bool,
+    /// Name color from `--git-age`, replacing the class color.
+    age_color: Option<&'static str>,
+    /// The `--git-age` commit age as text (`3mo`), for `--ascii-status`.
+    age_text: Option<String>,
+    /// Author of the last commit touching the entry, for `--git-author`.
+    author: Option<String>,
+    /// Processes holding the entry open, for `--open-by`.
+    open_by: Option<String>,
+    /// The name `--rename-preview` would give the entry, when it changes.
+    renamed: Option<rename::Preview>,
+    /// Size change since the last `--delta` snapshot.
+    size_change: Option<delta::SizeChange>,
 }
 
-#[derive(Clone, Copy)]
+/// How row values become text at render time.
+struct RowFormat<'a> {
+    strings: &'a Strings,
+    headers: &'a Headers,
+    aligns: &'a Alignments,
+    recency: RecencyThresholds,
+    time_style: TimeStyle,
+    size_style: SizeStyle,
+    group_by: Option<GroupBy>,
+    now: SystemTime,
+    /// Sum and average the numeric columns under the table (`--totals`).
+    totals: bool,
+}
+
+impl EntryRow {
+    fn is_dir(&self) -> bool {
+        self.entry_type == EntryType::Dir
+    }
+
+    fn display_name(&self) -> &str {
+        self.display_name.as_deref().unwrap_or(&self.name_plain)
+    }
+
+    /// The displayed name in its class color, without annotations. With
+    /// `--ascii-status`, directories end in `/`, executables in `*`, junk
+    /// files (dimmed otherwise) in `(junk)`, and `--git-age` names in their
+    /// commit age, e.g. `(committed 3mo ago)`.
+    fn name_cell(&self) -> (String, String) {
+        let marker = match self.entry_type {
+            _ if !palette::ascii_status() => "",
+            EntryType::Dir => "/",
+            EntryType::File if self.is_executable => "*",
+            EntryType::File => "",
+        };
+        let mut name = format!("{}{marker}", self.display_name());
+        let colored = if self.security.any() {
+            palette::paint(&name, palette::SECURITY)
+        } else if self.not_checked_out {
+            palette::paint(&name, palette::GIT_CLEAN)
+        } else if let Some(color) = self.age_color {
+            let colored = palette::paint(&name, color);
+            match &self.age_text {
+                Some(age) => {
+                    let tag = format!("(committed {age} ago)");
+                    name = format!("{name} {tag}");
+                    colored + " " + &palette::paint(tag, color)
+                }
+                None => colored,
+            }
+        } else {
+            let colored = color_name(&name, self.entry_type, self.is_executable, self.name_plain.starts_with('.'));
+            if palette::ascii_status() && !self.is_dir() && is_junk(&self.name_plain) {
+                name.push_str(" (junk)");
+                colored + " " + &palette::paint("(junk)", palette::JUNK)
+            } else {
+                colored
+            }
+        };
+        match self.archive_branch {
+            Some(branch) => (
+                format!("  {branch} {name}"),
+                format!("{}{colored}", palette::paint(format!("  {branch} "), palette::BORDER)),
+            ),
+            None => (name, colored),
+        }
+    }
+
+    /// The name followed by its git, security, and hard-link annotations.
+    fn annotated_name_cell(&self) -> (String, String) {
+        let (mut plain, mut colored) = self.name_cell();
+        let mut annotate = |tag_plain: &str, tag_colored: &str| {
+            plain = format!("{plain} {tag_plain}");
+            colored = format!("{colored} {tag_colored}");
+        };
+
+        let git_tag = self.git.as_ref().and_then(format_git);
+        if let Some((tag_plain, tag_colored)) = git_tag.filter(|(tag, _)| !tag.is_empty()) {
+            annotate(&tag_plain, &tag_colored);
+        }
+        if self.security.any() {
+            let tag = format!("[{}]", self.security.labels().join(" "));
+            annotate(&tag, &palette::paint(&tag, palette::SECURITY));
+        }
+        if let Some(tag) = &self.link_tag {
+            annotate(tag, &palette::paint(tag, palette::LINK));
+        }
+        if let Some(tag) = &self.project_tag {
+            annotate(tag, &palette::paint(tag, palette::TYPE));
+        }
+        if self.empty {
+            annotate("(empty)", &palette::paint("(empty)", palette::GIT_CLEAN));
+        }
+        (plain, colored)
+    }
+
+    fn type_cell(&self, strings: &Strings) -> (String, String) {
+        let label = match self.entry_type {
+            EntryType::Dir => strings.dir,
+            EntryType::File => strings.file,
+        };
+        (label.to_string(), palette::paint(label, palette::TYPE))
+    }
+
+    fn size_cell(&self, strings: &Strings, style: SizeStyle) -> (String, String) {
+        if self.not_checked_out || self.stat_skipped {
+            return ("-".to_string(), palette::paint("-", palette::GIT_CLEAN));
+        }
+        if self.broken_link {
+            return (
+                strings.broken_link.to_string(),
+                palette::paint(strings.broken_link, palette::WARN),
+            );
+        }
+        let mut text = String::new();
+        write_size_as(&mut text, self.size_bytes, style);
+        (text.clone(), palette::paint(text, palette::SIZE))
+    }
+
+    fn entries_cell(&self) -> (String, String) {
+        match self.child_count {
+            Some(count) => (count.to_string(), palette::paint(count.to_string(), palette::INDEX)),
+            None => (String::new(), String::new()),
+        }
+    }
+
+    fn disk_cell(&self, style: SizeStyle) -> (String, String) {
+        format_disk_size(self.disk_bytes, self.size_bytes, style)
+    }
+
+    fn delta_cell(&self, style: SizeStyle) -> (String, String) {
+        let Some(change) = self.size_change else {
+            return (String::new(), String::new());
+        };
+        let text = change.text(style);
+        let color = match change {
+            delta::SizeChange::By(bytes) if bytes > 0 => palette::GIT_ADDED,
+            delta::SizeChange::By(_) => palette::GIT_REMOVED,
+            delta::SizeChange::New => palette::GIT_CLEAN,
+        };
+        let colored = palette::paint(&text, color);
+        (text, colored)
+    }
+
+    fn author_cell(&self) -> (String, String) {
+        match &self.author {
+            Some(author) => (author.clone(), palette::paint(author, palette::TYPE)),
+            None => (String::new(), String::new()),
+        }
+    }
+
+    fn rename_cell(&self) -> (String, String) {
+        match &self.renamed {
+            Some(rename::Preview { name, clash: false }) => (name.clone(), palette::paint(name, palette::TYPE)),
+            Some(rename::Preview { name, clash: true }) => {
+                let text = format!("{name} (taken)");
+                let colored = palette::paint(&text, palette::WARN);
+                (text, colored)
+            }
+            None => (String::new(), String::new()),
+        }
+    }
+
+    fn open_by_cell(&self) -> (String, String) {
+        match &self.open_by {
+            Some(holders) => (holders.clone(), palette::paint(holders, palette::WARN)),
+            None => (String::new(), String::new()),
+        }
+    }
+
+    fn note_cell(&self) -> (String, String) {
+        match &self.annotation {
+            Some(annotate::Annotation::Text(text)) => (text.clone(), palette::paint(text, palette::FILE)),
+            Some(annotate::Annotation::TimedOut) => ("timeout".to_string(), palette::paint("timeout", palette::WARN)),
+            Some(annotate::Annotation::Failed(err)) => {
+                let text = format!("error: {err}");
+                (text.clone(), palette::paint(text, palette::WARN))
+            }
+            None => (String::new(), String::new()),
+        }
+    }
+
+    /// `rwx owner`-style rights, with a warning when the user cannot read the
+    /// entry or enter the directory.
+    fn access_cell(&self) -> (String, String) {
+        let Some(access) = self.access else {
+            return (String::new(), String::new());
+        };
+        let mut text: String = [(access.read, 'r'), (access.write, 'w'), (access.execute, 'x')]
+            .iter()
+            .map(|(granted, flag)| if *granted { *flag } else { '-' })
+            .collect();
+        if let Some(class) = access.class {
+            text = format!("{text} {}", class.label());
+        }
+        let problem = if !access.read {
+            Some("cannot read")
+        } else if self.is_dir() && !access.execute {
+            Some("cannot enter")
+        } else {
+            None
+        };
+        match problem {
+            Some(problem) => {
+                let text = format!("{text} · {problem}");
+                (text.clone(), palette::paint(text, palette::WARN))
+            }
+            None => (text.clone(), palette::paint(text, palette::TYPE)),
+        }
+    }
+
+    fn modified_cell(&self, format: &RowFormat) -> (String, String) {
+        let (text, color) = self.modified_text(format);
+        let colored = palette::paint(&text, color);
+        (text, colored)
+    }
+
+    /// The modified column's text and the color its recency gets.
+    fn modified_text(&self, format: &RowFormat) -> (String, &'static str) {
+        if self.not_checked_out || self.stat_skipped {
+            return ("-".to_string(), palette::GIT_CLEAN);
+        }
+        let (text, recency) = self
+            .modified_time
+            .map(|ts| {
+                let (relative, recency) = format_relative_time(ts, format.now, &format.recency, format.strings);
+                match format.time_style {
+                    TimeStyle::Relative => (relative, recency),
+                    TimeStyle::Hybrid => (format_hybrid_time(ts, format.now, format.strings), recency),
+                    TimeStyle::Compact => (format_compact_time(ts, format.now), recency),
+                }
+            })
+            .unwrap_or_else(|| (format.strings.unknown.to_string(), Recency::Unknown));
+        (text, recency_color(recency))
+    }
+}
+
+/// Identity of a file with more than one hard link.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct HardLink {
+    device: u64,
+    inode: u64,
+    links: u64,
+}
+
+/// Which permission class applies to the current user for an entry.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(not(unix), allow(dead_code))]
+enum AccessClass {
+    /// Root bypasses the mode bits (except that execute needs some `x` bit).
+    Root,
+    Owner,
+    Group,
+    Other,
+}
+
+impl AccessClass {
+    fn label(self) -> &'static str {
+        match self {
+            AccessClass::Root => "root",
+            AccessClass::Owner => "owner",
+            AccessClass::Group => "group",
+            AccessClass::Other => "other",
+        }
+    }
+}
+
+/// What the current user may do with an entry, per `--who-can`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct EffectiveAccess {
+    read: bool,
+    write: bool,
+    execute: bool,
+    /// `None` where the platform has no owner/group/other bits.
+    class: Option<AccessClass>,
+}
+
+/// Permission and ownership red flags surfaced by `--security`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct SecurityFlags {
+    world_writable: bool,
+    setuid: bool,
+    setgid: bool,
+    /// Owned by root inside a directory that is not.
+    root_owned: bool,
+    /// Some other user's entry inside a directory that is neither theirs nor
+    /// root's: the owner's uid.
+    other_owner: Option<u32>,
+    /// In a group other than its directory's, which is not root's: the gid.
+    other_group: Option<u32>,
+    /// Linux file capabilities (`setcap`), which grant privileges like setuid does.
+    capabilities: Option<caps::FileCaps>,
+    /// `chattr +i`: no one, root included, may change, rename, or delete it.
+    immutable: bool,
+    /// `chattr +a`: it may only be appended to.
+    append_only: bool,
+}
+
+impl SecurityFlags {
+    fn any(self) -> bool {
+        self.world_writable
+            || self.setuid
+            || self.setgid
+            || self.root_owned
+            || self.other_owner.is_some()
+            || self.other_group.is_some()
+            || self.capabilities.is_some()
+            || self.immutable
+            || self.append_only
+    }
+
+    fn labels(self) -> Vec<String> {
+        [
+            (self.setuid, "setuid"),
+            (self.setgid, "setgid"),
+            (self.world_writable, "world-writable"),
+            (self.root_owned, "root-owned"),
+            (self.immutable, "immutable"),
+            (self.append_only, "append-only"),
+        ]
+        .into_iter()
+        .filter(|(set, _)| *set)
+        .map(|(_, label)| label.to_string())
+        .chain(self.other_owner.map(|uid| format!("owner {}", user_label(uid))))
+        .chain(self.other_group.map(|gid| format!("group {}", group_label(gid))))
+        .chain(self.capabilities.map(|caps| caps.to_string()))
+        .collect()
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+struct SecuritySummary {
+    world_writable: usize,
+    setuid: usize,
+    setgid: usize,
+    root_owned: usize,
+    other_owner: usize,
+    other_group: usize,
+    capabilities: usize,
+    immutable: usize,
+    append_only: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 enum Align {
     Left,
     Right,
+    Center,
 }
 
 mod palette {
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    static ENABLED: AtomicBool = AtomicBool::new(true);
+    static ASCII_STATUS: AtomicBool = AtomicBool::new(false);
+
     pub const RESET: &str = "\x1b[0m";
     pub const BORDER: &str = "\x1b[38;5;99m";
     pub const HEADER: &str = "\x1b[38;5;82m";
@@ -88,196 +1152,1662 @@ mod palette {
     pub const EXEC: &str = "\x1b[38;5;197m";
     pub const DOTFILE: &str = "\x1b[38;5;179m";
     pub const WARN: &str = "\x1b[38;5;214m";
+    pub const LINK: &str = "\x1b[38;5;141m";
+    pub const SECURITY: &str = "\x1b[1;38;5;196m";
     pub const GIT_DIRTY: &str = "\x1b[38;5;214m";
     pub const GIT_ADDED: &str = "\x1b[38;5;77m";
     pub const GIT_REMOVED: &str = "\x1b[38;5;203m";
     pub const GIT_CLEAN: &str = "\x1b[38;5;240m";
+    pub const JUNK: &str = "\x1b[2;38;5;244m";
+
+    pub fn set_enabled(enabled: bool) {
+        ENABLED.store(enabled, Ordering::Relaxed);
+    }
+
+    /// `--ascii-status`: spell out in text what would otherwise only be a color.
+    pub fn set_ascii_status(enabled: bool) {
+        ASCII_STATUS.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn ascii_status() -> bool {
+        ASCII_STATUS.load(Ordering::Relaxed)
+    }
+
+    pub fn paint(text: impl AsRef<str>, color: &str) -> String {
+        let text = text.as_ref();
+        let mut out = String::with_capacity(color.len() + text.len() + RESET.len());
+        paint_into(&mut out, text, color);
+        out
+    }
+
+    /// `paint`, appending to `out` instead of allocating.
+    pub fn paint_into(out: &mut String, text: &str, color: &str) {
+        let enabled = ENABLED.load(Ordering::Relaxed);
+        if enabled {
+            out.push_str(color);
+        }
+        out.push_str(text);
+        if enabled {
+            out.push_str(RESET);
+        }
+    }
+}
+
+#[derive(Debug)]
+struct GitInfo {
+    entries: HashMap<String, GitStatus>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct GitStatus {
+    added: Option<u64>,
+    deleted: Option<u64>,
+    /// Numstat reported the change as binary (`-`), so there are no counts.
+    binary: bool,
+    dirty: bool,
+    untracked: bool,
+    /// Changed paths beneath a directory entry, counted by
+    /// `scope_git_entries`; `None` for files.
+    files: Option<u64>,
+    /// Previous path of a renamed file, relative to the listed directory
+    /// when it was inside it.
+    renamed_from: Option<String>,
+    /// The file has git's skip-worktree bit (set by sparse checkout).
+    skip_worktree: bool,
+    /// Skip-worktree paths beneath a directory entry; `None` for files.
+    skipped: Option<u64>,
+    /// The file is flagged `--assume-unchanged`, so git ignores its edits.
+    assume_unchanged: bool,
+    /// Assume-unchanged paths beneath a directory entry; `None` for files.
+    assumed: Option<u64>,
+}
+
+fn main() {
+    diag::set_color(ColorMode::Auto.enabled(io::stderr()));
+    let mut args = compat::translate_args(std::env::args_os());
+    if config::uses_alias(&args) {
+        args = Config::load()
+            .and_then(|config| config.expand_aliases(args))
+            .unwrap_or_else(|err| exit_with_error(&err));
+    }
+    let mut cli = Cli::parse_from(&args);
+    match with_default_flags(&cli, &args) {
+        Ok(Some(args)) => cli = Cli::parse_from(args),
+        Ok(None) => {}
+        Err(err) => exit_with_error(&err),
+    }
+    palette::set_enabled(cli.color.enabled(io::stdout()));
+    diag::set_color(cli.color.enabled(io::stderr()));
+    diag::set_level(cli.quiet, cli.verbose);
+    diag::set_timings(cli.timings);
+    palette::set_ascii_status(cli.ascii_status);
+    interrupt::install();
+    if let Err(err) = run(cli) {
+        exit_with_error(&err);
+    }
+    if interrupt::requested() {
+        diag::warn("interrupted; the output above is incomplete");
+        std::process::exit(interrupt::EXIT_CODE);
+    }
+}
+
+fn exit_with_error(err: &str) -> ! {
+    diag::error(err);
+    std::process::exit(1);
+}
+
+/// `args` with `$NULS_DEFAULT_ARGS` and then the config's `[directories]`
+/// flags for the listed path put ahead of the user's own, so each later
+/// source overrides the ones before it; `None` when neither applies.
+fn with_default_flags(cli: &Cli, args: &[OsString]) -> Result<Option<Vec<OsString>>, String> {
+    let mut flags = config::default_args()?;
+    let list = match &cli.command {
+        None => Some(&cli.list),
+        Some(Commands::List(list)) => Some(&**list),
+        Some(_) => None,
+    };
+    if let Some(list) = list {
+        let config = Config::load()?;
+        if !config.directories.is_empty()
+            && let Ok(dir) = bookmark::expand(&list.path)?.canonicalize()
+        {
+            let home = std::env::var_os("HOME").map(PathBuf::from);
+            flags.extend(config.directory_flags(&dir, home.as_deref()));
+        }
+    }
+    if flags.is_empty() {
+        return Ok(None);
+    }
+    // Flags for an explicit `list` go after it; otherwise after the program name.
+    let at = match (&cli.command, list) {
+        (Some(_), Some(_)) => args.iter().position(|arg| arg == "list").map_or(1, |idx| idx + 1),
+        _ => 1,
+    };
+    let mut with_defaults = args[..at].to_vec();
+    with_defaults.extend(flags.into_iter().map(OsString::from));
+    with_defaults.extend_from_slice(&args[at..]);
+    Ok(Some(with_defaults))
+}
+
+fn run(cli: Cli) -> Result<(), String> {
+    if let Some(dir) = &cli.man {
+        let command = Cli::command().disable_help_subcommand(true);
+        match dir {
+            Some(dir) => clap_mangen::generate_to(command, dir)
+                .map_err(|err| format!("cannot write man pages to {}: {err}", dir.display()))?,
+            None => {
+                let mut page = Vec::new();
+                clap_mangen::Man::new(command)
+                    .render(&mut page)
+                    .map_err(|err| format!("cannot render the man page: {err}"))?;
+                print!("{}", String::from_utf8_lossy(&page));
+            }
+        }
+        return Ok(());
+    }
+    if cli.self_update {
+        #[cfg(feature = "self-update")]
+        {
+            println!("{}", self_update::run()?);
+            return Ok(());
+        }
+        #[cfg(not(feature = "self-update"))]
+        return Err("this nuls was built without --self-update (the `self-update` feature); \
+            update it with the tool that installed it"
+            .to_string());
+    }
+    let config = Config::load()?;
+    let lang = cli.lang.unwrap_or_else(Lang::detect);
+    let session = Session {
+        recency: config.recency_thresholds()?,
+        now: match cli.now {
+            Some(now) => now,
+            None => match std::env::var("NULS_NOW") {
+                Ok(value) => parse_timestamp(&value).map_err(|err| format!("NULS_NOW: {err}"))?,
+                Err(_) => SystemTime::now(),
+            },
+        },
+        strings: lang.strings(),
+        headers: Headers::resolve(&config.headers, lang),
+        shrink: Column::shrink_order(&config.table)?,
+        aligns: Alignments::resolve(&config.table)?,
+        git: GitOptions {
+            timeout: cli.git_timeout,
+            diff_base: cli.git_diff_base,
+        },
+    };
+    if let Some(query) = &cli.history {
+        let history = history::History::load()?;
+        match query {
+            Some(query) => match history.best_match(query, session.now) {
+                Some(dir) => println!("{dir}"),
+                None => return Err(format!("no listed directory matches '{query}'")),
+            },
+            None => print!("{}", history.render(&session)),
+        }
+        return Ok(());
+    }
+    if cli.show_theme {
+        let format = RowFormat {
+            strings: session.strings,
+            headers: &session.headers,
+            aligns: &session.aligns,
+            recency: session.recency,
+            time_style: TimeStyle::Relative,
+            size_style: SizeStyle::default(),
+            group_by: None,
+            now: session.now,
+            totals: false,
+        };
+        let columns = [Column::Index, Column::Name, Column::Type, Column::Size, Column::Modified, Column::Note];
+        print!("{}", render_table(theme::sample_rows(&session), &columns, &format, table_fit(&session)));
+        println!("{}", theme::caption());
+        return Ok(());
+    }
+    match cli.command.unwrap_or(Commands::List(Box::new(cli.list))) {
+        Commands::List(mut args) => {
+            args.path = bookmark::expand(&args.path)?;
+            args.git = args.git.or(config.git);
+            run_list(&args, &session)?;
+            if config.history
+                && !args.directory
+                && !args.stdin
+                && args.path.is_dir()
+                && let Err(err) =
+                    history::History::load().and_then(|mut history| history.record(&args.path, session.now))
+            {
+                diag::warn(&err);
+            }
+            match &args.cd_on_exit {
+                Some(file) => write_cd_target(&args.path, file),
+                None => Ok(()),
+            }
+        }
+        Commands::Tree(mut args) => {
+            args.path = bookmark::expand(&args.path)?;
+            print!("{}", tree::render_tree(&args, &session)?);
+            Ok(())
+        }
+        Commands::Stat(mut args) => {
+            args.path = bookmark::expand(&args.path)?;
+            let options = stat::StatOptions {
+                hash: args.hash,
+                recency: session.recency,
+                now: session.now,
+                strings: session.strings,
+                git: &session.git,
+            };
+            print!("{}", stat::render_stat(&args.path, &options)?);
+            Ok(())
+        }
+        Commands::Diff(mut args) => {
+            args.left = bookmark::expand(&args.left)?;
+            args.right = bookmark::expand(&args.right)?;
+            print!("{}", diff::render_diff(&args, &session)?);
+            Ok(())
+        }
+        Commands::Bookmark(args) => {
+            print!("{}", bookmark::run(args.action.as_ref().unwrap_or(&BookmarkAction::List))?);
+            Ok(())
+        }
+        Commands::Completions(args) => {
+            clap_complete::generate(args.shell, &mut Cli::command(), "nuls", &mut std::io::stdout());
+            Ok(())
+        }
+    }
+}
+
+/// Settings every subcommand shares, resolved once from the config, locale, and clock.
+struct Session {
+    recency: RecencyThresholds,
+    now: SystemTime,
+    strings: &'static Strings,
+    headers: Headers,
+    /// `[table] shrink`: what gives way when the table is too wide.
+    shrink: Vec<Column>,
+    aligns: Alignments,
+    git: GitOptions,
+}
+
+fn run_list(cli: &ListArgs, session: &Session) -> Result<(), String> {
+    let run_started = Instant::now();
+    let path = cli.path.clone();
+    let git = match cli.git.unwrap_or_default() {
+        // `--recent` lists paths git status cannot be matched against.
+        GitMode::Auto if cli.recent.is_some() || cli.stdin => false,
+        mode => mode.enabled(&path),
+    };
+    if !GIT_SUPPORT && (git || cli.git_age || cli.git_author) {
+        warn_no_git("this nuls was built without git support (the `git` feature)");
+    }
+    if cli.security && cfg!(target_os = "linux") && !caps::XATTR {
+        diag::warn(
+            "this nuls was built without xattr support (the `xattr` feature); file capabilities are not shown",
+        );
+    }
+    let started = Instant::now();
+    let git_info = if !git {
+        None
+    } else if cli.directory {
+        load_parent_git_info(&path, &session.git)?
+    } else {
+        load_git_info(&path, &session.git)?
+    };
+    let mut git_time = started.elapsed();
+    let display_base = if cli.full_path {
+        let abs = path
+            .canonicalize()
+            .map(|abs| long_path::friendly(&abs).into_owned())
+            .map_err(|err| format!("cannot canonicalize {}: {err}", path.display()))?;
+        let home = std::env::var_os("HOME").map(PathBuf::from);
+        Some(if cli.tilde {
+            contract_home(&abs, home.as_deref())
+        } else {
+            abs
+        })
+    } else {
+        None
+    };
+    let mut options = CollectOptions {
+        include_hidden: cli.include_hidden,
+        dereference: cli.dereference,
+        display_base: display_base.as_deref(),
+        security: cli.security,
+        disk_size: cli.compressed_size,
+        link_groups: cli.link_groups,
+        who_can: cli.who_can,
+        dir_counts: cli.dir_counts
+            || cli.min_entries.is_some()
+            || cli.sort_order().keys().any(|key| key == SortKey::Entries),
+        statx: cli.statx,
+        names_only: false,
+        filter: EntryFilter::from_args(cli)?,
+    };
+    if cli.tracked_only {
+        options.filter.tracked = Some(load_tracked_paths(&path, &session.git)?);
+    }
+    options.filter.modified_since = cli
+        .hide_older_than
+        .and_then(|secs| session.now.checked_sub(Duration::from_secs(secs)));
+    options.names_only = !cli.full
+        && !cli.directory
+        && !cli.needs_metadata()
+        && !options.dir_counts
+        && options.basic_metadata()
+        && remote::is_remote(&path);
+    let mut omitted = 0;
+    let sort_spec = cli.sort_spec();
+    let started = Instant::now();
+    let mut entries = if cli.directory {
+        collect_self(&path, &options, git_info)?
+    } else if let Some(limit) = cli.limit {
+        let mut top = TopRows::new(limit, sort_spec.clone());
+        if cli.stdin {
+            for_each_listed_path(io::stdin().lock(), &options, WarnAndSkip(|row| top.push(row)))?;
+        } else {
+            for_each_entry(&path, &options, git_info, |row| top.push(row))?;
+        }
+        let (rows, dropped) = top.finish();
+        omitted = dropped;
+        rows
+    } else if let Some(count) = cli.recent {
+        let mut walk = RecentWalk {
+            top: TopRows::new(count, SortSpec::by(SortKey::Modified).tie_break(cli.tie_break)),
+            progress: progress::spinner("scanning"),
+        };
+        let result = for_each_recent_file(&path, &options, &mut walk);
+        walk.progress.finish_and_clear();
+        result?;
+        let (rows, dropped) = walk.top.finish();
+        omitted = dropped;
+        rows
+    } else if cli.stdin {
+        let mut rows = Vec::new();
+        for_each_listed_path(io::stdin().lock(), &options, WarnAndSkip(|row| rows.push(row)))?;
+        rows
+    } else {
+        let sparse = git_info
+            .as_ref()
+            .map(|info| sparse_rows(info, &path, options.include_hidden))
+            .unwrap_or_default();
+        let mut rows = collect_entries(&path, &options, git_info)?;
+        rows.extend(sparse);
+        rows
+    };
+    let collect_time = started.elapsed();
+    if GIT_SUPPORT && (cli.git_age || cli.git_author) {
+        let started = Instant::now();
+        let names: HashSet<&str> = entries.iter().map(|row| row.name_plain.as_str()).collect();
+        let git = GitEnv::for_path(&path.canonicalize().unwrap_or_else(|_| path.clone()), &session.git);
+        let mut commits = git_log::last_commits(&git, &path, &names).unwrap_or_else(|err| {
+            warn_no_git(&err);
+            HashMap::new()
+        });
+        for row in &mut entries {
+            let Some(commit) = commits.remove(&row.name_plain) else {
+                continue;
+            };
+            if cli.git_age {
+                let (_, recency) = format_relative_time(commit.time, session.now, &session.recency, session.strings);
+                row.age_color = Some(recency_color(recency));
+                if palette::ascii_status() {
+                    row.age_text = Some(format_compact_time(commit.time, session.now));
+                }
+            }
+            if cli.git_author {
+                row.author = Some(commit.author);
+            }
+        }
+        git_time += started.elapsed();
+    }
+    if cli.cargo && !cli.directory {
+        tag_cargo_entries(&mut entries, &path);
+    }
+    if cli.npm && !cli.directory {
+        tag_npm_entries(&mut entries, &path);
+    }
+    diag::timed(Phase::Sort, || sort_spec.sort(&mut entries));
+    if cli.link_groups {
+        group_hard_links(&mut entries);
+    }
+    if cli.show_self {
+        let mut self_rows = collect_self_rows(&path, &options, git.then_some(&session.git))?;
+        self_rows.append(&mut entries);
+        entries = self_rows;
+    }
+    let path_of = |row: &EntryRow| if cli.directory { path.clone() } else { path.join(&row.name_plain) };
+    if !cli.trash.is_empty() {
+        #[cfg(feature = "trash")]
+        trash::trash_picked(&mut entries, &cli.trash, path_of)?;
+        #[cfg(not(feature = "trash"))]
+        return Err("this nuls was built without --trash (the `trash` feature)".to_string());
+    }
+    if let Some(template) = &cli.annotate {
+        let paths: Vec<PathBuf> = entries.iter().map(path_of).collect();
+        let annotations = annotate::run_all(template, &paths, cli.annotate_timeout);
+        for (row, annotation) in entries.iter_mut().zip(annotations) {
+            row.annotation = Some(annotation);
+        }
+    }
+    if let Some(substitution) = &cli.rename_preview {
+        let names: Vec<&str> = entries.iter().map(|row| row.name_plain.as_str()).collect();
+        let previews = rename::preview(substitution, &names, |name| path.join(name).symlink_metadata().is_ok());
+        for (row, preview) in entries.iter_mut().zip(previews) {
+            row.renamed = preview;
+        }
+    }
+    if cli.open_by {
+        let holders = diag::timed(Phase::ReadDir, open_files::Holders::scan);
+        for row in &mut entries {
+            row.open_by = path_of(row).canonicalize().ok().and_then(|abs| holders.describe(&abs));
+        }
+    }
+    let delta_note = if cli.delta { Some(record_size_delta(&mut entries, &path, session)?) } else { None };
+    let security_summary = cli.security.then(|| summarize_security(&entries));
+    let histogram = cli.size_histogram.then(|| size_histogram(&entries));
+    let extensions = cli.ext_summary.then(|| render_ext_summary(&entries));
+    let renderer = render::for_args(cli, session);
+    if cli.peek_archives && !renderer.standalone() {
+        entries = expand_archives(entries, path_of);
+    }
+    let mut columns = vec![Column::Index, Column::Name];
+    if cli.rename_preview.is_some() {
+        columns.push(Column::Rename);
+    }
+    columns.extend([Column::Type, Column::Size]);
+    if cli.delta {
+        columns.push(Column::Delta);
+    }
+    if cli.compressed_size {
+        columns.push(Column::Disk);
+    }
+    columns.push(Column::Modified);
+    if options.dir_counts {
+        columns.push(Column::Entries);
+    }
+    if cli.who_can {
+        columns.push(Column::Access);
+    }
+    if cli.git_author {
+        columns.push(Column::Author);
+    }
+    if cli.open_by {
+        columns.push(Column::OpenBy);
+    }
+    if cli.annotate.is_some() {
+        columns.push(Column::Note);
+    }
+    let format = RowFormat {
+        strings: session.strings,
+        headers: &session.headers,
+        aligns: &session.aligns,
+        recency: session.recency,
+        time_style: cli.time_style,
+        size_style: SizeStyle {
+            digits: cli.size_digits.map(usize::from),
+            fixed_width: cli.size_fixed_width,
+        },
+        group_by: cli.group_by,
+        now: session.now,
+        totals: cli.totals,
+    };
+    let elided = if cli.elide_common && renderer.takes_notes() {
+        columns::elide_common(&entries, &mut columns, &format)
+    } else {
+        Vec::new()
+    };
+    let output = render::Output {
+        base: &path,
+        format: &format,
+        columns: &columns,
+        sort: sort_spec.meta(),
+        omitted,
+        path_of: &path_of,
+    };
+    let timed_render = |entries| {
+        let started = Instant::now();
+        let text = diag::timed(Phase::Render, || renderer.render(entries, &output))?;
+        diag::timings(&[("collect", collect_time), ("git", git_time), ("render", started.elapsed())]);
+        diag::print_timings(run_started.elapsed());
+        Ok::<_, String>(text)
+    };
+    if renderer.standalone() {
+        print!("{}", timed_render(entries)?);
+        return Ok(());
+    }
+    if let Some(project) = cli.project.then(|| project::detect(&path)).flatten() {
+        println!("{}", project::render_header(&project));
+    }
+    print!("{}", timed_render(entries)?);
+    if options.names_only && renderer.takes_notes() {
+        let note = "sizes and times not read on this network filesystem (--full reads them)";
+        println!("{}", palette::paint(note, palette::GIT_CLEAN));
+    }
+    if let Some(note) = delta_note.filter(|_| renderer.takes_notes()) {
+        println!("{}", palette::paint(note, palette::GIT_CLEAN));
+    }
+    if let Some(note) = elided_note(&elided, &session.headers) {
+        println!("{}", palette::paint(note, palette::GIT_CLEAN));
+    }
+    if let Some(note) = cli.mount_info.then(|| mounts::note(&path)).flatten() {
+        println!("{}", palette::paint(note, palette::GIT_CLEAN));
+    }
+    if omitted > 0 {
+        let flag = if cli.recent.is_some() { "--recent" } else { "--limit" };
+        println!(
+            "{}",
+            palette::paint(format!("… {omitted} more entries not shown ({flag})"), palette::GIT_CLEAN)
+        );
+    }
+    if let Some(counts) = histogram {
+        print!("{}", format_size_histogram(&counts));
+    }
+    if let Some(table) = extensions {
+        print!("{table}");
+    }
+    if let Some(summary) = security_summary {
+        println!("{}", format_security_summary(&summary));
+    }
+    Ok(())
+}
+
+/// The footer for `--elide-common`, e.g. `every entry: type file, size 4.0 KB`.
+/// Columns that were blank throughout need no mention.
+fn elided_note(elided: &[(Column, String)], headers: &Headers) -> Option<String> {
+    let shared: Vec<String> = elided
+        .iter()
+        .filter(|(_, text)| !text.is_empty())
+        .map(|(column, text)| format!("{} {text}", headers.get(*column)))
+        .collect();
+    (!shared.is_empty()).then(|| format!("every entry: {}", shared.join(", ")))
+}
+
+/// `--cargo`: tags directories holding a crate (or, one level down, several)
+/// and gives `target/` next to a `Cargo.toml` its recursive size, so it sorts
+/// and reads like the build cache it is.
+fn tag_cargo_entries(rows: &mut [EntryRow], base: &Path) {
+    let is_cargo_root = base.join("Cargo.toml").is_file();
+    for row in rows.iter_mut().filter(|row| row.is_dir()) {
+        let dir = base.join(&row.name_plain);
+        if is_cargo_root && row.name_plain == "target" {
+            row.size_bytes = project::tree_size(&dir);
+            row.project_tag = Some("[build output]".to_string());
+        } else {
+            row.project_tag = project::crate_tag(&dir);
+        }
+    }
+}
+
+/// `--npm`: tags package directories with `name@version` and sizes
+/// `node_modules/`, counting its top-level entries. A directory already
+/// tagged by `--cargo` keeps that tag.
+fn tag_npm_entries(rows: &mut [EntryRow], base: &Path) {
+    for row in rows.iter_mut().filter(|row| row.is_dir() && row.project_tag.is_none()) {
+        let dir = base.join(&row.name_plain);
+        if row.name_plain == "node_modules" {
+            let count = fs::read_dir(&dir).map(|entries| entries.count()).unwrap_or(0);
+            row.size_bytes = project::tree_size(&dir);
+            row.project_tag = Some(format!("[dependencies · {count} entries]"));
+        } else {
+            row.project_tag = project::npm_tag(&dir);
+        }
+    }
+}
+
+/// Follows every archive row with one pseudo-row per member. An archive that
+/// cannot be read gets a single row naming the error instead.
+fn expand_archives(rows: Vec<EntryRow>, path_of: impl Fn(&EntryRow) -> PathBuf) -> Vec<EntryRow> {
+    let mut out = Vec::with_capacity(rows.len());
+    for row in rows {
+        if row.is_dir() || !archive::is_archive(&row.name_plain) {
+            out.push(row);
+            continue;
+        }
+        let members = archive::members(&path_of(&row)).unwrap_or_else(|err| {
+            vec![archive::Member {
+                path: format!("({err})"),
+                size: 0,
+                modified: None,
+                is_dir: false,
+            }]
+        });
+        out.push(row);
+        let count = members.len();
+        for (idx, member) in members.into_iter().enumerate() {
+            out.push(EntryRow {
+                name_plain: member.path,
+                entry_type: if member.is_dir { EntryType::Dir } else { EntryType::File },
+                size_bytes: member.size,
+                modified_time: member.modified,
+                archive_branch: Some(if idx + 1 == count { "└─" } else { "├─" }),
+                ..EntryRow::default()
+            });
+        }
+    }
+    out
+}
+
+/// Per-run settings that shape how each directory entry becomes a row.
+struct CollectOptions<'a> {
+    include_hidden: bool,
+    dereference: bool,
+    display_base: Option<&'a Path>,
+    security: bool,
+    disk_size: bool,
+    link_groups: bool,
+    who_can: bool,
+    dir_counts: bool,
+    /// `--statx`, where `basic_metadata` allows it.
+    statx: bool,
+    /// Rows from names and types alone, on a network filesystem without `--full`.
+    names_only: bool,
+    filter: EntryFilter,
+}
+
+impl CollectOptions<'_> {
+    /// Bare-name rows with none of the optional columns or markers.
+    fn plain(include_hidden: bool) -> CollectOptions<'static> {
+        CollectOptions {
+            include_hidden,
+            dereference: false,
+            display_base: None,
+            security: false,
+            disk_size: false,
+            link_groups: false,
+            who_can: false,
+            dir_counts: false,
+            statx: false,
+            names_only: false,
+            filter: EntryFilter::default(),
+        }
+    }
+
+    /// Whether rows need nothing beyond each entry's type, size, mode, and
+    /// modification time, which is all `--statx` reads.
+    fn basic_metadata(&self) -> bool {
+        !(self.dereference
+            || self.security
+            || self.disk_size
+            || self.link_groups
+            || self.who_can
+            || self.filter.checks_access())
+    }
+}
+
+/// Permission and ownership predicates (`--writable`, `--owned-by`, ...);
+/// an entry is listed only if all requested ones hold.
+#[derive(Clone, Debug, Default)]
+struct EntryFilter {
+    writable: bool,
+    readable: bool,
+    executable: bool,
+    owner: Option<u32>,
+    min_entries: Option<u64>,
+    hide_junk: bool,
+    /// `--tracked-only`: tracked files and their parent directories,
+    /// relative to the listed path.
+    tracked: Option<HashSet<String>>,
+    /// `--hide-older-than`: the oldest modified time still listed.
+    modified_since: Option<SystemTime>,
+}
+
+impl EntryFilter {
+    fn from_args(args: &ListArgs) -> Result<EntryFilter, String> {
+        let owner = match &args.owned_by {
+            Some(user) => Some(resolve_uid(user)?),
+            None => None,
+        };
+        Ok(EntryFilter {
+            writable: args.writable,
+            readable: args.readable,
+            executable: args.executable_only,
+            owner,
+            min_entries: args.min_entries,
+            hide_junk: args.hide_junk,
+            tracked: None,
+            modified_since: None,
+        })
+    }
+
+    /// Checks `path` as the current user would access it (following symlinks).
+    fn matches(&self, path: &Path, metadata: &fs::Metadata) -> bool {
+        (!self.writable || can_access(path, Access::Write))
+            && (!self.readable || can_access(path, Access::Read))
+            && (!self.executable || (!path.is_dir() && can_access(path, Access::Execute)))
+            && self.owner.is_none_or(|uid| owner_uid(metadata) == Some(uid))
+            && !(self.hide_junk && !metadata.is_dir() && is_junk(&path.to_string_lossy()))
+    }
+
+    /// Whether `matches` looks at more than the entry's name and type.
+    fn checks_access(&self) -> bool {
+        self.writable || self.readable || self.executable || self.owner.is_some()
+    }
+
+    /// Checks what is only known once the row is built (`--min-entries`,
+    /// `--hide-older-than`, and `--tracked-only` by the row's path under the
+    /// listed one). A row without a modified time is never too old.
+    fn keeps(&self, row: &EntryRow) -> bool {
+        self.min_entries.is_none_or(|min| row.child_count.is_some_and(|count| count >= min))
+            && self
+                .modified_since
+                .is_none_or(|since| row.modified_time.is_none_or(|modified| modified >= since))
+            && self.tracks(&row.name_plain)
+    }
+
+    /// Whether `relative`, a `/`-separated path under the listed one, passes
+    /// `--tracked-only`.
+    fn tracks(&self, relative: &str) -> bool {
+        self.tracked.as_ref().is_none_or(|tracked| tracked.contains(relative))
+    }
+}
+
+fn collect_entries(
+    path: &Path,
+    options: &CollectOptions,
+    git_info: Option<GitInfo>,
+) -> Result<Vec<EntryRow>, String> {
+    let mut rows = Vec::new();
+    for_each_entry(path, options, git_info, |row| rows.push(row))?;
+    Ok(rows)
+}
+
+/// Receives a listing while it is read rather than as one finished `Vec`.
+/// Any `FnMut(EntryRow)` is a visitor that only wants the rows.
+trait Visitor {
+    /// A row that passed every filter.
+    fn on_entry(&mut self, row: EntryRow);
+
+    /// An entry (or, for `--recent`, a subdirectory) that could not be read.
+    /// `Err` ends the listing with it, as the default does; `Ok` skips it.
+    fn on_error(&mut self, err: String) -> Result<(), String> {
+        Err(err)
+    }
+
+    /// Called as each directory is opened, with the entries read before it.
+    fn on_progress(&mut self, _dir: &Path, _read: usize) {}
+}
+
+impl<F: FnMut(EntryRow)> Visitor for F {
+    fn on_entry(&mut self, row: EntryRow) {
+        self(row);
+    }
+}
+
+/// Builds a row for each entry of `path` and hands it to `visitor` as soon
+/// as it is ready, so callers decide how many rows to keep.
+fn for_each_entry(
+    path: &Path,
+    options: &CollectOptions,
+    git_info: Option<GitInfo>,
+    mut visitor: impl Visitor,
+) -> Result<(), String> {
+    visitor.on_progress(path, 0);
+    if options.names_only {
+        return for_each_entry_named(path, options, git_info, visitor);
+    }
+    if options.statx && options.basic_metadata() {
+        #[cfg(target_os = "linux")]
+        return for_each_entry_statx(path, options, git_info, visitor);
+    }
+    let io_path = long_path::extended(path);
+    let mut dir_reader = diag::timed(Phase::ReadDir, || fs::read_dir(&io_path))
+        .map_err(|err| format!("cannot read {}: {err}", path.display()))?;
+    let parent_owner = if options.security {
+        diag::timed(Phase::Stat, || fs::metadata(&io_path)).ok().as_ref().and_then(owner_ids)
+    } else {
+        None
+    };
+
+    while let Some(entry) = diag::timed(Phase::ReadDir, || dir_reader.next()) {
+        if interrupt::requested() {
+            break;
+        }
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(err) => {
+                visitor.on_error(format!("cannot read entry: {err}"))?;
+                continue;
+            }
+        };
+        let name = entry.file_name().to_string_lossy().to_string();
+        let is_hidden = name.starts_with('.');
+        if !options.include_hidden && is_hidden {
+            continue;
+        }
+
+        let file_type = match entry.file_type() {
+            Ok(file_type) => file_type,
+            Err(err) => {
+                visitor.on_error(format!("cannot get type for {}: {err}", name))?;
+                continue;
+            }
+        };
+        let link_metadata = match diag::timed(Phase::Stat, || entry.metadata()) {
+            Ok(metadata) => metadata,
+            Err(err) => {
+                visitor.on_error(format!("cannot read metadata for {}: {err}", name))?;
+                continue;
+            }
+        };
+        if !options.filter.matches(&entry.path(), &link_metadata) {
+            continue;
+        }
+        let display_name = match options.display_base {
+            Some(base) => base.join(&name).display().to_string(),
+            None => name.clone(),
+        };
+        let raw = RawEntry {
+            git_key: name.clone(),
+            name,
+            display_name,
+            path: entry.path(),
+            file_type,
+            link_metadata,
+        };
+        let row = build_row(raw, options, git_info.as_ref(), parent_owner);
+        if options.filter.keeps(&row) {
+            visitor.on_entry(row);
+        }
+    }
+
+    Ok(())
+}
+
+/// `for_each_entry` on a network filesystem: the type comes with the
+/// directory listing, so no entry is stat'ed on its own.
+fn for_each_entry_named(
+    path: &Path,
+    options: &CollectOptions,
+    git_info: Option<GitInfo>,
+    mut visitor: impl Visitor,
+) -> Result<(), String> {
+    let io_path = long_path::extended(path);
+    let mut dir_reader = diag::timed(Phase::ReadDir, || fs::read_dir(&io_path))
+        .map_err(|err| format!("cannot read {}: {err}", path.display()))?;
+    while let Some(entry) = diag::timed(Phase::ReadDir, || dir_reader.next()) {
+        if interrupt::requested() {
+            break;
+        }
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(err) => {
+                visitor.on_error(format!("cannot read entry: {err}"))?;
+                continue;
+            }
+        };
+        let name = entry.file_name().to_string_lossy().to_string();
+        if !options.include_hidden && name.starts_with('.') {
+            continue;
+        }
+        let is_dir = entry.file_type().is_ok_and(|file_type| file_type.is_dir());
+        if options.filter.hide_junk && !is_dir && is_junk(&name) {
+            continue;
+        }
+        let display_name = options.display_base.map(|base| base.join(&name).display().to_string());
+        let row = EntryRow {
+            display_name: display_name.filter(|display| *display != name),
+            git: git_info.as_ref().and_then(|info| info.entries.get(&name)).cloned(),
+            name_plain: name,
+            entry_type: if is_dir { EntryType::Dir } else { EntryType::File },
+            stat_skipped: true,
+            ..EntryRow::default()
+        };
+        if options.filter.keeps(&row) {
+            visitor.on_entry(row);
+        }
+    }
+    Ok(())
+}
+
+/// `for_each_entry` for `--statx`: every name first, then their metadata in
+/// batches. An entry removed between the two is left out; one that cannot
+/// be read goes to the visitor's `on_error`.
+#[cfg(target_os = "linux")]
+fn for_each_entry_statx(
+    path: &Path,
+    options: &CollectOptions,
+    git_info: Option<GitInfo>,
+    mut visitor: impl Visitor,
+) -> Result<(), String> {
+    use std::ffi::{CString, OsStr};
+    use std::os::unix::ffi::OsStrExt;
+
+    let dir = fs::File::open(path).map_err(|err| format!("cannot read {}: {err}", path.display()))?;
+    let mut names = Vec::new();
+    let mut dir_reader = diag::timed(Phase::ReadDir, || fs::read_dir(path))
+        .map_err(|err| format!("cannot read {}: {err}", path.display()))?;
+    while let Some(entry) = diag::timed(Phase::ReadDir, || dir_reader.next()) {
+        if interrupt::requested() {
+            break;
+        }
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(err) => {
+                visitor.on_error(format!("cannot read entry: {err}"))?;
+                continue;
+            }
+        };
+        let name = entry.file_name();
+        if options.include_hidden || !name.as_bytes().starts_with(b".") {
+            // File names never hold a NUL byte.
+            names.push(CString::new(name.into_encoded_bytes()).unwrap_or_default());
+        }
+    }
+    let stats = diag::timed(Phase::Stat, || statx::stat_all(&dir, &names));
+    for (raw_name, stat) in names.iter().zip(stats) {
+        if interrupt::requested() {
+            break;
+        }
+        let entry_path = path.join(OsStr::from_bytes(raw_name.to_bytes()));
+        let stat = match stat {
+            Ok(stat) => stat,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+            Err(err) => {
+                let name = String::from_utf8_lossy(raw_name.to_bytes());
+                visitor.on_error(format!("cannot read metadata for {name}: {err}"))?;
+                continue;
+            }
+        };
+        if options.filter.hide_junk && !stat.is_dir && is_junk(&entry_path.to_string_lossy()) {
+            continue;
+        }
+        let name = String::from_utf8_lossy(raw_name.to_bytes()).to_string();
+        let display_name = options.display_base.map(|base| base.join(&name).display().to_string());
+        let child_count = if options.dir_counts && stat.is_dir {
+            fs::read_dir(&entry_path).ok().map(|entries| entries.count() as u64)
+        } else {
+            None
+        };
+        let row = EntryRow {
+            display_name: display_name.filter(|display| *display != name),
+            git: git_info.as_ref().and_then(|info| info.entries.get(&name)).cloned(),
+            name_plain: name,
+            entry_type: if stat.is_dir { EntryType::Dir } else { EntryType::File },
+            is_executable: stat.mode & 0o111 != 0,
+            size_bytes: stat.size,
+            modified_time: stat.modified,
+            child_count,
+            empty: stat.is_dir && child_count.map_or_else(|| is_empty_dir(&entry_path), |count| count == 0),
+            ..EntryRow::default()
+        };
+        if options.filter.keeps(&row) {
+            visitor.on_entry(row);
+        }
+    }
+    Ok(())
+}
+
+/// Version-control metadata directories that `--recent` never descends into.
+const VCS_DIRS: [&str; 6] = [".git", ".hg", ".svn", ".jj", ".bzr", "_darcs"];
+
+/// Like `for_each_entry`, but walks the whole tree under `root` and visits
+/// only non-directories, named by their path relative to `root`. Symlinked
+/// directories are not followed; unreadable subdirectories and entries go to
+/// `on_error`.
+fn for_each_recent_file(root: &Path, options: &CollectOptions, mut visitor: impl Visitor) -> Result<(), String> {
+    let io_root = long_path::extended(root);
+    let mut pending = vec![(io_root.to_path_buf(), PathBuf::new())];
+    let mut read = 0;
+    while let Some((dir, relative)) = pending.pop() {
+        visitor.on_progress(&root.join(&relative), read);
+        let mut dir_reader = match diag::timed(Phase::ReadDir, || fs::read_dir(&dir)) {
+            Ok(reader) => reader,
+            Err(err) if dir == *io_root => return Err(format!("cannot read {}: {err}", root.display())),
+            Err(err) => {
+                visitor.on_error(format!("cannot read {}: {err}", root.join(&relative).display()))?;
+                continue;
+            }
+        };
+        while let Some(entry) = diag::timed(Phase::ReadDir, || dir_reader.next()) {
+            if interrupt::requested() {
+                return Ok(());
+            }
+            read += 1;
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(err) => {
+                    visitor.on_error(format!("cannot read entry: {err}"))?;
+                    continue;
+                }
+            };
+            let name = entry.file_name().to_string_lossy().to_string();
+            if !options.include_hidden && name.starts_with('.') {
+                continue;
+            }
+            let (file_type, link_metadata) = match (entry.file_type(), diag::timed(Phase::Stat, || entry.metadata())) {
+                (Ok(file_type), Ok(metadata)) => (file_type, metadata),
+                (Err(err), _) | (_, Err(err)) => {
+                    visitor.on_error(format!("cannot read metadata for {}: {err}", relative.join(&name).display()))?;
+                    continue;
+                }
+            };
+            let relative = relative.join(&name);
+            let relative_key = relative.to_string_lossy().replace('\\', "/");
+            if !options.filter.tracks(&relative_key) {
+                continue;
+            }
+            if file_type.is_dir() {
+                if !VCS_DIRS.contains(&name.as_str()) {
+                    pending.push((entry.path(), relative));
+                }
+                continue;
+            }
+            if !options.filter.matches(&entry.path(), &link_metadata) {
+                continue;
+            }
+            let relative = relative_key;
+            let display_name = match options.display_base {
+                Some(base) => base.join(&relative).display().to_string(),
+                None => relative.clone(),
+            };
+            let raw = RawEntry {
+                git_key: relative.clone(),
+                name: relative,
+                display_name,
+                path: entry.path(),
+                file_type,
+                link_metadata,
+            };
+            let row = build_row(raw, options, None, None);
+            if options.filter.keeps(&row) {
+                visitor.on_entry(row);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// `--stdin`: builds a row for each path in `input`, named as given (less a
+/// leading `./`). Paths are NUL-separated when `input` holds a NUL and one
+/// per line otherwise; dotfiles are listed like any other path.
+fn for_each_listed_path(
+    mut input: impl Read,
+    options: &CollectOptions,
+    mut visitor: impl Visitor,
+) -> Result<(), String> {
+    let mut bytes = Vec::new();
+    input
+        .read_to_end(&mut bytes)
+        .map_err(|err| format!("cannot read paths from stdin: {err}"))?;
+    let separator = if bytes.contains(&0) { b'\0' } else { b'\n' };
+    for raw in bytes.split(|&byte| byte == separator) {
+        if interrupt::requested() {
+            break;
+        }
+        let raw = if separator == b'\n' { raw.strip_suffix(b"\r").unwrap_or(raw) } else { raw };
+        if raw.is_empty() {
+            continue;
+        }
+        let path = path_from_bytes(raw.strip_prefix(b"./").filter(|rest| !rest.is_empty()).unwrap_or(raw));
+        let link_metadata = match diag::timed(Phase::Stat, || fs::symlink_metadata(long_path::extended(&path))) {
+            Ok(metadata) => metadata,
+            Err(err) => {
+                visitor.on_error(format!("cannot read metadata for {}: {err}", path.display()))?;
+                continue;
+            }
+        };
+        if !options.filter.matches(&path, &link_metadata) {
+            continue;
+        }
+        let name = path.to_string_lossy().to_string();
+        let display_name = match options.display_base {
+            Some(base) => base.join(&path).display().to_string(),
+            None => name.clone(),
+        };
+        let raw = RawEntry {
+            git_key: name.clone(),
+            name,
+            display_name,
+            file_type: link_metadata.file_type(),
+            path,
+            link_metadata,
+        };
+        let row = build_row(raw, options, None, None);
+        if options.filter.keeps(&row) {
+            visitor.on_entry(row);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    use std::os::unix::ffi::OsStrExt;
+    PathBuf::from(std::ffi::OsStr::from_bytes(bytes))
+}
+
+#[cfg(not(unix))]
+fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    PathBuf::from(String::from_utf8_lossy(bytes).into_owned())
+}
+
+/// A visitor that warns about what it cannot read and lists the rest, for
+/// `--stdin`, where one bad path should not sink the others.
+struct WarnAndSkip<F>(F);
+
+impl<F: FnMut(EntryRow)> Visitor for WarnAndSkip<F> {
+    fn on_entry(&mut self, row: EntryRow) {
+        (self.0)(row);
+    }
+
+    fn on_error(&mut self, err: String) -> Result<(), String> {
+        diag::warn(&err);
+        Ok(())
+    }
+}
+
+/// `--recent`'s visitor: keeps the newest files, passes over what it cannot
+/// read, and shows a spinner while the walk lasts.
+struct RecentWalk {
+    top: TopRows,
+    progress: indicatif::ProgressBar,
+}
+
+impl Visitor for &mut RecentWalk {
+    fn on_entry(&mut self, row: EntryRow) {
+        self.top.push(row);
+    }
+
+    fn on_error(&mut self, _err: String) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn on_progress(&mut self, dir: &Path, read: usize) {
+        self.progress.set_position(read as u64);
+        self.progress.set_message(dir.display().to_string());
+    }
+}
+
+/// Keeps only the first `limit` rows of a stream in sort order, in a heap
+/// whose top is the last row kept: each new row either replaces it or is
+/// dropped, so memory stays at `limit` rows and the work at O(n log limit)
+/// however large the directory.
+struct TopRows {
+    limit: usize,
+    sort: Rc<SortSpec>,
+    heap: BinaryHeap<Ranked>,
+    seen: usize,
+}
+
+/// A row kept by `TopRows`, ordered as `SortSpec::sort` would place it;
+/// `seq` breaks ties in arrival order, as the stable sort does.
+struct Ranked {
+    row: EntryRow,
+    seq: usize,
+    sort: Rc<SortSpec>,
+}
+
+impl Ord for Ranked {
+    fn cmp(&self, other: &Ranked) -> Ordering {
+        self.sort.compare(&self.row, &other.row).then(self.seq.cmp(&other.seq))
+    }
+}
+
+impl PartialOrd for Ranked {
+    fn partial_cmp(&self, other: &Ranked) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Ranked {
+    fn eq(&self, other: &Ranked) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Ranked {}
+
+impl TopRows {
+    fn new(limit: usize, sort: SortSpec) -> TopRows {
+        TopRows {
+            limit,
+            sort: Rc::new(sort),
+            heap: BinaryHeap::with_capacity(limit.min(4096) + 1),
+            seen: 0,
+        }
+    }
+
+    fn push(&mut self, row: EntryRow) {
+        let ranked = Ranked {
+            row,
+            seq: self.seen,
+            sort: Rc::clone(&self.sort),
+        };
+        self.seen += 1;
+        if self.heap.len() < self.limit {
+            self.heap.push(ranked);
+        } else if let Some(mut last) = self.heap.peek_mut().filter(|last| ranked < **last) {
+            *last = ranked;
+        }
+    }
 
-    pub fn paint(text: impl AsRef<str>, color: &str) -> String {
-        format!("{}{}{}", color, text.as_ref(), RESET)
+    /// The kept rows, sorted, and how many were dropped.
+    fn finish(self) -> (Vec<EntryRow>, usize) {
+        let omitted = self.seen - self.heap.len();
+        (self.heap.into_sorted_vec().into_iter().map(|ranked| ranked.row).collect(), omitted)
     }
 }
 
-#[derive(Debug)]
-struct GitInfo {
-    entries: HashMap<String, GitStatus>,
+/// A single row for `path` itself rather than its contents (`-d`). Git status
+/// comes from `git_info` scoped to the parent directory.
+fn collect_self(path: &Path, options: &CollectOptions, git_info: Option<GitInfo>) -> Result<Vec<EntryRow>, String> {
+    collect_self_named(path, path.display().to_string(), options, git_info)
 }
 
-#[derive(Debug, Clone)]
-struct GitStatus {
-    added: Option<u64>,
-    deleted: Option<u64>,
-    dirty: bool,
-    untracked: bool,
+/// Synthetic `.` and `..` rows for `--show-self`, describing the listed
+/// directory and its parent; they stay on top whatever the sort order.
+fn collect_self_rows(
+    path: &Path,
+    options: &CollectOptions,
+    git: Option<&GitOptions>,
+) -> Result<Vec<EntryRow>, String> {
+    let options = CollectOptions {
+        display_base: None,
+        filter: options.filter.clone(),
+        ..*options
+    };
+    let mut rows = Vec::new();
+    for (name, target) in [(".", path.to_path_buf()), ("..", path.join(".."))] {
+        let git_info = match git {
+            Some(git) => load_parent_git_info(&target, git)?,
+            None => None,
+        };
+        rows.extend(collect_self_named(&target, name.to_string(), &options, git_info)?);
+    }
+    Ok(rows)
 }
 
-fn main() {
-    let cli = Cli::parse();
-    if let Err(err) = run(cli) {
-        eprintln!("{} {}", palette::paint("error:", palette::WARN), err);
-        std::process::exit(1);
-    }
+fn collect_self_named(
+    path: &Path,
+    name: String,
+    options: &CollectOptions,
+    git_info: Option<GitInfo>,
+) -> Result<Vec<EntryRow>, String> {
+    let link_metadata = fs::symlink_metadata(long_path::extended(path))
+        .map_err(|err| format!("cannot read metadata for {}: {err}", path.display()))?;
+    let git_key = path
+        .canonicalize()
+        .ok()
+        .and_then(|abs| abs.file_name().map(|file| file.to_string_lossy().to_string()))
+        .unwrap_or_default();
+    let parent_owner = if options.security {
+        let parent = path.canonicalize().ok().and_then(|abs| abs.parent().map(Path::to_path_buf));
+        parent.and_then(|parent| fs::metadata(parent).ok()).as_ref().and_then(owner_ids)
+    } else {
+        None
+    };
+    let raw = RawEntry {
+        display_name: options
+            .display_base
+            .map(|base| base.display().to_string())
+            .unwrap_or_else(|| name.clone()),
+        name,
+        git_key,
+        path: path.to_path_buf(),
+        file_type: link_metadata.file_type(),
+        link_metadata,
+    };
+    Ok(vec![build_row(raw, options, git_info.as_ref(), parent_owner)])
 }
 
-fn run(cli: Cli) -> Result<(), String> {
-    let path = cli.path;
-    let git_info = if cli.git { load_git_info(&path) } else { Ok(None) }?;
-    let entries = collect_entries(
-        &path,
-        cli.include_hidden,
-        cli.sort_modified,
-        cli.reverse,
-        git_info,
-    )?;
-    render_table(entries);
-    Ok(())
+/// What the filesystem told us about one entry, before formatting.
+struct RawEntry {
+    /// Name used for sorting and hidden-file checks.
+    name: String,
+    /// Text shown in the name column (bare name or full path).
+    display_name: String,
+    /// Key into `GitInfo::entries`.
+    git_key: String,
+    path: PathBuf,
+    file_type: fs::FileType,
+    link_metadata: fs::Metadata,
 }
 
-fn collect_entries(
-    path: &PathBuf,
-    include_hidden: bool,
-    sort_modified: bool,
-    reverse: bool,
-    git_info: Option<GitInfo>,
-) -> Result<Vec<EntryRow>, String> {
-    let mut rows = Vec::new();
-    let dir_reader = fs::read_dir(path).map_err(|err| format!("cannot read {}: {err}", path.display()))?;
+fn build_row(
+    raw: RawEntry,
+    options: &CollectOptions,
+    git_info: Option<&GitInfo>,
+    parent_owner: Option<(u32, u32)>,
+) -> EntryRow {
+    let CollectOptions {
+        dereference,
+        security,
+        disk_size,
+        link_groups,
+        who_can,
+        dir_counts,
+        ..
+    } = *options;
+    let RawEntry {
+        name,
+        display_name,
+        git_key,
+        path,
+        file_type,
+        link_metadata,
+    } = raw;
 
-    for entry in dir_reader {
-        let entry = entry.map_err(|err| format!("cannot read entry: {err}"))?;
-        let name = entry.file_name().to_string_lossy().to_string();
-        let is_hidden = name.starts_with('.');
-        if !include_hidden && is_hidden {
-            continue;
+    // A dangling or unreadable target falls back to the link's own metadata.
+    let (metadata, broken_link) = if dereference && file_type.is_symlink() {
+        match diag::timed(Phase::Stat, || fs::metadata(&path)) {
+            Ok(target) => (target, false),
+            Err(_) => (link_metadata, true),
         }
+    } else {
+        (link_metadata, false)
+    };
 
-        let file_type = entry
-            .file_type()
-            .map_err(|err| format!("cannot get type for {}: {err}", name))?;
-        let metadata = entry
-            .metadata()
-            .map_err(|err| format!("cannot read metadata for {}: {err}", name))?;
+    let entry_type = if metadata.is_dir() {
+        EntryType::Dir
+    } else {
+        EntryType::File
+    };
+    let child_count = if dir_counts && metadata.is_dir() {
+        diag::timed(Phase::ReadDir, || fs::read_dir(&path).ok().map(|entries| entries.count() as u64))
+    } else {
+        None
+    };
+    let empty = metadata.is_dir() && child_count.map_or_else(|| is_empty_dir(&path), |count| count == 0);
 
-        let entry_type = if file_type.is_dir() {
-            EntryType::Dir
+    EntryRow {
+        display_name: (display_name != name).then_some(display_name),
+        name_plain: name,
+        entry_type,
+        is_executable: is_executable(&metadata),
+        broken_link,
+        size_bytes: metadata.len(),
+        disk_bytes: if disk_size {
+            allocated_size(&path, &metadata)
         } else {
-            EntryType::File
-        };
-        let is_executable = is_executable(&metadata);
+            None
+        },
+        modified_time: metadata.modified().ok(),
+        git: git_info.and_then(|info| info.entries.get(&git_key)).cloned(),
+        security: if security {
+            security_flags(&path, &metadata, parent_owner)
+        } else {
+            SecurityFlags::default()
+        },
+        hard_link: if link_groups && entry_type == EntryType::File {
+            hard_link_info(&metadata)
+        } else {
+            None
+        },
+        link_tag: None,
+        project_tag: None,
+        annotation: None,
+        archive_branch: None,
+        access: who_can.then(|| effective_access(&path, &metadata)),
+        child_count,
+        empty,
+        not_checked_out: false,
+        stat_skipped: false,
+        age_color: None,
+        age_text: None,
+        author: None,
+        open_by: None,
+        renamed: None,
+        size_change: None,
+    }
+}
 
-        let size = metadata.len();
-        let modified_time = metadata.modified().ok();
-        let (modified_plain, recency) = modified_time
-            .map(format_relative_time)
-            .unwrap_or_else(|| ("unknown".to_string(), Recency::Unknown));
+/// Whether `dir` has no entries, from the first `read_dir` result alone. A
+/// directory that cannot be read is not called empty.
+fn is_empty_dir(dir: &Path) -> bool {
+    diag::timed(Phase::ReadDir, || fs::read_dir(dir).is_ok_and(|mut entries| entries.next().is_none()))
+}
 
-        let name_colored = color_name(&name, entry_type, is_executable, is_hidden);
-        let type_plain = match entry_type {
-            EntryType::Dir => "dir".to_string(),
-            EntryType::File => "file".to_string(),
-        };
+/// Moves rows that share an inode next to the first one (keeping the sort
+/// order otherwise) and tags them `[hardlink #N · M links]`; a lone row whose
+/// other links live elsewhere is tagged `[M links]`.
+fn group_hard_links(rows: &mut Vec<EntryRow>) {
+    let mut members: HashMap<(u64, u64), usize> = HashMap::new();
+    for link in rows.iter().filter_map(|row| row.hard_link) {
+        *members.entry((link.device, link.inode)).or_default() += 1;
+    }
 
-        let git_paths = git_info.as_ref().and_then(|info| info.entries.get(&name));
-        let (name_with_git_plain, name_with_git_colored) = if let Some(g) = git_paths {
-            let (plain_suffix, colored_suffix) = format_git(g).unwrap_or_default();
-            if plain_suffix.is_empty() {
-                (name.clone(), name_colored.clone())
-            } else {
-                (
-                    format!("{name} {plain_suffix}"),
-                    format!("{name_colored} {colored_suffix}"),
-                )
-            }
+    let mut group_ids: HashMap<(u64, u64), usize> = HashMap::new();
+    let mut groups: Vec<Vec<EntryRow>> = Vec::new();
+    for mut row in rows.drain(..) {
+        let Some(link) = row.hard_link else {
+            groups.push(vec![row]);
+            continue;
+        };
+        let key = (link.device, link.inode);
+        let tag = if members[&key] > 1 {
+            let next_id = group_ids.len() + 1;
+            let id = *group_ids.entry(key).or_insert(next_id);
+            format!("[hardlink #{id} · {} links]", link.links)
         } else {
-            (name.clone(), name_colored.clone())
+            format!("[{} links]", link.links)
         };
+        row.link_tag = Some(tag);
 
-        rows.push(EntryRow {
-            name_plain: name.clone(),
-            name_with_git_plain,
-            name_with_git_colored,
-            entry_type_plain: type_plain.clone(),
-            entry_type_colored: palette::paint(type_plain, palette::TYPE),
-            size_plain: format_size(size),
-            size_colored: palette::paint(format_size(size), palette::SIZE),
-            modified_colored: color_modified(&modified_plain, recency),
-            modified_plain,
-            modified_time,
-            is_dir: entry_type == EntryType::Dir,
-        });
+        match groups
+            .iter_mut()
+            .find(|group| group[0].hard_link.is_some_and(|first| (first.device, first.inode) == key))
+        {
+            Some(group) => group.push(row),
+            None => groups.push(vec![row]),
+        }
     }
+    rows.extend(groups.into_iter().flatten());
+}
 
-    sort_rows(&mut rows, sort_modified, reverse);
-
-    Ok(rows)
+/// Rewrites `path` as `~` or `~/rest` when it lies under `home`.
+fn contract_home(path: &Path, home: Option<&Path>) -> PathBuf {
+    match home.and_then(|home| path.strip_prefix(home).ok()) {
+        Some(rest) => Path::new("~").join(rest),
+        None => path.to_path_buf(),
+    }
 }
 
-fn sort_rows(rows: &mut [EntryRow], sort_modified: bool, reverse: bool) {
-    rows.sort_by(|a, b| {
-        let cmp = if sort_modified {
-            compare_modified_desc(&a.modified_time, &b.modified_time)
-                .then_with(|| a.name_with_git_plain.to_lowercase().cmp(&b.name_with_git_plain.to_lowercase()))
-        } else {
-            match (a.is_dir, b.is_dir) {
-                (true, false) => Ordering::Less,
-                (false, true) => Ordering::Greater,
-                _ => a
-                    .name_with_git_plain
-                    .to_lowercase()
-                    .cmp(&b.name_with_git_plain.to_lowercase()),
-            }
-        };
-        if reverse { cmp.reverse() } else { cmp }
-    });
+/// Sorts rows in place. The sort is stable: rows that compare equal after the
+/// primary key and `tie_break` keep their relative input order.
+fn sort_rows(rows: &mut [EntryRow], key: SortKey, reverse: bool, tie_break: TieBreak) {
+    SortSpec::by(key).reverse(reverse).tie_break(tie_break).sort(rows);
 }
 
-fn compare_modified_desc(a: &Option<SystemTime>, b: &Option<SystemTime>) -> Ordering {
-    match (a, b) {
-        (Some(a), Some(b)) => b.cmp(a), // newest first
-        (Some(_), None) => Ordering::Less, // real timestamps before unknown
-        (None, Some(_)) => Ordering::Greater,
-        (None, None) => Ordering::Equal,
+/// Git status for `path` itself, which lives in its parent's listing.
+fn load_parent_git_info(path: &Path, options: &GitOptions) -> Result<Option<GitInfo>, String> {
+    let abs = path
+        .canonicalize()
+        .map_err(|err| format!("cannot canonicalize {}: {err}", path.display()))?;
+    match abs.parent() {
+        Some(parent) => load_git_info(parent, options),
+        None => Ok(None),
     }
 }
 
-fn load_git_info(list_path: &Path) -> Result<Option<GitInfo>, String> {
+/// A git that hangs or fails costs the listing its git info, not the
+/// listing itself: the problem is reported on stderr instead. A build
+/// without the `git` feature never has git info.
+fn load_git_info(list_path: &Path, options: &GitOptions) -> Result<Option<GitInfo>, String> {
+    if !GIT_SUPPORT {
+        return Ok(None);
+    }
     let abs_list = list_path
         .canonicalize()
         .map_err(|err| format!("cannot canonicalize {}: {err}", list_path.display()))?;
+    let git = GitEnv::for_path(&abs_list, options);
+    read_git_info(&git, &abs_list).or_else(|err| {
+        warn_no_git(&err);
+        Ok(None)
+    })
+}
 
-    let root_output = Command::new("git")
-        .args(["rev-parse", "--show-toplevel"])
-        .current_dir(&abs_list)
-        .output();
+fn warn_no_git(err: &str) {
+    diag::warn(&format!("{err}; listing without git info"));
+}
 
-    let Ok(output) = root_output else {
-        return Ok(None);
+fn read_git_info(git: &GitEnv, abs_list: &Path) -> Result<Option<GitInfo>, String> {
+    let git_root = match diag::timed(Phase::GitStatus, || repo_layout(git, abs_list))? {
+        RepoLayout::WorkTree(root) => root,
+        RepoLayout::Outside => return Ok(None),
+        RepoLayout::NoWorkTree(what) => {
+            diag::note(&format!(
+                "{} is {what}; there is no work tree to compare, so --git shows nothing",
+                abs_list.display()
+            ));
+            return Ok(None);
+        }
     };
-    if !output.status.success() {
-        return Ok(None);
-    }
-    let git_root = PathBuf::from(
-        String::from_utf8_lossy(&output.stdout)
-            .trim()
-            .to_string(),
-    );
 
     if !abs_list.starts_with(&git_root) {
         return Ok(None);
     }
 
-    let mut status_map = read_git_status(&git_root)?;
-    merge_numstat(&mut status_map, &git_root)?;
-    let scoped = scope_git_entries(status_map, &git_root, &abs_list);
+    let mut status_map = diag::timed(Phase::GitStatus, || read_git_status(git, &git_root))?;
+    diag::timed(Phase::Numstat, || merge_numstat(git, &mut status_map, &git_root))?;
+    diag::timed(Phase::GitStatus, || merge_index_flags(git, &mut status_map, &git_root))?;
+    let scoped = scope_git_entries(status_map, &git_root, abs_list);
     Ok(Some(GitInfo { entries: scoped }))
 }
 
-fn read_git_status(git_root: &Path) -> Result<HashMap<String, GitStatus>, String> {
-    let output = Command::new("git")
-        .args(["status", "--porcelain=1"])
-        .current_dir(git_root)
-        .output()
-        .map_err(|err| format!("failed to run git status: {err}"))?;
+/// Settings shared by every git call of a run.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct GitOptions {
+    /// `--git-timeout`: how long any one git call may take.
+    timeout: Duration,
+    /// `--git-diff-base`: what line counts are measured against, if not HEAD.
+    diff_base: Option<String>,
+}
+
+/// How git runs for one listed path: with a deadline, and with `GIT_DIR`
+/// and `GIT_WORK_TREE` as they apply to that path. Git reads relative values
+/// against the caller's directory while nuls runs git elsewhere, so they are
+/// passed on as absolute paths, or removed when the listed path is outside
+/// that work tree (git would otherwise treat any directory as the work tree
+/// of `GIT_DIR`).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct GitEnv {
+    /// Absolute git dir and work tree to pass on.
+    repo: Option<(PathBuf, PathBuf)>,
+    options: GitOptions,
+}
+
+impl GitEnv {
+    fn for_path(abs_path: &Path, options: &GitOptions) -> GitEnv {
+        let cwd = std::env::current_dir().ok();
+        GitEnv {
+            options: options.clone(),
+            ..GitEnv::resolve(abs_path, std::env::var_os("GIT_DIR"), std::env::var_os("GIT_WORK_TREE"), cwd)
+        }
+    }
+
+    /// Without `GIT_WORK_TREE`, git takes the current directory as the work
+    /// tree of `GIT_DIR`.
+    fn resolve(
+        abs_path: &Path,
+        git_dir: Option<OsString>,
+        work_tree: Option<OsString>,
+        cwd: Option<PathBuf>,
+    ) -> GitEnv {
+        let (Some(git_dir), Some(cwd)) = (git_dir.filter(|value| !value.is_empty()), cwd) else {
+            return GitEnv::default();
+        };
+        let work_tree = match work_tree.filter(|value| !value.is_empty()) {
+            Some(tree) => cwd.join(tree),
+            None => cwd.clone(),
+        };
+        let work_tree = work_tree.canonicalize().unwrap_or(work_tree);
+        if !abs_path.starts_with(&work_tree) {
+            return GitEnv::default();
+        }
+        GitEnv {
+            repo: Some((cwd.join(git_dir), work_tree)),
+            ..GitEnv::default()
+        }
+    }
+
+    /// `git` run in `dir` with this environment.
+    fn command(&self, dir: &Path) -> Command {
+        let mut command = Command::new("git");
+        command.current_dir(dir);
+        match &self.repo {
+            Some((git_dir, work_tree)) => command.env("GIT_DIR", git_dir).env("GIT_WORK_TREE", work_tree),
+            None => command.env_remove("GIT_DIR").env_remove("GIT_WORK_TREE"),
+        };
+        command
+    }
+
+    /// Runs `git ARGS` in `dir` to completion, killing it at the deadline
+    /// (reported as `ErrorKind::TimedOut`).
+    fn output(&self, dir: &Path, args: &[&str]) -> io::Result<Output> {
+        let mut child = self
+            .command(dir)
+            .args(args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        // Drain both pipes on their own threads so a large status cannot fill
+        // one and stall git. They are left behind on timeout: a grandchild
+        // (a hook, an fsmonitor) may keep the pipes open long after git dies.
+        fn drain(pipe: Option<impl Read + Send + 'static>) -> thread::JoinHandle<Vec<u8>> {
+            thread::spawn(move || {
+                let mut buf = Vec::new();
+                if let Some(mut pipe) = pipe {
+                    let _ = pipe.read_to_end(&mut buf);
+                }
+                buf
+            })
+        }
+        let stdout = drain(child.stdout.take());
+        let stderr = drain(child.stderr.take());
+        if !annotate::wait_with_deadline(&mut child, Instant::now() + self.options.timeout) {
+            let _ = child.kill();
+            let _ = child.wait();
+            let message = format!("timed out after {}s", self.options.timeout.as_secs());
+            return Err(io::Error::new(io::ErrorKind::TimedOut, message));
+        }
+        Ok(Output {
+            status: child.wait()?,
+            stdout: stdout.join().unwrap_or_default(),
+            stderr: stderr.join().unwrap_or_default(),
+        })
+    }
+}
+
+/// Where a directory sits relative to git.
+#[derive(Debug, PartialEq, Eq)]
+enum RepoLayout {
+    /// Inside a work tree (the main one or a linked `git worktree`), whose
+    /// top level is given.
+    WorkTree(PathBuf),
+    /// Inside a bare repository or a `.git` directory, described for the note.
+    NoWorkTree(&'static str),
+    Outside,
+}
+
+/// Asks only questions `rev-parse` can answer without a work tree:
+/// `--show-toplevel` alone fails in bare repositories and inside `.git`.
+/// Only a timeout is an error; a missing git binary means no repository.
+fn repo_layout(git: &GitEnv, dir: &Path) -> Result<RepoLayout, String> {
+    let rev_parse = |flags: &[&str]| match git.output(dir, &[&["rev-parse"], flags].concat()) {
+        Ok(output) => Ok(Some(output).filter(|output| output.status.success())),
+        Err(err) if err.kind() == io::ErrorKind::TimedOut => Err(format!("git rev-parse: {err}")),
+        Err(_) => Ok(None),
+    };
+    let Some(output) = rev_parse(&["--is-bare-repository", "--is-inside-git-dir", "--is-inside-work-tree"])? else {
+        return Ok(RepoLayout::Outside);
+    };
+    let text = String::from_utf8_lossy(&output.stdout);
+    let answers: Vec<bool> = text.lines().map(|line| line.trim() == "true").collect();
+    Ok(match answers[..] {
+        [true, ..] => RepoLayout::NoWorkTree("a bare repository"),
+        [_, true, _] => RepoLayout::NoWorkTree("inside a .git directory"),
+        [_, _, true] => rev_parse(&["--show-toplevel"])?.map_or(RepoLayout::Outside, |output| {
+            RepoLayout::WorkTree(PathBuf::from(String::from_utf8_lossy(&output.stdout).trim()))
+        }),
+        _ => RepoLayout::Outside,
+    })
+}
+
+fn read_git_status(git: &GitEnv, git_root: &Path) -> Result<HashMap<String, GitStatus>, String> {
+    let output = git
+        .output(git_root, &["status", "--porcelain=1"])
+        .map_err(|err| format!("git status: {err}"))?;
 
     if !output.status.success() {
         return Err("git status failed".to_string());
@@ -293,13 +2823,9 @@ fn read_git_status(git_root: &Path) -> Result<HashMap<String, GitStatus>, String
         }
         let code = &line[..2];
         let raw_path = line[3..].trim();
-        let path = if raw_path.contains(" -> ") {
-            raw_path
-                .rsplit_once(" -> ")
-                .map(|(_, new)| new.to_string())
-                .unwrap_or_else(|| raw_path.to_string())
-        } else {
-            raw_path.to_string()
+        let (renamed_from, path) = match raw_path.rsplit_once(" -> ") {
+            Some((old, new)) => (Some(old.to_string()), new.to_string()),
+            None => (None, raw_path.to_string()),
         };
 
         let untracked = code == "??";
@@ -307,61 +2833,172 @@ fn read_git_status(git_root: &Path) -> Result<HashMap<String, GitStatus>, String
         map.insert(
             path,
             GitStatus {
-                added: None,
-                deleted: None,
                 dirty,
                 untracked,
+                renamed_from,
+                ..GitStatus::default()
             },
         );
     }
     Ok(map)
 }
 
-fn merge_numstat(map: &mut HashMap<String, GitStatus>, git_root: &Path) -> Result<(), String> {
-    let output = Command::new("git")
-        .args(["diff", "--numstat", "HEAD"])
-        .current_dir(git_root)
-        .output()
-        .map_err(|err| format!("failed to run git diff: {err}"))?;
-
+/// `HEAD`, or with `--git-diff-base REF` the commit where HEAD and REF
+/// diverged, so the counts cover all work on the branch plus local edits.
+fn diff_base(git: &GitEnv, git_root: &Path) -> Result<String, String> {
+    let Some(reference) = &git.options.diff_base else {
+        return Ok("HEAD".to_string());
+    };
+    if reference.starts_with('-') {
+        return Err(format!("--git-diff-base {reference}: not a commit"));
+    }
+    let output = git
+        .output(git_root, &["merge-base", "HEAD", reference])
+        .map_err(|err| format!("git merge-base: {err}"))?;
     if !output.status.success() {
-        return Err("git diff failed".to_string());
+        return Err(format!("--git-diff-base {reference}: not a commit sharing history with HEAD"));
     }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
 
-    for line in String::from_utf8_lossy(&output.stdout).lines() {
-        let parts: Vec<&str> = line.split('\t').collect();
-        if parts.len() < 3 {
-            continue;
-        }
-        let added = parts[0].parse::<u64>().ok();
-        let deleted = parts[1].parse::<u64>().ok();
-        let path = parts[2].to_string();
-        if added.is_none() && deleted.is_none() {
-            continue;
+/// Line counts from `git diff --numstat` against the base (work tree, so
+/// staged and unstaged edits net out), topped up from the index for paths
+/// whose staged change the work tree no longer shows, e.g. an edit staged
+/// and then undone, or any staged file before the first commit.
+fn merge_numstat(git: &GitEnv, map: &mut HashMap<String, GitStatus>, git_root: &Path) -> Result<(), String> {
+    let base = diff_base(git, git_root)?;
+    let (worktree, cached) = match numstat(git, git_root, &["diff", "--numstat", &base])? {
+        Some(counts) => (counts, numstat(git, git_root, &["diff", "--cached", "--numstat", &base])?),
+        None => {
+            // A repository with no commits yet has no HEAD to diff against;
+            // `--cached` alone compares the index with an empty tree.
+            let has_head = git
+                .output(git_root, &["rev-parse", "--verify", "--quiet", "HEAD"])
+                .is_ok_and(|output| output.status.success());
+            if has_head {
+                return Err("git diff failed".to_string());
+            }
+            (Vec::new(), numstat(git, git_root, &["diff", "--cached", "--numstat"])?)
         }
+    };
+    let counted: HashSet<String> = worktree.iter().map(|(path, ..)| path.clone()).collect();
+    let staged_only = cached.unwrap_or_default().into_iter().filter(|(path, ..)| !counted.contains(path));
+
+    for (path, added, deleted) in worktree.into_iter().chain(staged_only) {
+        let binary = added.is_none() && deleted.is_none();
         map.entry(path)
             .and_modify(|entry| {
                 entry.added = added.or(entry.added);
                 entry.deleted = deleted.or(entry.deleted);
+                entry.binary = binary;
                 entry.dirty = true;
             })
             .or_insert(GitStatus {
                 added,
                 deleted,
+                binary,
                 dirty: true,
-                untracked: false,
+                ..GitStatus::default()
             });
     }
 
     Ok(())
 }
 
+/// One numstat line: path, lines added, lines deleted.
+type LineCounts = (String, Option<u64>, Option<u64>);
+
+/// Counts per changed file (none for a binary file, which numstat counts
+/// as `-`), or `None` when git refuses the diff.
+fn numstat(git: &GitEnv, git_root: &Path, args: &[&str]) -> Result<Option<Vec<LineCounts>>, String> {
+    let output = git.output(git_root, args).map_err(|err| format!("git diff: {err}"))?;
+    if !output.status.success() {
+        return Ok(None);
+    }
+    let mut counts = Vec::new();
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        let parts: Vec<&str> = line.split('\t').collect();
+        if parts.len() < 3 {
+            continue;
+        }
+        let added = parts[0].parse::<u64>().ok();
+        let deleted = parts[1].parse::<u64>().ok();
+        counts.push((numstat_new_path(parts[2]), added, deleted));
+    }
+    Ok(Some(counts))
+}
+
+/// `--tracked-only`: every path git tracks under `list_path`, relative to
+/// it, with each directory on the way to one.
+fn load_tracked_paths(list_path: &Path, options: &GitOptions) -> Result<HashSet<String>, String> {
+    if !GIT_SUPPORT {
+        return Err("--tracked-only needs git support, which this nuls was built without (the `git` feature)".into());
+    }
+    let abs_list = list_path
+        .canonicalize()
+        .map_err(|err| format!("cannot canonicalize {}: {err}", list_path.display()))?;
+    let git = GitEnv::for_path(&abs_list, options);
+    let output = diag::timed(Phase::GitStatus, || git.output(&abs_list, &["ls-files", "-z"]))
+        .map_err(|err| format!("git ls-files: {err}"))?;
+    if !output.status.success() {
+        return Err(format!("--tracked-only: {} is not in a git work tree", list_path.display()));
+    }
+    let mut tracked = HashSet::new();
+    for path in String::from_utf8_lossy(&output.stdout).split('\0').filter(|path| !path.is_empty()) {
+        let mut end = path.len();
+        while tracked.insert(path[..end].to_string()) {
+            match path[..end].rfind('/') {
+                Some(slash) => end = slash,
+                None => break,
+            }
+        }
+    }
+    Ok(tracked)
+}
+
+/// Marks skip-worktree and assume-unchanged files from `git ls-files -v`:
+/// `S` tags skip-worktree, and a lowercase tag means assume-unchanged.
+fn merge_index_flags(git: &GitEnv, map: &mut HashMap<String, GitStatus>, git_root: &Path) -> Result<(), String> {
+    let output = git
+        .output(git_root, &["ls-files", "-v"])
+        .map_err(|err| format!("git ls-files: {err}"))?;
+    if !output.status.success() {
+        return Err("git ls-files failed".to_string());
+    }
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        let Some((tag, path)) = line.split_once(' ') else {
+            continue;
+        };
+        let skip_worktree = tag.eq_ignore_ascii_case("s");
+        let assume_unchanged = tag.chars().all(|c| c.is_ascii_lowercase());
+        if skip_worktree || assume_unchanged {
+            let entry = map.entry(path.to_string()).or_default();
+            entry.skip_worktree = skip_worktree;
+            entry.assume_unchanged = assume_unchanged;
+        }
+    }
+    Ok(())
+}
+
+/// The post-rename path of a numstat line: `old => new` or the compact
+/// `src/{a => b}/lib.rs` form, so a renamed file's line counts land on the
+/// path `git status` reports.
+fn numstat_new_path(raw: &str) -> String {
+    if let (Some(open), Some(close)) = (raw.find('{'), raw.rfind('}'))
+        && let Some((_, new)) = raw[open + 1..close].split_once(" => ")
+    {
+        let joined = format!("{}{new}{}", &raw[..open], &raw[close + 1..]);
+        return joined.replace("//", "/").trim_start_matches('/').to_string();
+    }
+    raw.split_once(" => ").map_or(raw, |(_, new)| new).to_string()
+}
+
 fn scope_git_entries(
     map: HashMap<String, GitStatus>,
     git_root: &Path,
     list_path: &Path,
 ) -> HashMap<String, GitStatus> {
-    let mut scoped = HashMap::new();
+    let mut scoped: HashMap<String, GitStatus> = HashMap::new();
     let rel_base = list_path
         .strip_prefix(git_root)
         .unwrap_or(list_path)
@@ -379,121 +3016,444 @@ fn scope_git_entries(
 
         if let Some(component) = relative.components().next() {
             let key = component.as_os_str().to_string_lossy().to_string();
-            let entry = scoped.entry(key).or_insert(GitStatus {
-                added: None,
-                deleted: None,
-                dirty: false,
-                untracked: false,
-            });
+            let entry = scoped.entry(key).or_default();
+            let nested = relative.components().nth(1).is_some();
+            if nested && (status.dirty || status.untracked) {
+                entry.files = Some(entry.files.unwrap_or(0) + 1);
+            }
+            if nested && status.skip_worktree {
+                entry.skipped = Some(entry.skipped.unwrap_or(0) + 1);
+            } else {
+                entry.skip_worktree |= status.skip_worktree;
+            }
+            if nested && status.assume_unchanged {
+                entry.assumed = Some(entry.assumed.unwrap_or(0) + 1);
+            } else {
+                entry.assume_unchanged |= status.assume_unchanged;
+            }
+            if let Some(old) = status.renamed_from.as_ref().filter(|_| !nested) {
+                let old = Path::new(old);
+                let shown = if rel_base.as_os_str().is_empty() {
+                    old
+                } else {
+                    old.strip_prefix(&rel_base).unwrap_or(old)
+                };
+                entry.renamed_from = Some(shown.to_string_lossy().to_string());
+            }
+            entry.binary |= status.binary && !nested;
             entry.dirty |= status.dirty;
             entry.untracked |= status.untracked;
             entry.added = sum_opts(entry.added, status.added);
             entry.deleted = sum_opts(entry.deleted, status.deleted);
         }
     }
-
-    scoped
+
+    scoped
+}
+
+/// Rows for paths git tracks under `base` that sparse checkout left off
+/// disk: a missing file with the skip-worktree bit, or a missing directory
+/// holding such files.
+fn sparse_rows(info: &GitInfo, base: &Path, include_hidden: bool) -> Vec<EntryRow> {
+    info.entries
+        .iter()
+        .filter(|(name, status)| {
+            (status.skip_worktree || status.skipped.is_some())
+                && (include_hidden || !name.starts_with('.'))
+                && fs::symlink_metadata(base.join(name)).is_err()
+        })
+        .map(|(name, status)| EntryRow {
+            name_plain: name.clone(),
+            entry_type: if status.skipped.is_some() { EntryType::Dir } else { EntryType::File },
+            git: Some(status.clone()),
+            not_checked_out: true,
+            ..EntryRow::default()
+        })
+        .collect()
+}
+
+fn sum_opts(a: Option<u64>, b: Option<u64>) -> Option<u64> {
+    match (a, b) {
+        (Some(x), Some(y)) => Some(x + y),
+        (Some(x), None) => Some(x),
+        (None, Some(y)) => Some(y),
+        (None, None) => None,
+    }
+}
+
+/// Alignment of every column, from `[table] align` over the defaults.
+struct Alignments {
+    aligns: Vec<Align>,
+}
+
+impl Alignments {
+    fn resolve(config: &TableConfig) -> Result<Alignments, String> {
+        if let Some(key) = config.align.keys().find(|key| Column::from_key(key).is_none()) {
+            return Err(format!("table.align: unknown column '{key}'"));
+        }
+        let aligns = Column::ALL
+            .iter()
+            .map(|column| match config.align.get(column.key()) {
+                Some(ColumnAlign::Left) => Align::Left,
+                Some(ColumnAlign::Right) => Align::Right,
+                Some(ColumnAlign::Center) => Align::Center,
+                None if column.spec().numeric() => Align::Right,
+                None => Align::Left,
+            })
+            .collect();
+        Ok(Alignments { aligns })
+    }
+
+    fn get(&self, column: Column) -> Align {
+        self.aligns[column as usize]
+    }
+}
+
+/// Header text for every column, resolved once from the language and the
+/// `[headers]` config section.
+struct Headers {
+    labels: Vec<String>,
+}
+
+impl Headers {
+    fn resolve(config: &HeaderConfig, lang: Lang) -> Headers {
+        let strings = lang.strings();
+        Headers {
+            labels: Column::ALL
+                .iter()
+                .map(|column| config.label(column.key(), lang, column.spec().default_header(strings)))
+                .collect(),
+        }
+    }
+
+    fn get(&self, column: Column) -> &str {
+        &self.labels[column as usize]
+    }
+}
+
+/// Every body cell of a table in one buffer, rather than a plain and a
+/// colored `String` per cell; a cell in a single color keeps only its plain
+/// text and is painted as its row is written.
+struct CellArena {
+    text: String,
+    cells: Vec<ArenaCell>,
+}
+
+struct ArenaCell {
+    plain: Range<usize>,
+    /// The one color of the cell, or `None` when `colored` holds its
+    /// already-colored text.
+    color: Option<&'static str>,
+    colored: Range<usize>,
+    width: usize,
+    align: Align,
+}
+
+/// A cell as `push_row` writes it.
+struct CellRef<'a> {
+    plain: &'a str,
+    color: Option<&'static str>,
+    colored: &'a str,
+    width: usize,
+    align: Align,
+}
+
+impl CellArena {
+    fn with_capacity(cells: usize) -> CellArena {
+        CellArena {
+            text: String::with_capacity(cells * 8),
+            cells: Vec::with_capacity(cells),
+        }
+    }
+
+    /// Adds a cell whose plain text `write` appends, all in `color`.
+    fn push_painted(&mut self, color: &'static str, align: Align, write: impl FnOnce(&mut String)) {
+        let start = self.text.len();
+        write(&mut self.text);
+        let plain = start..self.text.len();
+        self.cells.push(ArenaCell {
+            width: display_width(&self.text[plain.clone()]),
+            plain,
+            color: Some(color),
+            colored: 0..0,
+            align,
+        });
+    }
+
+    fn push(&mut self, plain: &str, colored: &str, align: Align) {
+        let start = self.text.len();
+        self.text.push_str(plain);
+        self.text.push_str(colored);
+        let middle = start + plain.len();
+        self.cells.push(ArenaCell {
+            plain: start..middle,
+            color: None,
+            colored: middle..self.text.len(),
+            width: display_width(plain),
+            align,
+        });
+    }
+
+    /// The cells of each row, `columns` at a time.
+    fn rows(&self, columns: usize) -> impl Iterator<Item = impl Iterator<Item = CellRef<'_>>> {
+        self.cells.chunks(columns.max(1)).map(|row| row.iter().map(|cell| self.get(cell)))
+    }
+
+    fn get(&self, cell: &ArenaCell) -> CellRef<'_> {
+        CellRef {
+            plain: &self.text[cell.plain.clone()],
+            color: cell.color,
+            colored: &self.text[cell.colored.clone()],
+            width: cell.width,
+            align: cell.align,
+        }
+    }
+}
+
+/// The width a table must fit in, and what gives way to get there.
+#[derive(Clone, Copy)]
+struct TableFit<'a> {
+    width: usize,
+    shrink: &'a [Column],
+}
+
+/// Tables fit the terminal they print to, or `$COLUMNS` when set; anything
+/// else (a pipe, a file) gets whole rows.
+fn table_fit(session: &Session) -> Option<TableFit<'_>> {
+    let explicit = std::env::var("COLUMNS").is_ok_and(|value| !value.is_empty());
+    (explicit || std::io::stdout().is_terminal()).then(|| TableFit {
+        width: terminal_width(),
+        shrink: &session.shrink,
+    })
+}
+
+/// Names are never cut shorter than this many cells.
+const MIN_NAME_WIDTH: usize = 8;
+
+type Cells = Vec<(String, String, Align)>;
+
+fn render_table(rows: Vec<EntryRow>, columns: &[Column], format: &RowFormat, fit: Option<TableFit>) -> String {
+    let mut columns = columns.to_vec();
+    let mut time_style = format.time_style;
+    let mut name_limit = None;
+    let mut steps = fit.as_ref().map_or(&[][..], |fit| fit.shrink).iter();
+    // Apply shrink steps one at a time until the table fits or none are left.
+    let (body, header_cells, widths) = loop {
+        let format = RowFormat { time_style, ..*format };
+        let (body, header_cells, widths) = table_cells(&rows, &columns, &format, name_limit);
+        let total = widths.iter().sum::<usize>() + 3 * widths.len() + 1;
+        let (Some(fit), Some(&step)) = (fit.as_ref().filter(|fit| total > fit.width), steps.next()) else {
+            break (body, header_cells, widths);
+        };
+        match step {
+            Column::Modified => time_style = TimeStyle::Compact,
+            Column::Name => {
+                if let Some(at) = columns.iter().position(|&column| column == Column::Name) {
+                    let room = fit.width.saturating_sub(total - widths[at]);
+                    name_limit = Some(room.max(MIN_NAME_WIDTH));
+                }
+            }
+            column if columns.len() > 1 => columns.retain(|&kept| kept != column),
+            _ => {}
+        }
+    };
+
+    // One buffer for the whole table: rows are written into it in place
+    // rather than each allocating its own line and cell strings.
+    let border = palette::paint("│", palette::BORDER);
+    let middle = horizontal_border(&widths, BorderKind::Middle);
+    let mut out = String::with_capacity((widths.iter().sum::<usize>() + 3 * widths.len() + 2) * (rows.len() + 4));
+    let line = |out: &mut String, text: &str| {
+        out.push_str(text);
+        out.push('\n');
+    };
+    line(&mut out, &horizontal_border(&widths, BorderKind::Top));
+    push_row(&mut out, header_cells.iter().map(cell_ref), &widths, &border);
+    out.push('\n');
+    let mut group = None;
+    let mut after_header = true;
+    let mut body_rows = body.rows(columns.len());
+    for (row, data_cells) in rows.iter().zip(body_rows.by_ref()) {
+        if let Some(label) = group_heading(row, format).filter(|label| group.as_ref() != Some(label)) {
+            line(&mut out, &horizontal_border(&widths, BorderKind::Join));
+            line(&mut out, &render_span(&label, &widths));
+            line(&mut out, &horizontal_border(&widths, BorderKind::Split));
+            group = Some(label);
+        } else if after_header {
+            line(&mut out, &middle);
+        }
+        after_header = false;
+        push_row(&mut out, data_cells, &widths, &border);
+        out.push('\n');
+    }
+    if after_header {
+        line(&mut out, &middle);
+    }
+    // `--totals` lines, when `table_cells` added them after the body.
+    let mut totals = body_rows.peekable();
+    if totals.peek().is_some() {
+        line(&mut out, &middle);
+    }
+    for total_cells in totals {
+        push_row(&mut out, total_cells, &widths, &border);
+        out.push('\n');
+    }
+    line(&mut out, &horizontal_border(&widths, BorderKind::Bottom));
+    out
+}
+
+/// Body cells, header cells, and column widths, with names cut to
+/// `name_limit` cells when set.
+fn table_cells(
+    rows: &[EntryRow],
+    columns: &[Column],
+    format: &RowFormat,
+    name_limit: Option<usize>,
+) -> (CellArena, Cells, Vec<usize>) {
+    let mut body = CellArena::with_capacity(rows.len() * columns.len());
+    for (idx, row) in numbered(rows) {
+        for &column in columns {
+            column.push_cell(row, idx, format, name_limit, &mut body);
+        }
+    }
+    if format.totals && !rows.is_empty() {
+        columns::push_totals(rows, columns, format, &mut body);
+    }
+    let header_cells: Cells = columns
+        .iter()
+        .map(|column| {
+            let text = format.headers.get(*column);
+            (text.to_string(), palette::paint(text, column.spec().header_color()), format.aligns.get(*column))
+        })
+        .collect();
+    let widths: Vec<usize> = (0..columns.len())
+        .map(|col| {
+            body.cells
+                .iter()
+                .skip(col)
+                .step_by(columns.len())
+                .map(|cell| cell.width)
+                .chain(std::iter::once(display_width(&header_cells[col].0)))
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    (body, header_cells, widths)
+}
+
+/// Pairs each row with its index-column number; archive members do not
+/// advance the count, so real entries keep the numbers they have without them.
+fn numbered(rows: &[EntryRow]) -> impl Iterator<Item = (usize, &EntryRow)> {
+    rows.iter().scan(0, |next: &mut usize, row| {
+        let idx = next.saturating_sub(usize::from(row.archive_branch.is_some()));
+        if row.archive_branch.is_none() {
+            *next += 1;
+        }
+        Some((idx, row))
+    })
+}
+
+/// Tab-separated plain cells, one line per row after a header line. Tabs
+/// and newlines inside a cell are escaped as `\t` and `\n` so every line
+/// keeps the same number of fields.
+fn render_tabs(rows: &[EntryRow], columns: &[Column], format: &RowFormat) -> String {
+    let line = |cells: Vec<String>| {
+        let escaped: Vec<String> = cells
+            .iter()
+            .map(|cell| cell.replace('\t', "\\t").replace('\n', "\\n"))
+            .collect();
+        format!("{}\n", escaped.join("\t"))
+    };
+    let mut out = line(columns.iter().map(|column| format.headers.get(*column).to_string()).collect());
+    for (idx, row) in numbered(rows) {
+        out.push_str(&line(columns.iter().map(|column| column.cell(row, idx, format).0).collect()));
+    }
+    out
+}
+
+/// One name per line with no table chrome, for scripts and quick scans.
+fn render_oneline(rows: &[EntryRow], format: &RowFormat) -> String {
+    let mut out = String::new();
+    let mut group = None;
+    for row in rows {
+        if let Some(label) = group_heading(row, format).filter(|label| group.as_ref() != Some(label)) {
+            out.push_str(&palette::paint(format!("{label}:"), palette::HEADER));
+            out.push('\n');
+            group = Some(label);
+        }
+        out.push_str(&row.name_cell().1);
+        out.push('\n');
+    }
+    out
 }
 
-fn sum_opts(a: Option<u64>, b: Option<u64>) -> Option<u64> {
-    match (a, b) {
-        (Some(x), Some(y)) => Some(x + y),
-        (Some(x), None) => Some(x),
-        (None, Some(y)) => Some(y),
-        (None, None) => None,
+/// The `--group-by` heading `row` falls under; `None` without grouping and
+/// for archive members, which stay with their archive.
+fn group_heading(row: &EntryRow, format: &RowFormat) -> Option<String> {
+    let GroupBy::ModifiedDay = format.group_by?;
+    if row.archive_branch.is_some() {
+        return None;
     }
+    let strings = format.strings;
+    let Some(ts) = row.modified_time else {
+        return Some(strings.unknown.to_string());
+    };
+    let ts: DateTime<Local> = ts.into();
+    let now: DateTime<Local> = format.now.into();
+    Some(match (now.date_naive() - ts.date_naive()).num_days() {
+        0 => strings.today.to_string(),
+        1 => strings.yesterday.to_string(),
+        2..=7 => strings.last_week.to_string(),
+        _ => format!("{} {}", strings.months[ts.month0() as usize], ts.year()),
+    })
 }
 
-fn render_table(rows: Vec<EntryRow>) {
-    let index_width = format!("{}", rows.len().saturating_sub(1)).len().max(1);
-    let name_width = rows
-        .iter()
-        .map(|row| row.name_with_git_plain.len())
-        .max()
-        .unwrap_or(4)
-        .max("name".len());
-    let type_width = rows
-        .iter()
-        .map(|row| row.entry_type_plain.len())
-        .max()
-        .unwrap_or(4)
-        .max("type".len());
-    let size_width = rows
-        .iter()
-        .map(|row| row.size_plain.len())
-        .max()
-        .unwrap_or(4)
-        .max("size".len());
-    let modified_width = rows
-        .iter()
-        .map(|row| row.modified_plain.len())
-        .max()
-        .unwrap_or(8)
-        .max("modified".len());
-    let widths = vec![index_width, name_width, type_width, size_width, modified_width];
-
-    println!("{}", horizontal_border(&widths, BorderKind::Top));
-    let header_cells = vec![
-        ("#".to_string(), palette::paint("#", palette::INDEX), Align::Right),
-        (
-            "name".to_string(),
-            palette::paint("name", palette::HEADER),
-            Align::Left,
-        ),
-        (
-            "type".to_string(),
-            palette::paint("type", palette::HEADER),
-            Align::Left,
-        ),
-        (
-            "size".to_string(),
-            palette::paint("size", palette::HEADER),
-            Align::Right,
-        ),
-        (
-            "modified".to_string(),
-            palette::paint("modified", palette::HEADER),
-            Align::Left,
-        ),
-    ];
-    println!("{}", render_row(&header_cells, &widths));
-    println!("{}", horizontal_border(&widths, BorderKind::Middle));
-
+/// Names joined by ", " and wrapped so no line exceeds `width` cells
+/// (a single overlong name still gets its own line).
+fn render_commas(rows: &[EntryRow], width: usize) -> String {
+    let mut out = String::new();
+    let mut line_width = 0;
     for (idx, row) in rows.iter().enumerate() {
-        let idx_plain = idx.to_string();
-        let idx_colored = palette::paint(idx_plain.clone(), palette::INDEX);
-        let data_cells = vec![
-            (idx_plain, idx_colored, Align::Right),
-            (
-                row.name_with_git_plain.clone(),
-                row.name_with_git_colored.clone(),
-                Align::Left,
-            ),
-            (
-                row.entry_type_plain.clone(),
-                row.entry_type_colored.clone(),
-                Align::Left,
-            ),
-            (row.size_plain.clone(), row.size_colored.clone(), Align::Right),
-            (
-                row.modified_plain.clone(),
-                row.modified_colored.clone(),
-                Align::Left,
-            ),
-        ];
-        println!(
-            "{}",
-            render_row(&data_cells, &widths)
-        );
+        let separator = if idx + 1 == rows.len() { "" } else { "," };
+        let (name_plain, name_colored) = row.name_cell();
+        let item_width = display_width(&name_plain) + separator.len();
+        if line_width > 0 {
+            if line_width + 1 + item_width > width {
+                out.push('\n');
+                line_width = 0;
+            } else {
+                out.push(' ');
+                line_width += 1;
+            }
+        }
+        out.push_str(&isolate_bidi(&name_plain, &name_colored));
+        out.push_str(separator);
+        line_width += item_width;
     }
+    if !rows.is_empty() {
+        out.push('\n');
+    }
+    out
+}
 
-    println!("{}", horizontal_border(&widths, BorderKind::Bottom));
+/// Columns available for wrapped output: `$COLUMNS`, then the terminal, then 80.
+fn terminal_width() -> usize {
+    std::env::var("COLUMNS")
+        .ok()
+        .and_then(|value| value.parse::<usize>().ok())
+        .filter(|&columns| columns > 0)
+        .or_else(|| terminal_size::terminal_size().map(|(terminal_size::Width(w), _)| usize::from(w)))
+        .unwrap_or(80)
 }
 
 enum BorderKind {
     Top,
     Middle,
     Bottom,
+    /// Closes the columns above a full-width row.
+    Join,
+    /// Reopens the columns below a full-width row.
+    Split,
 }
 
 fn horizontal_border(widths: &[usize], kind: BorderKind) -> String {
@@ -501,6 +3461,8 @@ fn horizontal_border(widths: &[usize], kind: BorderKind) -> String {
         BorderKind::Top => ('┌', '┬', '┐'),
         BorderKind::Middle => ('├', '┼', '┤'),
         BorderKind::Bottom => ('└', '┴', '┘'),
+        BorderKind::Join => ('├', '┴', '┤'),
+        BorderKind::Split => ('├', '┬', '┤'),
     };
 
     let mut line = String::new();
@@ -518,26 +3480,87 @@ fn horizontal_border(widths: &[usize], kind: BorderKind) -> String {
 
 fn render_row(columns: &[(String, String, Align)], widths: &[usize]) -> String {
     let mut line = String::new();
-    line.push_str(&palette::paint("│", palette::BORDER));
-    for ((plain, colored, align), width) in columns.iter().zip(widths.iter()) {
-        let padded = pad_cell(colored, plain, *width, *align);
-        line.push(' ');
-        line.push_str(&padded);
-        line.push(' ');
-        line.push_str(&palette::paint("│", palette::BORDER));
-    }
+    push_row(&mut line, columns.iter().map(cell_ref), widths, &palette::paint("│", palette::BORDER));
     line
 }
 
+fn cell_ref((plain, colored, align): &(String, String, Align)) -> CellRef<'_> {
+    CellRef {
+        plain,
+        color: None,
+        colored,
+        width: display_width(plain),
+        align: *align,
+    }
+}
+
+/// Appends one row, without its newline, to `out`; `border` is the painted
+/// `│` so a whole table paints it once.
+fn push_row<'a>(out: &mut String, cells: impl Iterator<Item = CellRef<'a>>, widths: &[usize], border: &str) {
+    out.push_str(border);
+    for (cell, &width) in cells.zip(widths) {
+        let pad = width.saturating_sub(cell.width);
+        let (left, right) = match cell.align {
+            Align::Left => (0, pad),
+            Align::Right => (pad, 0),
+            Align::Center => (pad / 2, pad - pad / 2),
+        };
+        out.extend(std::iter::repeat_n(' ', left + 1));
+        let isolate = needs_isolation(cell.plain);
+        if isolate {
+            out.push('\u{2068}');
+        }
+        match cell.color {
+            Some(color) => palette::paint_into(out, cell.plain, color),
+            None => out.push_str(cell.colored),
+        }
+        if isolate {
+            out.push('\u{2069}');
+        }
+        out.extend(std::iter::repeat_n(' ', right + 1));
+        out.push_str(border);
+    }
+}
+
+/// One cell spanning every column, e.g. a `--group-by` heading.
+fn render_span(text: &str, widths: &[usize]) -> String {
+    let inner = widths.iter().sum::<usize>() + 3 * widths.len().saturating_sub(1);
+    let border = palette::paint("│", palette::BORDER);
+    let padded = pad_cell(&palette::paint(text, palette::HEADER), text, inner, Align::Left);
+    format!("{border} {padded} {border}")
+}
+
 fn pad_cell(colored: &str, plain: &str, width: usize, align: Align) -> String {
-    let pad = width.saturating_sub(plain.len());
+    let pad = width.saturating_sub(display_width(plain));
     match align {
         Align::Left => format!("{colored}{}", " ".repeat(pad)),
         Align::Right => format!("{}{}", " ".repeat(pad), colored),
+        Align::Center => format!("{}{colored}{}", " ".repeat(pad / 2), " ".repeat(pad - pad / 2)),
     }
 }
 
 fn format_size(size: u64) -> String {
+    let mut text = String::new();
+    write_size(&mut text, size);
+    text
+}
+
+/// `format_size`, appending to `out`.
+fn write_size(out: &mut String, size: u64) {
+    write_size_as(out, size, SizeStyle::default());
+}
+
+/// How sizes are written: `--size-digits` and `--size-fixed-width`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct SizeStyle {
+    /// Decimal places from KB up; by default one below 10 and none above.
+    digits: Option<usize>,
+    /// Room for four integer digits and a two-letter unit on every size.
+    fixed_width: bool,
+}
+
+/// `write_size` in `style`. Bytes never get decimals.
+fn write_size_as(out: &mut String, size: u64, style: SizeStyle) {
     const UNITS: &[(&str, u64)] = &[
         ("B", 1),
         ("KB", 1024),
@@ -556,17 +3579,110 @@ fn format_size(size: u64) -> String {
     }
 
     let value = size as f64 / unit.1 as f64;
-    let text = if value < 10.0 && unit.0 != "B" {
-        format!("{value:.1}")
+    // Fixed-width sizes keep their decimal points in one column.
+    let digits = match style.digits {
+        _ if unit.0 == "B" => 0,
+        Some(digits) => digits,
+        None if value < 10.0 || style.fixed_width => 1,
+        None => 0,
+    };
+    let _ = if style.fixed_width {
+        let widest_digits = style.digits.unwrap_or(1);
+        let width = if widest_digits == 0 { 4 } else { 5 + widest_digits };
+        write!(out, "{value:>width$.digits$} {:<2}", unit.0)
+    } else {
+        write!(out, "{value:.digits$} {}", unit.0)
+    };
+}
+
+/// Parses an `--annotate-timeout` or `--git-timeout` duration such as `2s` or `1m`.
+fn parse_timeout(value: &str) -> Result<Duration, String> {
+    match config::parse_duration(value)? {
+        0 => Err("timeout must be at least 1s".to_string()),
+        secs => Ok(Duration::from_secs(secs)),
+    }
+}
+
+/// Parses a Unix timestamp in seconds, optionally prefixed with `@` as in `date -d @N`.
+fn parse_timestamp(value: &str) -> Result<SystemTime, String> {
+    let trimmed = value.trim();
+    let digits = trimmed.strip_prefix('@').unwrap_or(trimmed);
+    let secs = digits
+        .parse::<u64>()
+        .map_err(|_| format!("invalid timestamp '{value}' (expected Unix seconds)"))?;
+    Ok(SystemTime::UNIX_EPOCH + Duration::from_secs(secs))
+}
+
+/// On-disk size followed by its percentage of the apparent size; a share
+/// under 100% is space saved by compression (or a sparse file).
+fn format_disk_size(allocated: Option<u64>, apparent: u64, style: SizeStyle) -> (String, String) {
+    let Some(allocated) = allocated else {
+        return ("-".to_string(), palette::paint("-", palette::GIT_CLEAN));
+    };
+    let mut size_text = String::new();
+    write_size_as(&mut size_text, allocated, style);
+    if apparent == 0 {
+        return (size_text.clone(), palette::paint(size_text, palette::SIZE));
+    }
+    let percent = (allocated as f64 / apparent as f64 * 100.0).round() as u64;
+    let percent_text = format!("{percent}%");
+    let percent_color = if allocated < apparent {
+        palette::GIT_ADDED
     } else {
-        format!("{value:.0}")
+        palette::GIT_CLEAN
     };
+    (
+        format!("{size_text} {percent_text}"),
+        format!(
+            "{} {}",
+            palette::paint(size_text, palette::SIZE),
+            palette::paint(percent_text, percent_color)
+        ),
+    )
+}
+
+/// Wall-clock text in local time: `14:32` for today, `Tue 14:32` for the
+/// previous six days, and `2024-03-05` for anything older or in the future.
+fn format_hybrid_time(ts: SystemTime, now: SystemTime, strings: &Strings) -> String {
+    let ts: DateTime<Local> = ts.into();
+    let now: DateTime<Local> = now.into();
+    let days_ago = (now.date_naive() - ts.date_naive()).num_days();
+    match days_ago {
+        0 => ts.format("%H:%M").to_string(),
+        1..=6 => {
+            let weekday = strings.weekdays[ts.weekday().num_days_from_monday() as usize];
+            format!("{weekday} {}", ts.format("%H:%M"))
+        }
+        _ => ts.format("%Y-%m-%d").to_string(),
+    }
+}
 
-    format!("{text} {}", unit.0)
+/// `TimeStyle::Compact`: the largest whole unit, with `+` for the future.
+fn format_compact_time(ts: SystemTime, now: SystemTime) -> String {
+    let (sign, secs) = match now.duration_since(ts) {
+        Ok(dur) => ("", dur.as_secs()),
+        Err(err) => ("+", err.duration().as_secs()),
+    };
+    let (value, unit) = match secs {
+        s if s < 60 => (s, "s"),
+        s if s < 3_600 => (s / 60, "m"),
+        s if s < 86_400 => (s / 3_600, "h"),
+        s if s < 604_800 => (s / 86_400, "d"),
+        s if s < 2_629_746 => (s / 604_800, "w"),
+        s if s < 31_557_600 => (s / 2_629_746, "mo"),
+        s => (s / 31_557_600, "y"),
+    };
+    format!("{sign}{value}{unit}")
 }
 
-fn format_relative_time(ts: SystemTime) -> (String, Recency) {
-    let now = SystemTime::now();
+/// Relative text such as "3 days ago" plus the `Recency` bucket that colors it.
+/// Text units are fixed; `thresholds` only move the bucket boundaries.
+fn format_relative_time(
+    ts: SystemTime,
+    now: SystemTime,
+    thresholds: &RecencyThresholds,
+    strings: &Strings,
+) -> (String, Recency) {
     let (past, duration) = match now.duration_since(ts) {
         Ok(dur) => (true, dur),
         Err(err) => (false, err.duration()),
@@ -575,57 +3691,85 @@ fn format_relative_time(ts: SystemTime) -> (String, Recency) {
     let secs = duration.as_secs();
     let recency = if !past {
         Recency::Future
-    } else if secs < 5 {
+    } else if secs < thresholds.just_now {
         Recency::JustNow
-    } else if secs < 60 {
+    } else if secs < thresholds.seconds {
         Recency::Seconds
-    } else if secs < 3_600 {
+    } else if secs < thresholds.minutes {
         Recency::Minutes
-    } else if secs < 86_400 {
+    } else if secs < thresholds.hours {
         Recency::Hours
-    } else if secs < 604_800 {
+    } else if secs < thresholds.days {
         Recency::Days
-    } else if secs < 2_629_746 {
+    } else if secs < thresholds.weeks {
         Recency::Weeks
-    } else if secs < 31_557_600 {
+    } else if secs < thresholds.months {
         Recency::Months
     } else {
         Recency::Years
     };
 
     let text = if recency == Recency::JustNow {
-        "just now".to_string()
+        strings.just_now.to_string()
     } else if !past {
         let (value, unit) = match secs {
-            s if s < 60 => (s, "second"),
-            s if s < 3_600 => (s / 60, "minute"),
-            s if s < 86_400 => (s / 3_600, "hour"),
-            s if s < 604_800 => (s / 86_400, "day"),
-            s => (s / 604_800, "week"),
-        };
-        let plural = if value == 1 { "" } else { "s" };
-        format!("in {value} {unit}{plural}")
+            s if s < 60 => (s, TimeUnit::Second),
+            s if s < 3_600 => (s / 60, TimeUnit::Minute),
+            s if s < 86_400 => (s / 3_600, TimeUnit::Hour),
+            s if s < 604_800 => (s / 86_400, TimeUnit::Day),
+            s => (s / 604_800, TimeUnit::Week),
+        };
+        strings.relative(value, unit, false)
     } else {
         let (value, unit) = match secs {
-            s if s < 60 => (s, "second"),
-            s if s < 3_600 => (s / 60, "minute"),
-            s if s < 86_400 => (s / 3_600, "hour"),
-            s if s < 604_800 => (s / 86_400, "day"),
-            s if s < 2_629_746 => (s / 604_800, "week"),
-            s if s < 31_557_600 => (s / 2_629_746, "month"),
-            s => (s / 31_557_600, "year"),
+            s if s < 60 => (s, TimeUnit::Second),
+            s if s < 3_600 => (s / 60, TimeUnit::Minute),
+            s if s < 86_400 => (s / 3_600, TimeUnit::Hour),
+            s if s < 604_800 => (s / 86_400, TimeUnit::Day),
+            s if s < 2_629_746 => (s / 604_800, TimeUnit::Week),
+            s if s < 31_557_600 => (s / 2_629_746, TimeUnit::Month),
+            s => (s / 31_557_600, TimeUnit::Year),
         };
-        let plural = if value == 1 { "" } else { "s" };
-        format!("{value} {unit}{plural} ago")
+        strings.relative(value, unit, true)
     };
     (text, recency)
 }
 
+/// Built-in junk patterns: editor backups and swap files, merge leftovers,
+/// and OS folder metadata. `*` matches any prefix.
+const JUNK_PATTERNS: [&str; 12] = [
+    "*~",
+    "*.bak",
+    "*.swp",
+    "*.swo",
+    "*.tmp",
+    "*.orig",
+    "*.rej",
+    "#*#",
+    "._*",
+    ".DS_Store",
+    "Thumbs.db",
+    "desktop.ini",
+];
+
+/// Whether the last component of `name` matches one of `JUNK_PATTERNS`.
+fn is_junk(name: &str) -> bool {
+    let base = name.rsplit(['/', '\\']).next().unwrap_or(name);
+    JUNK_PATTERNS.iter().any(|pattern| match pattern.split_once('*') {
+        Some((prefix, suffix)) => {
+            base.len() > prefix.len() + suffix.len() && base.starts_with(prefix) && base.ends_with(suffix)
+        }
+        None => base.eq_ignore_ascii_case(pattern),
+    })
+}
+
 fn color_name(name: &str, entry_type: EntryType, is_executable: bool, is_hidden: bool) -> String {
     match entry_type {
         EntryType::Dir => palette::paint(name, palette::DIR),
         EntryType::File => {
-            if is_hidden {
+            if is_junk(name) {
+                palette::paint(name, palette::JUNK)
+            } else if is_hidden {
                 palette::paint(name, palette::DOTFILE)
             } else if is_executable {
                 palette::paint(name, palette::EXEC)
@@ -638,68 +3782,600 @@ fn color_name(name: &str, entry_type: EntryType, is_executable: bool, is_hidden:
     }
 }
 
-fn format_git(status: &GitStatus) -> Option<(String, String)> {
-    if !status.dirty && !status.untracked {
-        return Some((
-            "".to_string(),
-            palette::paint("(clean)", palette::GIT_CLEAN),
-        ));
+fn format_git(status: &GitStatus) -> Option<(String, String)> {
+    let changed = status.dirty || status.untracked;
+    let flagged =
+        status.skip_worktree || status.assume_unchanged || status.skipped.is_some() || status.assumed.is_some();
+    if !changed && !flagged {
+        return Some((
+            "".to_string(),
+            palette::paint("(clean)", palette::GIT_CLEAN),
+        ));
+    }
+
+    let mut plain_parts = Vec::new();
+    let mut color_parts = Vec::new();
+
+    // `--ascii-status` leads with a letter, as `git status --short` does.
+    let ascii = palette::ascii_status();
+    if ascii && status.untracked {
+        plain_parts.push("?".to_string());
+        color_parts.push(palette::paint("?", palette::GIT_ADDED));
+    } else if ascii && status.dirty {
+        plain_parts.push("M".to_string());
+        color_parts.push(palette::paint("M", palette::GIT_DIRTY));
+    }
+
+    if status.untracked && status.added.is_none() && !ascii {
+        plain_parts.push("+?".to_string());
+        color_parts.push(palette::paint("+?", palette::GIT_ADDED));
+    }
+
+    if let Some(a) = status.added {
+        plain_parts.push(format!("+{a}"));
+        color_parts.push(palette::paint(format!("+{a}"), palette::GIT_ADDED));
+    }
+    if let Some(d) = status.deleted {
+        plain_parts.push(format!("-{d}"));
+        color_parts.push(palette::paint(format!("-{d}"), palette::GIT_REMOVED));
+    }
+
+    if status.binary && plain_parts.is_empty() {
+        plain_parts.push("bin".to_string());
+        color_parts.push(palette::paint("bin", palette::GIT_DIRTY));
+    }
+
+    if changed && plain_parts.is_empty() && status.renamed_from.is_none() {
+        plain_parts.push("dirty".to_string());
+        color_parts.push(palette::paint("dirty", palette::GIT_DIRTY));
+    }
+
+    // Extra facts after the counts, separated by `·`.
+    let mut note = |text: String, color: &str| {
+        let text = if plain_parts.is_empty() { text } else { format!("· {text}") };
+        color_parts.push(palette::paint(&text, color));
+        plain_parts.push(text);
+    };
+    if let Some(old) = &status.renamed_from {
+        note(format!("renamed from {old}"), palette::GIT_DIRTY);
+    }
+    if let Some(files) = status.files {
+        note(format!("{files} {}", if files == 1 { "file" } else { "files" }), palette::GIT_CLEAN);
+    }
+    if status.skip_worktree {
+        note("skip-worktree".to_string(), palette::LINK);
+    }
+    if let Some(skipped) = status.skipped {
+        note(format!("{skipped} not checked out"), palette::LINK);
+    }
+    if status.assume_unchanged {
+        note("assume-unchanged".to_string(), palette::WARN);
+    }
+    if let Some(assumed) = status.assumed {
+        note(format!("{assumed} assume-unchanged"), palette::WARN);
+    }
+
+    let plain = format!("({})", plain_parts.join(" "));
+    let colored = format!("({})", color_parts.join(" "));
+    Some((plain, colored))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Recency {
+    JustNow,
+    Seconds,
+    Minutes,
+    Hours,
+    Days,
+    Weeks,
+    Months,
+    Years,
+    Future,
+    Unknown,
+}
+
+fn color_modified(text: &str, recency: Recency) -> String {
+    palette::paint(text, recency_color(recency))
+}
+
+fn recency_color(recency: Recency) -> &'static str {
+    match recency {
+        Recency::JustNow | Recency::Seconds => palette::MODIFIED_RECENT,
+        Recency::Minutes => palette::MODIFIED_SOON,
+        Recency::Hours => palette::MODIFIED,
+        Recency::Days => palette::MODIFIED_HOURS,
+        Recency::Weeks => palette::MODIFIED_DAYS,
+        Recency::Months => palette::MODIFIED_WEEKS,
+        Recency::Years => palette::MODIFIED_OLD,
+        Recency::Future => palette::MODIFIED_FUTURE,
+        Recency::Unknown => palette::MODIFIED,
+    }
+}
+
+/// Upper bounds (exclusive) and labels of the `--size-histogram` buckets;
+/// the last bucket takes everything above.
+const SIZE_BUCKETS: [(u64, &str); 4] = [
+    (1024, "< 1 KB"),
+    (100 * 1024, "1–100 KB"),
+    (10 * 1024 * 1024, "100 KB–10 MB"),
+    (u64::MAX, "> 10 MB"),
+];
+
+/// File counts per size bucket; directories are skipped since their sizes
+/// describe the filesystem, not their contents.
+fn size_histogram(rows: &[EntryRow]) -> [usize; 4] {
+    let mut counts = [0; 4];
+    for row in rows.iter().filter(|row| !row.is_dir()) {
+        let bucket = SIZE_BUCKETS
+            .iter()
+            .position(|(bound, _)| row.size_bytes < *bound)
+            .unwrap_or(SIZE_BUCKETS.len() - 1);
+        counts[bucket] += 1;
+    }
+    counts
+}
+
+/// One line per bucket: label, count, and a bar scaled to the largest bucket.
+fn format_size_histogram(counts: &[usize; 4]) -> String {
+    const BAR_WIDTH: usize = 24;
+    let max = counts.iter().copied().max().unwrap_or(0).max(1);
+    let label_width = SIZE_BUCKETS.iter().map(|(_, label)| display_width(label)).max().unwrap_or(0);
+    let count_width = counts.iter().map(|count| count.to_string().len()).max().unwrap_or(1);
+    let mut out = format!("{}\n", palette::paint("size histogram (files):", palette::HEADER));
+    for ((_, label), count) in SIZE_BUCKETS.iter().zip(counts) {
+        // Any non-empty bucket gets at least one block so it stays visible.
+        let bar = (count * BAR_WIDTH).div_ceil(max);
+        out.push_str(&format!(
+            "  {label:<label_width$}  {}",
+            palette::paint(format!("{count:>count_width$}"), palette::INDEX)
+        ));
+        if bar > 0 {
+            out.push_str(&format!("  {}", palette::paint("█".repeat(bar), palette::SIZE)));
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// Count and total size of the files with each (lowercased) extension,
+/// largest total first; names without one are grouped as `(none)`.
+fn extension_totals(rows: &[EntryRow]) -> Vec<(String, usize, u64)> {
+    let mut totals: HashMap<String, (usize, u64)> = HashMap::new();
+    for row in rows.iter().filter(|row| !row.is_dir()) {
+        let ext = Path::new(&row.name_plain)
+            .extension()
+            .map(|ext| format!(".{}", ext.to_string_lossy().to_lowercase()))
+            .unwrap_or_else(|| "(none)".to_string());
+        let entry = totals.entry(ext).or_default();
+        entry.0 += 1;
+        entry.1 += row.size_bytes;
+    }
+    let mut totals: Vec<(String, usize, u64)> =
+        totals.into_iter().map(|(ext, (count, size))| (ext, count, size)).collect();
+    totals.sort_by(|a, b| b.2.cmp(&a.2).then_with(|| b.1.cmp(&a.1)).then_with(|| a.0.cmp(&b.0)));
+    totals
+}
+
+/// The `--ext-summary` table: extension, file count, total size, and share
+/// of all file bytes.
+fn render_ext_summary(rows: &[EntryRow]) -> String {
+    let totals = extension_totals(rows);
+    let all_bytes: u64 = totals.iter().map(|(_, _, size)| size).sum();
+    let header = [
+        ("ext", Align::Left),
+        ("files", Align::Right),
+        ("size", Align::Right),
+        ("share", Align::Right),
+    ]
+    .map(|(text, align)| (text.to_string(), palette::paint(text, palette::HEADER), align));
+    let body: Vec<Vec<(String, String, Align)>> = totals
+        .iter()
+        .map(|(ext, count, size)| {
+            let share = if all_bytes == 0 {
+                0.0
+            } else {
+                *size as f64 * 100.0 / all_bytes as f64
+            };
+            [
+                (ext.clone(), palette::TYPE, Align::Left),
+                (count.to_string(), palette::INDEX, Align::Right),
+                (format_size(*size), palette::SIZE, Align::Right),
+                (format!("{share:.1}%"), palette::MODIFIED, Align::Right),
+            ]
+            .into_iter()
+            .map(|(text, color, align)| {
+                let colored = palette::paint(&text, color);
+                (text, colored, align)
+            })
+            .collect()
+        })
+        .collect();
+    render_grid(&header, &body)
+}
+
+/// A bordered table with a header row, each column as wide as its widest cell.
+fn render_grid(header: &[(String, String, Align)], body: &[Vec<(String, String, Align)>]) -> String {
+    let widths: Vec<usize> = (0..header.len())
+        .map(|col| {
+            body.iter()
+                .map(|cells| display_width(&cells[col].0))
+                .chain(std::iter::once(display_width(&header[col].0)))
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+
+    let mut out = String::new();
+    for line in [
+        horizontal_border(&widths, BorderKind::Top),
+        render_row(header, &widths),
+        horizontal_border(&widths, BorderKind::Middle),
+    ] {
+        out.push_str(&line);
+        out.push('\n');
+    }
+    for cells in body {
+        out.push_str(&render_row(cells, &widths));
+        out.push('\n');
+    }
+    out.push_str(&horizontal_border(&widths, BorderKind::Bottom));
+    out.push('\n');
+    out
+}
+
+/// `--cd-on-exit FILE`: writes where a shell should `cd` after listing
+/// `path`, the directory itself or, for a file, the one holding it.
+fn write_cd_target(path: &Path, file: &Path) -> Result<(), String> {
+    let dir = path
+        .canonicalize()
+        .map_err(|err| format!("cannot canonicalize {}: {err}", path.display()))?;
+    let dir = if dir.is_dir() { dir.as_path() } else { dir.parent().unwrap_or(&dir) };
+    fs::write(file, long_path::friendly(dir).to_string_lossy().as_bytes())
+        .map_err(|err| format!("cannot write {}: {err}", file.display()))
+}
+
+/// Fills in each row's `--delta` change against the last snapshot of `dir`
+/// and records the current sizes for the next run. Returns the note shown
+/// under the table.
+fn record_size_delta(rows: &mut [EntryRow], dir: &Path, session: &Session) -> Result<String, String> {
+    let abs = dir
+        .canonicalize()
+        .map_err(|err| format!("cannot canonicalize {}: {err}", dir.display()))?;
+    let mut history = delta::SizeHistory::load()?;
+    let current = delta::Snapshot::of(rows, session.now);
+    let note = match history.get(&abs) {
+        Some(previous) => {
+            for row in rows.iter_mut() {
+                row.size_change = previous.change(row);
+            }
+            let (when, _) = format_relative_time(previous.recorded(), session.now, &session.recency, session.strings);
+            match previous.gone(&current) {
+                0 => format!("size changes since the snapshot from {when}"),
+                1 => format!("size changes since the snapshot from {when}; 1 file gone since"),
+                gone => format!("size changes since the snapshot from {when}; {gone} files gone since"),
+            }
+        }
+        None => "no earlier snapshot of this directory; sizes recorded for the next --delta".to_string(),
+    };
+    if let Err(err) = history.record(&abs, current) {
+        diag::warn(&err);
+    }
+    Ok(note)
+}
+
+fn summarize_security(rows: &[EntryRow]) -> SecuritySummary {
+    let mut summary = SecuritySummary::default();
+    for flags in rows.iter().map(|row| row.security) {
+        summary.world_writable += usize::from(flags.world_writable);
+        summary.setuid += usize::from(flags.setuid);
+        summary.setgid += usize::from(flags.setgid);
+        summary.root_owned += usize::from(flags.root_owned);
+        summary.other_owner += usize::from(flags.other_owner.is_some());
+        summary.other_group += usize::from(flags.other_group.is_some());
+        summary.capabilities += usize::from(flags.capabilities.is_some());
+        summary.immutable += usize::from(flags.immutable);
+        summary.append_only += usize::from(flags.append_only);
+    }
+    summary
+}
+
+fn format_security_summary(summary: &SecuritySummary) -> String {
+    if *summary == SecuritySummary::default() {
+        return format!(
+            "{} {}",
+            palette::paint("security:", palette::HEADER),
+            palette::paint("no issues found", palette::GIT_CLEAN)
+        );
+    }
+    let parts = [
+        (summary.world_writable, "world-writable"),
+        (summary.setuid, "setuid"),
+        (summary.setgid, "setgid"),
+        (summary.root_owned, "root-owned"),
+        (summary.other_owner, "with another owner"),
+        (summary.other_group, "in another group"),
+        (summary.capabilities, "with capabilities"),
+        (summary.immutable, "immutable"),
+        (summary.append_only, "append-only"),
+    ]
+    .into_iter()
+    .map(|(count, label)| {
+        let color = if count > 0 { palette::SECURITY } else { palette::GIT_CLEAN };
+        palette::paint(format!("{count} {label}"), color)
+    })
+    .collect::<Vec<_>>();
+    format!("{} {}", palette::paint("security:", palette::HEADER), parts.join(" · "))
+}
+
+#[cfg(unix)]
+fn owner_uid(metadata: &fs::Metadata) -> Option<u32> {
+    use std::os::unix::fs::MetadataExt;
+    Some(metadata.uid())
+}
+
+#[cfg(not(unix))]
+fn owner_uid(_metadata: &fs::Metadata) -> Option<u32> {
+    None
+}
+
+/// The owner's uid and gid.
+#[cfg(unix)]
+fn owner_ids(metadata: &fs::Metadata) -> Option<(u32, u32)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((metadata.uid(), metadata.gid()))
+}
+
+#[cfg(not(unix))]
+fn owner_ids(_metadata: &fs::Metadata) -> Option<(u32, u32)> {
+    None
+}
+
+/// The user name for `uid`, or the number when the account files lack it.
+#[cfg(unix)]
+fn user_label(uid: u32) -> String {
+    account_names().user_label(uid)
+}
+
+#[cfg(unix)]
+fn group_label(gid: u32) -> String {
+    account_names().group_label(gid)
+}
+
+/// `/etc/passwd` and `/etc/group`, read once for labels.
+#[cfg(unix)]
+fn account_names() -> &'static owner::Owners {
+    static OWNERS: std::sync::OnceLock<owner::Owners> = std::sync::OnceLock::new();
+    OWNERS.get_or_init(owner::Owners::load)
+}
+
+#[cfg(not(unix))]
+fn user_label(uid: u32) -> String {
+    uid.to_string()
+}
+
+#[cfg(not(unix))]
+fn group_label(gid: u32) -> String {
+    gid.to_string()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Access {
+    Read,
+    Write,
+    Execute,
+}
+
+/// Asks the kernel via `access(2)`, so ACLs, read-only mounts, and root's
+/// overrides are all taken into account.
+#[cfg(unix)]
+fn can_access(path: &Path, access: Access) -> bool {
+    use std::os::unix::ffi::OsStrExt;
+    let Ok(c_path) = std::ffi::CString::new(path.as_os_str().as_bytes()) else {
+        return false;
+    };
+    let mode = match access {
+        Access::Read => libc::R_OK,
+        Access::Write => libc::W_OK,
+        Access::Execute => libc::X_OK,
+    };
+    // SAFETY: `c_path` is a valid NUL-terminated string for the duration of the call.
+    unsafe { libc::access(c_path.as_ptr(), mode) == 0 }
+}
+
+/// Windows has no per-user mode bits here: everything readable, writable
+/// unless read-only, and executable by extension.
+#[cfg(not(unix))]
+fn can_access(path: &Path, access: Access) -> bool {
+    match access {
+        Access::Read => fs::metadata(path).is_ok(),
+        Access::Write => fs::metadata(path).is_ok_and(|metadata| !metadata.permissions().readonly()),
+        Access::Execute => path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| ["exe", "bat", "cmd", "com", "ps1"].contains(&ext.to_ascii_lowercase().as_str())),
+    }
+}
+
+/// The current user's rights from the entry's owner, group, and other bits
+/// (whichever class matches first), with root reading and writing anything.
+#[cfg(unix)]
+fn effective_access(_path: &Path, metadata: &fs::Metadata) -> EffectiveAccess {
+    use std::os::unix::fs::MetadataExt;
+    use std::sync::OnceLock;
+    static IDENTITY: OnceLock<(u32, Vec<u32>)> = OnceLock::new();
+    let (uid, gids) = IDENTITY.get_or_init(|| {
+        // SAFETY: these calls only read the process credentials; `getgroups`
+        // writes at most `count` ids into a buffer of that length.
+        unsafe {
+            let mut gids = vec![libc::getegid()];
+            let count = libc::getgroups(0, std::ptr::null_mut());
+            if count > 0 {
+                let mut extra = vec![0 as libc::gid_t; count as usize];
+                let filled = libc::getgroups(count, extra.as_mut_ptr());
+                extra.truncate(filled.max(0) as usize);
+                gids.extend(extra);
+            }
+            (libc::geteuid(), gids)
+        }
+    });
+    interpret_mode(metadata.mode(), metadata.uid(), metadata.gid(), metadata.is_dir(), *uid, gids)
+}
+
+/// Applies the first matching class of `mode` for the user `uid` in `gids`.
+#[cfg(unix)]
+fn interpret_mode(mode: u32, owner: u32, group: u32, is_dir: bool, uid: u32, gids: &[u32]) -> EffectiveAccess {
+    let (class, bits) = if uid == 0 {
+        let any_execute = mode & 0o111 != 0 || is_dir;
+        (AccessClass::Root, 0o6 | u32::from(any_execute))
+    } else if owner == uid {
+        (AccessClass::Owner, (mode >> 6) & 0o7)
+    } else if gids.contains(&group) {
+        (AccessClass::Group, (mode >> 3) & 0o7)
+    } else {
+        (AccessClass::Other, mode & 0o7)
+    };
+    EffectiveAccess {
+        read: bits & 0o4 != 0,
+        write: bits & 0o2 != 0,
+        execute: bits & 0o1 != 0,
+        class: Some(class),
+    }
+}
+
+#[cfg(not(unix))]
+fn effective_access(path: &Path, metadata: &fs::Metadata) -> EffectiveAccess {
+    EffectiveAccess {
+        read: true,
+        write: !metadata.permissions().readonly(),
+        execute: metadata.is_dir() || can_access(path, Access::Execute),
+        class: None,
+    }
+}
+
+#[cfg(unix)]
+fn resolve_uid(user: &str) -> Result<u32, String> {
+    owner::Owners::load().uid_for(user).ok_or_else(|| {
+        if owner::LOOKUP {
+            format!("unknown user '{user}'")
+        } else {
+            format!("unknown user '{user}': this nuls was built without user names (the `owners` feature); use a uid")
+        }
+    })
+}
+
+#[cfg(not(unix))]
+fn resolve_uid(_user: &str) -> Result<u32, String> {
+    Err("--owned-by is only supported on Unix".to_string())
+}
+
+#[cfg(unix)]
+fn security_flags(path: &Path, metadata: &fs::Metadata, parent_owner: Option<(u32, u32)>) -> SecurityFlags {
+    use std::os::unix::fs::MetadataExt;
+    let mode = metadata.mode();
+    let (immutable, append_only) = chattr_flags(path, metadata);
+    let (uid, gid) = (metadata.uid(), metadata.gid());
+    // Entries owned by others are normal in root's directories (/home, /tmp).
+    let user_dir = parent_owner.filter(|&(parent_uid, _)| parent_uid != 0);
+    SecurityFlags {
+        // Symlinks are always 0777; their target's mode is what matters.
+        world_writable: !metadata.file_type().is_symlink() && mode & 0o002 != 0,
+        setuid: mode & 0o4000 != 0,
+        setgid: mode & 0o2000 != 0,
+        root_owned: uid == 0 && user_dir.is_some(),
+        other_owner: user_dir.filter(|&(parent_uid, _)| uid != 0 && uid != parent_uid).map(|_| uid),
+        other_group: user_dir.filter(|&(_, parent_gid)| gid != parent_gid).map(|_| gid),
+        capabilities: if metadata.is_file() { caps::read(path) } else { None },
+        immutable,
+        append_only,
+    }
+}
+
+/// The immutable and append-only inode flags, read with `FS_IOC_GETFLAGS`
+/// as `lsattr` does. Only files and directories are opened, and only those
+/// the user can read report any.
+#[cfg(target_os = "linux")]
+fn chattr_flags(path: &Path, metadata: &fs::Metadata) -> (bool, bool) {
+    use rustix::fs::{IFlags, Mode, OFlags};
+    if !metadata.is_file() && !metadata.is_dir() {
+        return (false, false);
+    }
+    let flags = OFlags::RDONLY | OFlags::NONBLOCK | OFlags::NOCTTY | OFlags::CLOEXEC;
+    match rustix::fs::open(path, flags, Mode::empty()).and_then(rustix::fs::ioctl_getflags) {
+        Ok(flags) => (flags.contains(IFlags::IMMUTABLE), flags.contains(IFlags::APPEND)),
+        Err(_) => (false, false),
     }
+}
 
-    let mut plain_parts = Vec::new();
-    let mut color_parts = Vec::new();
+#[cfg(all(unix, not(target_os = "linux")))]
+fn chattr_flags(_path: &Path, _metadata: &fs::Metadata) -> (bool, bool) {
+    (false, false)
+}
 
-    if status.untracked && status.added.is_none() {
-        plain_parts.push("+?".to_string());
-        color_parts.push(palette::paint("+?", palette::GIT_ADDED));
-    }
+#[cfg(not(unix))]
+fn security_flags(_path: &Path, _metadata: &fs::Metadata, _parent_owner: Option<(u32, u32)>) -> SecurityFlags {
+    SecurityFlags::default()
+}
 
-    if let Some(a) = status.added {
-        plain_parts.push(format!("+{a}"));
-        color_parts.push(palette::paint(format!("+{a}"), palette::GIT_ADDED));
+/// Bytes actually allocated on disk: `st_blocks` on Unix (what `du` reports,
+/// compressed on ZFS), the file's extents on btrfs (whose `st_blocks` counts
+/// compressed data uncompressed), `GetCompressedFileSizeW` on Windows (NTFS
+/// compression).
+#[cfg(unix)]
+fn allocated_size(path: &Path, metadata: &fs::Metadata) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    #[cfg(target_os = "linux")]
+    if metadata.is_file()
+        && let Some(size) = fiemap::btrfs_disk_size(path)
+    {
+        return Some(size);
     }
-    if let Some(d) = status.deleted {
-        plain_parts.push(format!("-{d}"));
-        color_parts.push(palette::paint(format!("-{d}"), palette::GIT_REMOVED));
+    #[cfg(not(target_os = "linux"))]
+    let _ = path;
+    Some(metadata.blocks() * 512)
+}
+
+#[cfg(windows)]
+fn allocated_size(path: &Path, metadata: &fs::Metadata) -> Option<u64> {
+    use std::os::windows::ffi::OsStrExt;
+
+    #[link(name = "kernel32")]
+    unsafe extern "system" {
+        fn GetCompressedFileSizeW(file_name: *const u16, file_size_high: *mut u32) -> u32;
     }
 
-    if plain_parts.is_empty() {
-        plain_parts.push("dirty".to_string());
-        color_parts.push(palette::paint("dirty", palette::GIT_DIRTY));
+    if metadata.is_dir() {
+        return None;
+    }
+    // Unlike `std::fs`, the raw call does not lengthen paths past MAX_PATH itself.
+    let path = long_path::extended(path);
+    let wide: Vec<u16> = path.as_os_str().encode_wide().chain(std::iter::once(0)).collect();
+    let mut high = 0u32;
+    // SAFETY: `wide` is NUL-terminated and `high` is a valid out-pointer.
+    let low = unsafe { GetCompressedFileSizeW(wide.as_ptr(), &mut high) };
+    if low == u32::MAX && std::io::Error::last_os_error().raw_os_error() != Some(0) {
+        return None;
     }
+    Some((u64::from(high) << 32) | u64::from(low))
+}
 
-    let plain = format!("({})", plain_parts.join(" "));
-    let colored = format!("({})", color_parts.join(" "));
-    Some((plain, colored))
+#[cfg(not(any(unix, windows)))]
+fn allocated_size(_path: &Path, _metadata: &fs::Metadata) -> Option<u64> {
+    None
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
-enum Recency {
-    JustNow,
-    Seconds,
-    Minutes,
-    Hours,
-    Days,
-    Weeks,
-    Months,
-    Years,
-    Future,
-    Unknown,
+#[cfg(unix)]
+fn hard_link_info(metadata: &fs::Metadata) -> Option<HardLink> {
+    use std::os::unix::fs::MetadataExt;
+    (metadata.nlink() > 1).then(|| HardLink {
+        device: metadata.dev(),
+        inode: metadata.ino(),
+        links: metadata.nlink(),
+    })
 }
 
-fn color_modified(text: &str, recency: Recency) -> String {
-    let color = match recency {
-        Recency::JustNow | Recency::Seconds => palette::MODIFIED_RECENT,
-        Recency::Minutes => palette::MODIFIED_SOON,
-        Recency::Hours => palette::MODIFIED,
-        Recency::Days => palette::MODIFIED_HOURS,
-        Recency::Weeks => palette::MODIFIED_DAYS,
-        Recency::Months => palette::MODIFIED_WEEKS,
-        Recency::Years => palette::MODIFIED_OLD,
-        Recency::Future => palette::MODIFIED_FUTURE,
-        Recency::Unknown => palette::MODIFIED,
-    };
-    palette::paint(text, color)
+#[cfg(not(unix))]
+fn hard_link_info(_metadata: &fs::Metadata) -> Option<HardLink> {
+    None
 }
 
 #[cfg(unix)]
@@ -727,7 +4403,6 @@ fn help_styles() -> Styles {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::time::Duration;
 
     #[test]
     fn size_formats_human_readable() {
@@ -735,20 +4410,54 @@ mod tests {
         assert_eq!(format_size(1024), "1.0 KB");
         assert_eq!(format_size(1536), "1.5 KB");
         assert_eq!(format_size(12 * 1024 * 1024), "12 MB");
+        let styled = |size, digits, fixed_width| {
+            let mut text = String::new();
+            write_size_as(&mut text, size, SizeStyle { digits, fixed_width });
+            text
+        };
+        assert_eq!(styled(12 * 1024 * 1024, Some(2), false), "12.00 MB");
+        assert_eq!(styled(1536, Some(0), false), "2 KB");
+        assert_eq!(styled(4096, None, true), "   4.0 KB");
+        assert_eq!(styled(12 * 1024 * 1024, None, true), "  12.0 MB");
+        assert_eq!(styled(512, None, true), "   512 B ");
+        assert_eq!(styled(1536, Some(0), true), "   2 KB");
+    }
+
+    #[test]
+    fn disk_size_shows_share_of_apparent() {
+        assert_eq!(format_disk_size(Some(1024), 4096, SizeStyle::default()).0, "1.0 KB 25%");
+        assert_eq!(format_disk_size(Some(4096), 100, SizeStyle::default()).0, "4.0 KB 4096%");
+        assert_eq!(format_disk_size(Some(0), 0, SizeStyle::default()).0, "0 B");
+        assert_eq!(format_disk_size(None, 10, SizeStyle::default()).0, "-");
     }
 
     #[test]
     fn relative_time_buckets_future_and_past() {
         let now = SystemTime::now();
-        let (text_now, bucket_now) = format_relative_time(now - Duration::from_secs(3));
+        let (text_now, bucket_now) = format_relative_time(
+            now - Duration::from_secs(3),
+            now,
+            &RecencyThresholds::default(),
+            &i18n::EN,
+        );
         assert_eq!(bucket_now, Recency::JustNow);
         assert_eq!(text_now, "just now");
 
-        let (text_future, bucket_future) = format_relative_time(now + Duration::from_secs(90));
+        let (text_future, bucket_future) = format_relative_time(
+            now + Duration::from_secs(90),
+            now,
+            &RecencyThresholds::default(),
+            &i18n::EN,
+        );
         assert_eq!(bucket_future, Recency::Future);
         assert!(text_future.starts_with("in "));
 
-        let (text_hours, bucket_hours) = format_relative_time(now - Duration::from_secs(3_600));
+        let (text_hours, bucket_hours) = format_relative_time(
+            now - Duration::from_secs(3_600),
+            now,
+            &RecencyThresholds::default(),
+            &i18n::EN,
+        );
         assert_eq!(bucket_hours, Recency::Hours);
         assert!(text_hours.ends_with("ago"));
     }
@@ -756,13 +4465,140 @@ mod tests {
     #[test]
     fn relative_time_months_and_years() {
         let now = SystemTime::now();
-        let (_, bucket_months) = format_relative_time(now - Duration::from_secs(40 * 86_400));
+        let (_, bucket_months) = format_relative_time(
+            now - Duration::from_secs(40 * 86_400),
+            now,
+            &RecencyThresholds::default(),
+            &i18n::EN,
+        );
         assert_eq!(bucket_months, Recency::Months);
 
-        let (_, bucket_years) = format_relative_time(now - Duration::from_secs(370 * 86_400));
+        let (_, bucket_years) = format_relative_time(
+            now - Duration::from_secs(370 * 86_400),
+            now,
+            &RecencyThresholds::default(),
+            &i18n::EN,
+        );
         assert_eq!(bucket_years, Recency::Years);
     }
 
+    #[test]
+    fn relative_time_is_localized() {
+        let now = SystemTime::now();
+        let (text_es, _) = format_relative_time(
+            now - Duration::from_secs(3 * 86_400),
+            now,
+            &RecencyThresholds::default(),
+            Lang::Es.strings(),
+        );
+        assert_eq!(text_es, "hace 3 días");
+
+        let (text_de, _) = format_relative_time(
+            now - Duration::from_secs(3_600),
+            now,
+            &RecencyThresholds::default(),
+            Lang::De.strings(),
+        );
+        assert_eq!(text_de, "vor 1 Stunde");
+
+        let (text_fr, _) = format_relative_time(
+            now + Duration::from_secs(90),
+            now,
+            &RecencyThresholds::default(),
+            Lang::Fr.strings(),
+        );
+        assert_eq!(text_fr, "dans 1 minute");
+    }
+
+    #[test]
+    fn recency_thresholds_move_buckets_not_text() {
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+        let thresholds = RecencyThresholds {
+            seconds: 3_600,
+            ..RecencyThresholds::default()
+        };
+        let (text, bucket) = format_relative_time(now - Duration::from_secs(1_800), now, &thresholds, &i18n::EN);
+        assert_eq!(text, "30 minutes ago");
+        assert_eq!(bucket, Recency::Seconds);
+    }
+
+    #[test]
+    fn hybrid_time_narrows_by_age() {
+        let now = SystemTime::now();
+        let local = |ts: SystemTime| DateTime::<Local>::from(ts);
+        let midnight = local(now).date_naive().and_hms_opt(0, 0, 0).expect("valid time");
+        let secs_since_midnight = (local(now).naive_local() - midnight).num_seconds().max(0) as u64;
+
+        let earlier_today = now - Duration::from_secs(secs_since_midnight / 2);
+        assert_eq!(
+            format_hybrid_time(earlier_today, now, &i18n::EN),
+            local(earlier_today).format("%H:%M").to_string()
+        );
+
+        let three_days_ago = now - Duration::from_secs(3 * 86_400);
+        let text = format_hybrid_time(three_days_ago, now, &i18n::EN);
+        assert_eq!(text.len(), "Tue 14:32".len());
+        assert!(i18n::EN.weekdays.iter().any(|day| text.starts_with(day)));
+
+        let last_month = now - Duration::from_secs(40 * 86_400);
+        assert_eq!(
+            format_hybrid_time(last_month, now, &i18n::EN),
+            local(last_month).format("%Y-%m-%d").to_string()
+        );
+    }
+
+    #[test]
+    fn relative_time_uses_supplied_now() {
+        let base = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+        let now = base + Duration::from_secs(2 * 86_400);
+        let (text, bucket) = format_relative_time(base, now, &RecencyThresholds::default(), &i18n::EN);
+        assert_eq!(text, "2 days ago");
+        assert_eq!(bucket, Recency::Days);
+    }
+
+    #[test]
+    fn timestamp_parses_unix_seconds() {
+        let expected = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        assert_eq!(parse_timestamp("1700000000"), Ok(expected));
+        assert_eq!(parse_timestamp("@1700000000"), Ok(expected));
+        assert!(parse_timestamp("yesterday").is_err());
+
+        let cli = Cli::try_parse_from(["nuls", "--now", "1700000000"]).expect("parse ok");
+        assert_eq!(cli.now, Some(expected));
+    }
+
+    #[test]
+    fn contract_home_uses_tilde_only_under_home() {
+        let home = Path::new("/home/ana");
+        assert_eq!(contract_home(Path::new("/home/ana/code/x"), Some(home)), PathBuf::from("~/code/x"));
+        assert_eq!(contract_home(Path::new("/home/ana"), Some(home)), PathBuf::from("~"));
+        assert_eq!(contract_home(Path::new("/home/anabel"), Some(home)), PathBuf::from("/home/anabel"));
+        assert_eq!(contract_home(Path::new("/tmp"), None), PathBuf::from("/tmp"));
+
+        assert!(Cli::try_parse_from(["nuls", "--tilde"]).is_err());
+        assert!(Cli::try_parse_from(["nuls", "--full-path", "--tilde"]).is_ok());
+    }
+
+    #[test]
+    fn lang_parses_locale_codes() {
+        assert_eq!(Lang::from_code("pt_BR.UTF-8"), Some(Lang::Pt));
+        assert_eq!(Lang::from_code("de"), Some(Lang::De));
+        assert_eq!(Lang::from_code("C"), Some(Lang::En));
+        assert_eq!(Lang::from_code("xx_XX"), None);
+
+        let cli = Cli::try_parse_from(["nuls", "--lang", "es"]).expect("parse ok");
+        assert_eq!(cli.lang, Some(Lang::Es));
+        assert!(Cli::try_parse_from(["nuls", "--lang", "klingon"]).is_err());
+    }
+
+    #[test]
+    fn color_flag_parses() {
+        let cli = Cli::try_parse_from(["nuls", "--color", "never"]).expect("parse ok");
+        assert_eq!(cli.color, ColorMode::Never);
+        assert!(!ColorMode::Never.enabled(io::stdout()));
+        assert!(ColorMode::Always.enabled(io::stdout()));
+    }
+
     #[test]
     fn modified_color_matches_recency() {
         let colored = color_modified("value", Recency::Years);
@@ -773,10 +4609,10 @@ mod tests {
     #[test]
     fn cli_flags_parse() {
         let cli = Cli::try_parse_from(["nuls", "-atr", "/tmp"]).expect("parse ok");
-        assert!(cli.include_hidden);
-        assert!(cli.sort_modified);
-        assert!(cli.reverse);
-        assert_eq!(cli.path, PathBuf::from("/tmp"));
+        assert!(cli.list.include_hidden);
+        assert!(cli.list.sort_modified);
+        assert!(cli.list.reverse);
+        assert_eq!(cli.list.path, PathBuf::from("/tmp"));
     }
 
     #[test]
@@ -787,14 +4623,13 @@ mod tests {
     }
 
     #[test]
-    fn compare_modified_orders_newest_first_logic() {
-        let now = SystemTime::now();
-        let older = Some(now - Duration::from_secs(10));
-        let newer = Some(now - Duration::from_secs(1));
-        assert_eq!(compare_modified_desc(&newer, &older), Ordering::Less);
-        assert_eq!(compare_modified_desc(&older, &newer), Ordering::Greater);
-        assert_eq!(compare_modified_desc(&Some(now), &None), Ordering::Less);
-        assert_eq!(compare_modified_desc(&None, &Some(now)), Ordering::Greater);
+    fn junk_patterns_match_the_last_component() {
+        for name in ["notes.txt~", "db.bak", ".main.rs.swp", ".DS_Store", "thumbs.db", "#draft#", "src/._icon"] {
+            assert!(is_junk(name), "{name}");
+        }
+        for name in ["~", "bak", "backup.bak.txt", "DS_Store", "#draft", "src~/main.rs"] {
+            assert!(!is_junk(name), "{name}");
+        }
     }
 
     #[test]
@@ -810,47 +4645,11 @@ mod tests {
     fn sort_rows_respects_modified_over_directory_priority() {
         let now = SystemTime::now();
         let mut rows = vec![
-            EntryRow {
-                name_plain: "old_dir".into(),
-                name_with_git_plain: "old_dir".into(),
-                name_with_git_colored: String::new(),
-                entry_type_plain: "dir".into(),
-                entry_type_colored: String::new(),
-                size_plain: String::new(),
-                size_colored: String::new(),
-                modified_plain: String::new(),
-                modified_colored: String::new(),
-                modified_time: Some(now - Duration::from_secs(120)),
-                is_dir: true,
-            },
-            EntryRow {
-                name_plain: "new_file".into(),
-                name_with_git_plain: "new_file".into(),
-                name_with_git_colored: String::new(),
-                entry_type_plain: "file".into(),
-                entry_type_colored: String::new(),
-                size_plain: String::new(),
-                size_colored: String::new(),
-                modified_plain: String::new(),
-                modified_colored: String::new(),
-                modified_time: Some(now - Duration::from_secs(10)),
-                is_dir: false,
-            },
-            EntryRow {
-                name_plain: "mid_file".into(),
-                name_with_git_plain: "mid_file".into(),
-                name_with_git_colored: String::new(),
-                entry_type_plain: "file".into(),
-                entry_type_colored: String::new(),
-                size_plain: String::new(),
-                size_colored: String::new(),
-                modified_plain: String::new(),
-                modified_colored: String::new(),
-                modified_time: Some(now - Duration::from_secs(60)),
-                is_dir: false,
-            },
+            test_row("old_dir", true, Some(now - Duration::from_secs(120))),
+            test_row("new_file", false, Some(now - Duration::from_secs(10))),
+            test_row("mid_file", false, Some(now - Duration::from_secs(60))),
         ];
-        sort_rows(&mut rows, true, false);
+        sort_rows(&mut rows, SortKey::Modified, false, TieBreak::Name);
         assert_eq!(rows[0].name_plain, "new_file");
         assert_eq!(rows[1].name_plain, "mid_file");
         assert_eq!(rows[2].name_plain, "old_dir");
@@ -860,45 +4659,454 @@ mod tests {
     fn sort_rows_reverse_applies_after_modified() {
         let now = SystemTime::now();
         let mut rows = vec![
-            EntryRow {
-                name_plain: "a".into(),
-                name_with_git_plain: "a".into(),
-                name_with_git_colored: String::new(),
-                entry_type_plain: "file".into(),
-                entry_type_colored: String::new(),
-                size_plain: String::new(),
-                size_colored: String::new(),
-                modified_plain: String::new(),
-                modified_colored: String::new(),
-                modified_time: Some(now - Duration::from_secs(10)),
-                is_dir: false,
-            },
-            EntryRow {
-                name_plain: "b".into(),
-                name_with_git_plain: "b".into(),
-                name_with_git_colored: String::new(),
-                entry_type_plain: "file".into(),
-                entry_type_colored: String::new(),
-                size_plain: String::new(),
-                size_colored: String::new(),
-                modified_plain: String::new(),
-                modified_colored: String::new(),
-                modified_time: Some(now - Duration::from_secs(5)),
-                is_dir: false,
-            },
+            test_row("a", false, Some(now - Duration::from_secs(10))),
+            test_row("b", false, Some(now - Duration::from_secs(5))),
         ];
-        sort_rows(&mut rows, true, true);
+        sort_rows(&mut rows, SortKey::Modified, true, TieBreak::Name);
         assert_eq!(rows[0].name_plain, "a"); // oldest first when reversed
         assert_eq!(rows[1].name_plain, "b");
     }
 
+    /// Removes ANSI SGR sequences (`ESC [ ... m`) so colored text can be measured.
+    fn strip_ansi(text: &str) -> String {
+        let mut out = String::with_capacity(text.len());
+        let mut chars = text.chars();
+        while let Some(c) = chars.next() {
+            if c == '\x1b' {
+                for c in chars.by_ref() {
+                    if c == 'm' {
+                        break;
+                    }
+                }
+            } else {
+                out.push(c);
+            }
+        }
+        out
+    }
+
+    pub(crate) fn test_row(name: &str, is_dir: bool, modified_time: Option<SystemTime>) -> EntryRow {
+        EntryRow {
+            name_plain: name.into(),
+            entry_type: if is_dir { EntryType::Dir } else { EntryType::File },
+            modified_time,
+            ..EntryRow::default()
+        }
+    }
+
+    fn names(rows: &[EntryRow]) -> Vec<&str> {
+        rows.iter().map(|row| row.name_plain.as_str()).collect()
+    }
+
+    #[test]
+    fn sort_rows_by_size_ignores_directory_grouping() {
+        let mut rows = vec![test_row("small", false, None), test_row("dir", true, None), test_row("big", false, None)];
+        rows[0].size_bytes = 10;
+        rows[1].size_bytes = 4_096;
+        rows[2].size_bytes = 1_000_000;
+        sort_rows(&mut rows, SortKey::Size, false, TieBreak::Name);
+        assert_eq!(names(&rows), ["big", "dir", "small"]);
+
+        let cli = Cli::try_parse_from(["nuls", "-t", "-S"]).expect("parse ok");
+        assert_eq!(cli.list.sort_key(), SortKey::Size);
+        let cli = Cli::try_parse_from(["nuls", "-S", "-t"]).expect("parse ok");
+        assert_eq!(cli.list.sort_key(), SortKey::Modified);
+    }
+
+    #[test]
+    fn sort_rows_by_entries_puts_fullest_directories_first() {
+        let mut rows = vec![test_row("file", false, None), test_row("small", true, None), test_row("big", true, None)];
+        rows[1].child_count = Some(2);
+        rows[2].child_count = Some(90);
+        sort_rows(&mut rows, SortKey::Entries, false, TieBreak::Name);
+        assert_eq!(names(&rows), ["big", "small", "file"]);
+
+        let cli = Cli::try_parse_from(["nuls", "-t", "--sort", "entries"]).expect("parse ok");
+        assert_eq!(cli.list.sort_key(), SortKey::Entries);
+        let cli = Cli::try_parse_from(["nuls", "--sort", "entries", "-S"]).expect("parse ok");
+        assert_eq!(cli.list.sort_key(), SortKey::Size);
+    }
+
+    #[test]
+    fn sort_rows_breaks_mtime_ties_by_name() {
+        let same = Some(SystemTime::UNIX_EPOCH + Duration::from_secs(100));
+        let mut rows = vec![
+            test_row("beta", false, same),
+            test_row("Alpha", false, same),
+            test_row("alpha", false, same),
+        ];
+        sort_rows(&mut rows, SortKey::Modified, false, TieBreak::Name);
+        assert_eq!(names(&rows), ["Alpha", "alpha", "beta"]);
+    }
+
+    #[test]
+    fn sort_rows_tie_break_none_keeps_input_order() {
+        let same = Some(SystemTime::UNIX_EPOCH + Duration::from_secs(100));
+        let newer = Some(SystemTime::UNIX_EPOCH + Duration::from_secs(200));
+        let mut rows = vec![
+            test_row("c", false, same),
+            test_row("a", false, same),
+            test_row("b", false, newer),
+        ];
+        sort_rows(&mut rows, SortKey::Modified, false, TieBreak::None);
+        assert_eq!(names(&rows), ["b", "c", "a"]);
+
+        let mut rows = vec![test_row("z", false, None), test_row("y", true, None), test_row("x", false, None)];
+        sort_rows(&mut rows, SortKey::Name, false, TieBreak::None);
+        assert_eq!(names(&rows), ["y", "z", "x"]);
+    }
+
+    #[test]
+    fn top_rows_keeps_first_entries_across_chunks() {
+        let mut top = TopRows::new(3, SortSpec::by(SortKey::Modified));
+        for secs in 0..10_000u64 {
+            let modified = Some(SystemTime::UNIX_EPOCH + Duration::from_secs(secs));
+            top.push(test_row(&format!("f{secs}"), false, modified));
+            assert!(top.heap.len() <= 3);
+        }
+        let (rows, omitted) = top.finish();
+        assert_eq!(names(&rows), ["f9999", "f9998", "f9997"]);
+        assert_eq!(omitted, 9_997);
+
+        // Without a tie-break, equal rows keep their arrival order, as in `sort_rows`.
+        let mut top = TopRows::new(2, SortSpec::by(SortKey::Size).reverse(true).tie_break(TieBreak::None));
+        for name in ["a", "b", "c"] {
+            top.push(test_row(name, false, None));
+        }
+        assert_eq!(names(&top.finish().0), ["a", "b"]);
+        assert_eq!(names(&TopRows::new(0, SortSpec::by(SortKey::Name)).finish().0), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn group_hard_links_keeps_inode_siblings_together() {
+        let link = |inode| Some(HardLink { device: 1, inode, links: 2 });
+        let mut rows = vec![
+            test_row("a", false, None),
+            test_row("b", false, None),
+            test_row("c", false, None),
+            test_row("d", false, None),
+        ];
+        rows[0].hard_link = link(7);
+        rows[2].hard_link = link(9);
+        rows[3].hard_link = link(7);
+        group_hard_links(&mut rows);
+
+        let shown: Vec<String> = rows.iter().map(|row| row.annotated_name_cell().0).collect();
+        assert_eq!(
+            shown,
+            ["a [hardlink #1 · 2 links]", "d [hardlink #1 · 2 links]", "b", "c [2 links]"]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn security_flags_detect_mode_bits() {
+        use std::os::unix::fs::PermissionsExt;
+        let tmp = std::env::temp_dir().join(format!("nuls-security-{}", std::process::id()));
+        fs::write(&tmp, "x").expect("write temp file");
+        fs::set_permissions(&tmp, fs::Permissions::from_mode(0o4777)).expect("chmod");
+        let metadata = fs::symlink_metadata(&tmp).expect("metadata");
+        let flags = security_flags(&tmp, &metadata, None);
+        fs::remove_file(&tmp).ok();
+
+        assert!(flags.world_writable);
+        assert!(flags.setuid);
+        assert!(!flags.setgid);
+        assert!(!flags.root_owned);
+        assert_eq!(flags.capabilities, None);
+        assert!(!flags.immutable && !flags.append_only);
+        assert_eq!(flags.labels(), ["setuid", "world-writable"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn security_flags_note_owners_other_than_the_directorys() {
+        use std::os::unix::fs::MetadataExt;
+        let tmp = tempfile::NamedTempFile::new().expect("temp file");
+        let metadata = fs::symlink_metadata(tmp.path()).expect("metadata");
+        let (uid, gid) = (metadata.uid(), metadata.gid());
+        let flags = security_flags(tmp.path(), &metadata, Some((uid, gid)));
+        assert!(!flags.root_owned && flags.other_owner.is_none() && flags.other_group.is_none());
+
+        let flags = security_flags(tmp.path(), &metadata, Some((uid + 1, gid + 1)));
+        assert_eq!(flags.root_owned, uid == 0);
+        assert_eq!(flags.other_owner, (uid != 0).then_some(uid));
+        assert_eq!(flags.other_group, Some(gid));
+        // Anyone's entries may sit in root's directories.
+        let flags = security_flags(tmp.path(), &metadata, Some((0, gid + 1)));
+        assert!(flags.other_owner.is_none() && flags.other_group.is_none());
+
+        let flags = SecurityFlags {
+            other_owner: Some(4_000_000),
+            other_group: Some(4_000_001),
+            ..SecurityFlags::default()
+        };
+        assert_eq!(flags.labels(), ["owner 4000000", "group 4000001"]);
+    }
+
+    #[test]
+    fn security_summary_counts_flags() {
+        let mut rows = vec![test_row("a", false, None), test_row("b", false, None)];
+        rows[0].security.world_writable = true;
+        rows[1].security.world_writable = true;
+        rows[1].security.root_owned = true;
+        let summary = summarize_security(&rows);
+        assert_eq!(
+            summary,
+            SecuritySummary {
+                world_writable: 2,
+                setuid: 0,
+                setgid: 0,
+                root_owned: 1,
+                other_owner: 0,
+                other_group: 0,
+                capabilities: 0,
+                immutable: 0,
+                append_only: 0,
+            }
+        );
+        assert!(format_security_summary(&summary).contains("2 world-writable"));
+        assert!(format_security_summary(&SecuritySummary::default()).contains("no issues found"));
+    }
+
+    #[test]
+    fn size_histogram_buckets_files_only() {
+        let mut rows: Vec<EntryRow> = [10, 1024, 200 * 1024, 50 * 1024 * 1024, 5]
+            .into_iter()
+            .map(|size| {
+                let mut row = test_row("f", false, None);
+                row.size_bytes = size;
+                row
+            })
+            .collect();
+        rows.push(test_row("dir", true, None));
+        let counts = size_histogram(&rows);
+        assert_eq!(counts, [2, 1, 1, 1]);
+        let text = strip_ansi(&format_size_histogram(&counts));
+        assert!(text.contains("  < 1 KB        2  ████████████████████████\n"), "{text}");
+        assert!(text.contains("  > 10 MB       1  ████████████\n"), "{text}");
+    }
+
+    #[test]
+    fn extension_totals_group_case_insensitively_by_size() {
+        let rows: Vec<EntryRow> = [("a.log", 300), ("B.LOG", 500), ("notes.md", 100), ("Makefile", 20), ("src", 4096)]
+            .into_iter()
+            .map(|(name, size)| {
+                let mut row = test_row(name, name == "src", None);
+                row.size_bytes = size;
+                row
+            })
+            .collect();
+        assert_eq!(
+            extension_totals(&rows),
+            vec![
+                (".log".to_string(), 2, 800),
+                (".md".to_string(), 1, 100),
+                ("(none)".to_string(), 1, 20),
+            ]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn effective_access_uses_the_first_matching_class() {
+        let rights = |access: EffectiveAccess| (access.read, access.write, access.execute, access.class);
+        // Owner bits apply even when group or other would grant more.
+        assert_eq!(
+            rights(interpret_mode(0o077, 1000, 100, false, 1000, &[100])),
+            (false, false, false, Some(AccessClass::Owner))
+        );
+        assert_eq!(
+            rights(interpret_mode(0o750, 0, 100, true, 1000, &[20, 100])),
+            (true, false, true, Some(AccessClass::Group))
+        );
+        assert_eq!(
+            rights(interpret_mode(0o700, 0, 0, true, 1000, &[100])),
+            (false, false, false, Some(AccessClass::Other))
+        );
+        assert_eq!(
+            rights(interpret_mode(0o600, 1000, 100, false, 0, &[0])),
+            (true, true, false, Some(AccessClass::Root))
+        );
+
+        let mut row = test_row("secret", true, None);
+        row.access = Some(interpret_mode(0o711, 0, 0, true, 1000, &[100]));
+        assert_eq!(row.access_cell().0, "--x other · cannot read");
+        row.access = Some(interpret_mode(0o744, 0, 0, true, 1000, &[100]));
+        assert_eq!(row.access_cell().0, "r-- other · cannot enter");
+    }
+
+    #[test]
+    fn group_headings_fall_back_to_month_names() {
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1_717_243_200); // 2024-06-01 12:00 UTC
+        let format = RowFormat {
+            strings: &i18n::FR,
+            headers: &Headers::resolve(&HeaderConfig::default(), Lang::Fr),
+            aligns: &Alignments::resolve(&TableConfig::default()).expect("default alignments"),
+            recency: RecencyThresholds::default(),
+            time_style: TimeStyle::Relative,
+            size_style: SizeStyle::default(),
+            group_by: Some(GroupBy::ModifiedDay),
+            now,
+            totals: false,
+        };
+        let heading = |secs_ago: u64| {
+            let modified = Some(now - Duration::from_secs(secs_ago));
+            group_heading(&test_row("f", false, modified), &format)
+        };
+        assert_eq!(heading(60).as_deref(), Some("Aujourd'hui"));
+        assert_eq!(heading(3 * 86_400).as_deref(), Some("La semaine dernière"));
+        assert_eq!(heading(88 * 86_400).as_deref(), Some("mars 2024"));
+        assert_eq!(group_heading(&test_row("f", false, None), &format).as_deref(), Some("inconnu"));
+    }
+
+    #[test]
+    fn tabs_escape_separators_inside_cells() {
+        let rows = vec![test_row("a\tb", false, None), test_row("c", true, None)];
+        let format = RowFormat {
+            strings: &i18n::EN,
+            headers: &Headers::resolve(&HeaderConfig::default(), Lang::En),
+            aligns: &Alignments::resolve(&TableConfig::default()).expect("default alignments"),
+            recency: RecencyThresholds::default(),
+            time_style: TimeStyle::Relative,
+            size_style: SizeStyle::default(),
+            group_by: None,
+            now: SystemTime::UNIX_EPOCH,
+            totals: false,
+        };
+        assert_eq!(
+            render_tabs(&rows, &[Column::Index, Column::Name, Column::Type], &format),
+            "#\tname\ttype\n0\ta\\tb\tfile\n1\tc\tdir\n"
+        );
+    }
+
+    #[test]
+    fn names_only_rows_leave_size_and_time_unread() {
+        let tmp = tempfile::tempdir().expect("tempdir");
+        fs::create_dir(tmp.path().join("src")).expect("mkdir");
+        fs::write(tmp.path().join("notes.txt"), "hello").expect("write");
+        fs::write(tmp.path().join("notes.txt~"), "").expect("write");
+        let options = CollectOptions {
+            names_only: true,
+            filter: EntryFilter { hide_junk: true, ..EntryFilter::default() },
+            ..CollectOptions::plain(false)
+        };
+        let mut rows = collect_entries(tmp.path(), &options, None).expect("listed");
+        sort_rows(&mut rows, SortKey::Name, false, TieBreak::Name);
+        assert_eq!(names(&rows), ["src", "notes.txt"]);
+        assert!(rows[0].is_dir() && rows.iter().all(|row| row.stat_skipped && row.modified_time.is_none()));
+        assert_eq!(rows[1].size_cell(&i18n::EN, SizeStyle::default()).0, "-");
+
+        let list = |args: &[&str]| Cli::try_parse_from([&["nuls"], args].concat()).expect("parse ok").list;
+        assert!(!list(&[]).needs_metadata());
+        assert!(list(&["-t"]).needs_metadata() && list(&["--json"]).needs_metadata());
+    }
+
+    #[test]
+    fn visitors_hear_of_each_directory_before_its_entries() {
+        #[derive(Default)]
+        struct Log(Vec<String>);
+        impl Visitor for &mut Log {
+            fn on_entry(&mut self, row: EntryRow) {
+                self.0.push(row.name_plain);
+            }
+            fn on_progress(&mut self, dir: &Path, read: usize) {
+                let name = dir.file_name().map(|name| name.to_string_lossy().to_string());
+                self.0.push(format!("{} after {read}", name.unwrap_or_default()));
+            }
+        }
+        let tmp = tempfile::tempdir().expect("tempdir");
+        fs::create_dir(tmp.path().join("sub")).expect("mkdir");
+        fs::write(tmp.path().join("sub/b.txt"), "b").expect("write");
+        fs::write(tmp.path().join("a.txt"), "a").expect("write");
+        let mut log = Log::default();
+        for_each_recent_file(tmp.path(), &CollectOptions::plain(false), &mut log).expect("walked");
+        let root = tmp.path().file_name().expect("name").to_string_lossy().to_string();
+        assert_eq!(log.0, [format!("{root} after 0"), "a.txt".into(), "sub after 2".into(), "sub/b.txt".into()]);
+    }
+
+    #[test]
+    fn compact_times_use_the_largest_whole_unit() {
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+        let ago = |secs: u64| format_compact_time(now - Duration::from_secs(secs), now);
+        assert_eq!(ago(59), "59s");
+        assert_eq!(ago(3 * 86_400), "3d");
+        assert_eq!(ago(70 * 86_400), "2mo");
+        assert_eq!(ago(800 * 86_400), "2y");
+        assert_eq!(format_compact_time(now + Duration::from_secs(7_200), now), "+2h");
+    }
+
+    #[test]
+    fn shrink_order_rejects_unknown_columns() {
+        let config = |keys: &[&str]| TableConfig {
+            shrink: Some(keys.iter().map(|key| key.to_string()).collect()),
+            ..TableConfig::default()
+        };
+        assert_eq!(Column::shrink_order(&TableConfig::default()), Ok(Column::DEFAULT_SHRINK.to_vec()));
+        assert_eq!(Column::shrink_order(&config(&["size", "name"])), Ok(vec![Column::Size, Column::Name]));
+        assert!(Column::shrink_order(&config(&["colour"])).is_err());
+    }
+
+    #[test]
+    fn arena_rows_match_owned_cells() {
+        let mut arena = CellArena::with_capacity(2);
+        arena.push_painted(palette::SIZE, Align::Right, |text| write_size(text, 1536));
+        arena.push("日本.txt", "\x1b[34m日本.txt\x1b[0m", Align::Center);
+        let owned = [
+            ("1.5 KB".to_string(), palette::paint("1.5 KB", palette::SIZE), Align::Right),
+            ("日本.txt".to_string(), "\x1b[34m日本.txt\x1b[0m".to_string(), Align::Center),
+        ];
+        let widths = [8, 12];
+        let mut rows = arena.rows(2);
+        let mut line = String::new();
+        push_row(&mut line, rows.next().expect("one row"), &widths, &palette::paint("│", palette::BORDER));
+        assert!(rows.next().is_none());
+        assert_eq!(line, render_row(&owned, &widths));
+    }
+
+    #[test]
+    fn alignments_default_by_kind_and_follow_config() {
+        let defaults = Alignments::resolve(&TableConfig::default()).expect("defaults");
+        assert_eq!((defaults.get(Column::Size), defaults.get(Column::Name)), (Align::Right, Align::Left));
+        let config = TableConfig {
+            align: [("name", ColumnAlign::Right), ("type", ColumnAlign::Center)]
+                .into_iter()
+                .map(|(key, align)| (key.to_string(), align))
+                .collect(),
+            ..TableConfig::default()
+        };
+        let aligns = Alignments::resolve(&config).expect("valid keys");
+        assert_eq!((aligns.get(Column::Name), aligns.get(Column::Type)), (Align::Right, Align::Center));
+        assert_eq!(pad_cell("dir", "dir", 8, Align::Center), "  dir   ");
+
+        let config = TableConfig {
+            align: [("colour".to_string(), ColumnAlign::Left)].into_iter().collect(),
+            ..TableConfig::default()
+        };
+        assert!(Alignments::resolve(&config).is_err());
+    }
+
+    #[test]
+    fn commas_wrap_at_width() {
+        let rows = vec![
+            test_row("alpha", false, None),
+            test_row("beta", false, None),
+            test_row("gamma", false, None),
+        ];
+        let wide = strip_ansi(&render_commas(&rows, 80));
+        assert_eq!(wide, "alpha, beta, gamma\n");
+        let narrow = strip_ansi(&render_commas(&rows, 12));
+        assert_eq!(narrow, "alpha, beta,\ngamma\n");
+        assert_eq!(render_commas(&[], 80), "");
+    }
+
     #[test]
     fn format_git_dirty_with_counts() {
         let status = GitStatus {
             added: Some(3),
             deleted: Some(1),
             dirty: true,
-            untracked: false,
+            ..GitStatus::default()
         };
         let (plain, colored) = format_git(&status).expect("has output");
         assert!(plain.contains("+3"));
@@ -910,13 +5118,72 @@ mod tests {
     }
 
     #[test]
-    fn format_git_clean() {
-        let status = GitStatus {
-            added: None,
-            deleted: None,
-            dirty: false,
-            untracked: false,
+    fn scoped_directories_count_changed_files_beneath() {
+        let change = |added| GitStatus {
+            added: Some(added),
+            deleted: Some(1),
+            dirty: true,
+            ..GitStatus::default()
+        };
+        let map = HashMap::from([
+            ("src/a.rs".to_string(), change(100)),
+            ("src/deep/b.rs".to_string(), change(20)),
+            ("README.md".to_string(), change(3)),
+            ("other/c.rs".to_string(), change(1)),
+        ]);
+        let scoped = scope_git_entries(map, Path::new("/repo"), Path::new("/repo"));
+        let (plain, _) = format_git(&scoped["src"]).expect("has output");
+        assert_eq!(plain, "(+120 -2 · 2 files)");
+        assert_eq!(scoped["README.md"].files, None);
+        assert_eq!(format_git(&scoped["other"]).expect("has output").0, "(+1 -1 · 1 file)");
+    }
+
+    #[test]
+    fn numstat_renames_resolve_to_the_new_path() {
+        assert_eq!(numstat_new_path("src/lib.rs"), "src/lib.rs");
+        assert_eq!(numstat_new_path("old.txt => new.txt"), "new.txt");
+        assert_eq!(numstat_new_path("src/{a => b}/lib.rs"), "src/b/lib.rs");
+        assert_eq!(numstat_new_path("{sub => }/x.txt"), "x.txt");
+    }
+
+    #[test]
+    fn git_env_passes_on_only_for_paths_in_its_work_tree() {
+        let cwd = || Some(PathBuf::from("/nuls-missing/work"));
+        let dir = |value: &str| Some(OsString::from(value));
+        let inside = Path::new("/nuls-missing/work/src");
+        let outside = Path::new("/nuls-missing/other");
+
+        let env = GitEnv::resolve(inside, dir(".git"), None, cwd());
+        let expected = (PathBuf::from("/nuls-missing/work/.git"), PathBuf::from("/nuls-missing/work"));
+        assert_eq!(env.repo, Some(expected));
+        assert_eq!(GitEnv::resolve(outside, dir(".git"), None, cwd()), GitEnv::default());
+
+        let env = GitEnv::resolve(outside, dir("repo.git"), dir("/nuls-missing/other"), cwd());
+        let expected = (PathBuf::from("/nuls-missing/work/repo.git"), PathBuf::from("/nuls-missing/other"));
+        assert_eq!(env.repo, Some(expected));
+        assert_eq!(GitEnv::resolve(inside, None, dir("/nuls-missing/work"), cwd()), GitEnv::default());
+        assert_eq!(GitEnv::resolve(inside, dir(""), None, cwd()), GitEnv::default());
+    }
+
+    #[test]
+    fn renames_name_the_old_path_relative_to_the_listing() {
+        let renamed = |old: &str| GitStatus {
+            dirty: true,
+            renamed_from: Some(old.to_string()),
+            ..GitStatus::default()
         };
+        let map = HashMap::from([
+            ("src/new.rs".to_string(), renamed("src/old.rs")),
+            ("src/moved.rs".to_string(), renamed("lib/moved.rs")),
+        ]);
+        let scoped = scope_git_entries(map, Path::new("/repo"), Path::new("/repo/src"));
+        assert_eq!(format_git(&scoped["new.rs"]).expect("has output").0, "(renamed from old.rs)");
+        assert_eq!(format_git(&scoped["moved.rs"]).expect("has output").0, "(renamed from lib/moved.rs)");
+    }
+
+    #[test]
+    fn format_git_clean() {
+        let status = GitStatus::default();
         let (plain, colored) = format_git(&status).expect("has output");
         assert_eq!(plain, "");
         assert!(colored.contains(palette::GIT_CLEAN));
@@ -0,0 +1,147 @@
+//! What a directory is really stored on, for `--mount-info`: the mount
+//! holding it, from `/proc/self/mountinfo`, described when it is an overlay
+//! (container layers) or a bind mount of some other directory, where writes
+//! and deletes behave differently than the path suggests. Linux only.
+#![cfg_attr(not(target_os = "linux"), allow(dead_code))]
+
+use std::path::{Path, PathBuf};
+
+#[derive(Debug)]
+struct Mount {
+    /// The directory of the filesystem that appears at `mount_point`; `/`
+    /// unless it is a bind mount (or a btrfs subvolume).
+    root: String,
+    mount_point: PathBuf,
+    fs_type: String,
+    source: String,
+    /// Filesystem-specific options such as overlay's `lowerdir`.
+    super_options: String,
+}
+
+/// One line of `mountinfo`: `id parent dev root point options [tags...] - type source super-options`.
+fn parse_line(line: &str) -> Option<Mount> {
+    let (before, after) = line.split_once(" - ")?;
+    let fields: Vec<&str> = before.split(' ').collect();
+    let mut after = after.split(' ');
+    Some(Mount {
+        root: unescape(fields.get(3)?),
+        mount_point: PathBuf::from(unescape(fields.get(4)?)),
+        fs_type: after.next()?.to_string(),
+        source: unescape(after.next()?),
+        super_options: after.next().unwrap_or_default().to_string(),
+    })
+}
+
+/// Undoes the octal escapes (`\040` for a space) the kernel writes in paths.
+fn unescape(field: &str) -> String {
+    let bytes = field.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut idx = 0;
+    while idx < bytes.len() {
+        let octal = bytes.get(idx + 1..idx + 4).and_then(|digits| {
+            let digits = std::str::from_utf8(digits).ok()?;
+            u8::from_str_radix(digits, 8).ok()
+        });
+        match octal {
+            Some(byte) if bytes[idx] == b'\\' => {
+                out.push(byte);
+                idx += 4;
+            }
+            _ => {
+                out.push(bytes[idx]);
+                idx += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// The mount `path` (absolute) lives on: the deepest mount point above it,
+/// and of mounts stacked on one point the last, which hides the others.
+fn containing<'a>(mounts: &'a [Mount], path: &Path) -> Option<&'a Mount> {
+    mounts
+        .iter()
+        .filter(|mount| path.starts_with(&mount.mount_point))
+        .fold(None, |best: Option<&Mount>, mount| match best {
+            Some(best) if best.mount_point.components().count() > mount.mount_point.components().count() => {
+                Some(best)
+            }
+            _ => Some(mount),
+        })
+}
+
+fn option<'a>(options: &'a str, key: &str) -> Option<&'a str> {
+    options.split(',').find_map(|option| option.strip_prefix(key)?.strip_prefix('='))
+}
+
+/// A sentence on `mount` when it is an overlay or a bind mount.
+fn describe(mount: &Mount) -> Option<String> {
+    if mount.fs_type == "overlay" {
+        let layers = option(&mount.super_options, "lowerdir").map_or(0, |dirs| dirs.split(':').count());
+        let layers = if layers == 1 { "1 lower layer".to_string() } else { format!("{layers} lower layers") };
+        return Some(match option(&mount.super_options, "upperdir") {
+            Some(upper) => format!(
+                "overlay filesystem over {layers}: writes go to {upper}, and deleting a file from a lower layer \
+                 only hides it"
+            ),
+            None => format!("read-only overlay filesystem over {layers}"),
+        });
+    }
+    let subvolume = option(&mount.super_options, "subvol").is_some_and(|subvol| subvol == mount.root);
+    (mount.root != "/" && !subvolume).then(|| {
+        format!(
+            "bind mount of {} on {} ({}), mounted at {}",
+            mount.root,
+            mount.source,
+            mount.fs_type,
+            mount.mount_point.display()
+        )
+    })
+}
+
+/// The note for the listed directory `path`, if its mount calls for one.
+#[cfg(target_os = "linux")]
+pub fn note(path: &Path) -> Option<String> {
+    let abs = path.canonicalize().ok()?;
+    let text = std::fs::read_to_string("/proc/self/mountinfo").ok()?;
+    let mounts: Vec<Mount> = text.lines().filter_map(parse_line).collect();
+    describe(containing(&mounts, &abs)?)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn note(_path: &Path) -> Option<String> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MOUNTINFO: &str = "\
+22 1 0:50 / / rw,relatime master:1 - overlay overlay rw,lowerdir=/l/a:/l/b,upperdir=/l/up,workdir=/l/work
+23 22 8:1 /srv/my\\040data /data rw,relatime - ext4 /dev/sda1 rw
+24 22 0:30 /@home /home rw - btrfs /dev/sda2 rw,subvol=/@home
+25 23 0:31 / /data/cache rw - tmpfs tmpfs rw
+26 22 0:32 / /ro ro - overlay overlay ro,lowerdir=/l/a
+";
+
+    #[test]
+    fn notes_overlays_and_bind_mounts() {
+        let mounts: Vec<Mount> = MOUNTINFO.lines().filter_map(parse_line).collect();
+        let note_for = |path: &str| containing(&mounts, Path::new(path)).and_then(describe);
+        assert_eq!(
+            note_for("/usr/lib").as_deref(),
+            Some(
+                "overlay filesystem over 2 lower layers: writes go to /l/up, and deleting a file from a lower \
+                 layer only hides it"
+            )
+        );
+        assert_eq!(
+            note_for("/data/x").as_deref(),
+            Some("bind mount of /srv/my data on /dev/sda1 (ext4), mounted at /data")
+        );
+        assert_eq!(note_for("/data/cache/y"), None);
+        assert_eq!(note_for("/home/me"), None);
+        assert_eq!(note_for("/ro").as_deref(), Some("read-only overlay filesystem over 1 lower layer"));
+    }
+}
@@ -0,0 +1,122 @@
+//! `--open-by`: which processes hold each entry open, found by reading every
+//! `/proc/PID/fd` link once, the way `lsof` does (Linux). Only processes the
+//! user may inspect are seen, which is all of them for root; memory-mapped
+//! files without an open descriptor are not.
+#![cfg_attr(not(target_os = "linux"), allow(dead_code))]
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Processes named at most in a cell before the rest are counted.
+const SHOWN: usize = 2;
+
+/// Open files and who holds them, keyed by absolute path.
+#[derive(Debug, Default)]
+pub struct Holders {
+    by_path: HashMap<PathBuf, Vec<(u32, String)>>,
+}
+
+impl Holders {
+    /// Reads the descriptor table of every visible process but this one.
+    #[cfg(target_os = "linux")]
+    pub fn scan() -> Holders {
+        let mut holders = Holders::default();
+        let Ok(procs) = std::fs::read_dir("/proc") else {
+            return holders;
+        };
+        let own = std::process::id();
+        for proc_entry in procs.flatten() {
+            let Some(pid) = proc_entry.file_name().to_str().and_then(|name| name.parse::<u32>().ok()) else {
+                continue;
+            };
+            if pid == own {
+                continue;
+            }
+            let Ok(fds) = std::fs::read_dir(proc_entry.path().join("fd")) else {
+                continue;
+            };
+            let command = std::fs::read_to_string(proc_entry.path().join("comm"))
+                .map(|comm| comm.trim_end().to_string())
+                .unwrap_or_else(|_| "?".to_string());
+            for fd in fds.flatten() {
+                if let Ok(target) = std::fs::read_link(fd.path()) {
+                    holders.add(target, pid, &command);
+                }
+            }
+        }
+        holders
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn scan() -> Holders {
+        Holders::default()
+    }
+
+    /// Records that `pid` has `target` open. Sockets, pipes, and other
+    /// descriptors that are not paths are skipped.
+    fn add(&mut self, target: PathBuf, pid: u32, command: &str) {
+        if !target.is_absolute() {
+            return;
+        }
+        let holders = self.by_path.entry(target).or_default();
+        if !holders.iter().any(|(held_by, _)| *held_by == pid) {
+            holders.push((pid, command.to_string()));
+        }
+    }
+
+    /// `vim (4121), less (4200) +3` for the processes holding `path`, which
+    /// must be absolute with its directories resolved.
+    pub fn describe(&self, path: &Path) -> Option<String> {
+        let holders = self.by_path.get(path)?;
+        let mut text = holders
+            .iter()
+            .take(SHOWN)
+            .map(|(pid, command)| format!("{command} ({pid})"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        if holders.len() > SHOWN {
+            text.push_str(&format!(" +{}", holders.len() - SHOWN));
+        }
+        Some(text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn holders_are_named_with_their_pids() {
+        let mut holders = Holders::default();
+        let log = PathBuf::from("/var/log/app.log");
+        for (pid, command) in [(10, "tail"), (10, "tail"), (11, "less"), (12, "grep"), (13, "cat")] {
+            holders.add(log.clone(), pid, command);
+        }
+        holders.add(PathBuf::from("socket:[4242]"), 14, "nginx");
+        holders.add(PathBuf::from("/data/db.sqlite"), 15, "postgres");
+
+        assert_eq!(holders.describe(&log).as_deref(), Some("tail (10), less (11) +2"));
+        assert_eq!(holders.describe(Path::new("/data/db.sqlite")).as_deref(), Some("postgres (15)"));
+        assert_eq!(holders.describe(Path::new("/data")), None);
+        assert_eq!(holders.by_path.len(), 2);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn scan_finds_a_file_another_process_holds() {
+        let tmp = tempfile::tempdir().expect("tempdir");
+        let held = tmp.path().canonicalize().expect("canonicalize").join("held.txt");
+        std::fs::write(&held, "x").expect("write");
+        let mut child = std::process::Command::new("sleep")
+            .arg("30")
+            .stdin(std::fs::File::open(&held).expect("open"))
+            .spawn()
+            .expect("spawn sleep");
+        let described = Holders::scan().describe(&held);
+        child.kill().ok();
+        child.wait().ok();
+        // Right after the spawn the child may still carry this thread's name.
+        let described = described.expect("the held file is found");
+        assert!(described.ends_with(&format!(" ({})", child.id())), "{described}");
+    }
+}
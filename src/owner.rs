@@ -0,0 +1,101 @@
+//! User and group name lookup from `/etc/passwd` and `/etc/group`, parsed
+//! directly so static builds need no libc NSS support. Builds without the
+//! `owners` feature (Termux, where those files do not describe Android's
+//! app users) skip the lookup and show numeric ids.
+
+use std::collections::HashMap;
+use std::fs;
+
+/// Whether names are looked up at all.
+pub const LOOKUP: bool = cfg!(feature = "owners");
+
+#[derive(Debug, Default)]
+pub struct Owners {
+    users: HashMap<u32, String>,
+    groups: HashMap<u32, String>,
+}
+
+impl Owners {
+    /// Reads the local account databases; missing files yield empty tables.
+    pub fn load() -> Owners {
+        if !LOOKUP {
+            return Owners::default();
+        }
+        Owners {
+            users: fs::read_to_string("/etc/passwd")
+                .map(|text| parse_id_file(&text))
+                .unwrap_or_default(),
+            groups: fs::read_to_string("/etc/group")
+                .map(|text| parse_id_file(&text))
+                .unwrap_or_default(),
+        }
+    }
+
+    pub fn user_name(&self, uid: u32) -> Option<&str> {
+        self.users.get(&uid).map(String::as_str)
+    }
+
+    pub fn group_name(&self, gid: u32) -> Option<&str> {
+        self.groups.get(&gid).map(String::as_str)
+    }
+
+    /// Resolves a user name, or accepts a numeric uid as is.
+    pub fn uid_for(&self, user: &str) -> Option<u32> {
+        user.parse::<u32>().ok().or_else(|| {
+            self.users
+                .iter()
+                .find(|(_, name)| name.as_str() == user)
+                .map(|(uid, _)| *uid)
+        })
+    }
+
+    /// `name` when known, otherwise the numeric id.
+    pub fn user_label(&self, uid: u32) -> String {
+        self.user_name(uid).map(str::to_string).unwrap_or_else(|| uid.to_string())
+    }
+
+    pub fn group_label(&self, gid: u32) -> String {
+        self.group_name(gid).map(str::to_string).unwrap_or_else(|| gid.to_string())
+    }
+}
+
+/// Parses `name:password:id:...` lines, keeping the first name per id.
+fn parse_id_file(text: &str) -> HashMap<u32, String> {
+    let mut map = HashMap::new();
+    for line in text.lines() {
+        if line.starts_with('#') {
+            continue;
+        }
+        let mut fields = line.split(':');
+        let (Some(name), Some(_), Some(id)) = (fields.next(), fields.next(), fields.next()) else {
+            continue;
+        };
+        if let Ok(id) = id.parse::<u32>() {
+            map.entry(id).or_insert_with(|| name.to_string());
+        }
+    }
+    map
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_passwd_lines() {
+        let map = parse_id_file("# comment\nroot:x:0:0:root:/root:/bin/sh\nana:x:1000:1000::/home/ana:/bin/zsh\nbroken\nalias:x:0:0\n");
+        assert_eq!(map.get(&0).map(String::as_str), Some("root"));
+        assert_eq!(map.get(&1000).map(String::as_str), Some("ana"));
+        assert_eq!(map.len(), 2);
+
+        let owners = Owners {
+            users: map,
+            groups: HashMap::new(),
+        };
+        assert_eq!(owners.user_label(1000), "ana");
+        assert_eq!(owners.uid_for("ana"), Some(1000));
+        assert_eq!(owners.uid_for("42"), Some(42));
+        assert_eq!(owners.uid_for("nobody"), None);
+        assert_eq!(owners.group_label(7), "7");
+    }
+}
@@ -0,0 +1,40 @@
+//! Progress feedback on stderr for operations that can run long (tree walks,
+//! hashing). indicatif only draws when stderr is a terminal, so piped or
+//! redirected runs stay silent, as do `--quiet` ones.
+
+use crate::diag;
+use indicatif::{ProgressBar, ProgressStyle};
+use std::time::Duration;
+
+/// Inputs smaller than this hash too quickly for a bar to be worth drawing.
+const BYTES_BAR_MIN: u64 = 16 * 1024 * 1024;
+
+/// Spinner with a running count and the item being processed as its message.
+pub fn spinner(label: &str) -> ProgressBar {
+    if diag::quiet() {
+        return ProgressBar::hidden();
+    }
+    let bar = ProgressBar::new_spinner();
+    bar.set_style(
+        ProgressStyle::with_template("{spinner} {prefix} {human_pos} {wide_msg}")
+            .expect("valid template"),
+    );
+    bar.set_prefix(label.to_string());
+    bar.enable_steady_tick(Duration::from_millis(100));
+    bar
+}
+
+/// Byte bar for reading `total` bytes; hidden for small inputs.
+pub fn bytes(label: &str, total: u64) -> ProgressBar {
+    if total < BYTES_BAR_MIN || diag::quiet() {
+        return ProgressBar::hidden();
+    }
+    let bar = ProgressBar::new(total);
+    bar.set_style(
+        ProgressStyle::with_template("{prefix} [{bar:30}] {bytes}/{total_bytes} {eta}")
+            .expect("valid template")
+            .progress_chars("=> "),
+    );
+    bar.set_prefix(label.to_string());
+    bar
+}
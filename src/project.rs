@@ -0,0 +1,269 @@
+//! Project detection from marker files (`Cargo.toml`, `package.json`,
+//! `pyproject.toml`, ...), for the `--project` context header and the
+//! `--cargo` and `--npm` package tags.
+
+use crate::{interrupt, palette};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+    Cargo,
+    CargoWorkspace,
+    Npm,
+    Python,
+}
+
+impl Kind {
+    fn label(self) -> &'static str {
+        match self {
+            Kind::Cargo => "Cargo",
+            Kind::CargoWorkspace => "Cargo workspace",
+            Kind::Npm => "npm",
+            Kind::Python => "Python",
+        }
+    }
+}
+
+/// Name and version declared by a manifest, when it declares them.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Manifest {
+    pub name: Option<String>,
+    pub version: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Project {
+    pub root: PathBuf,
+    pub kind: Kind,
+    pub manifest: Manifest,
+}
+
+/// The nearest directory at or above `path` with a known marker file.
+pub fn detect(path: &Path) -> Option<Project> {
+    let start = path.canonicalize().ok()?;
+    start.ancestors().find_map(|dir| {
+        let (kind, manifest) = read_dir_manifest(dir)?;
+        Some(Project {
+            root: dir.to_path_buf(),
+            kind,
+            manifest,
+        })
+    })
+}
+
+/// The project kind and manifest of `dir` itself, checking markers in a
+/// fixed order so a Rust crate with a `package.json` still reads as Cargo.
+pub fn read_dir_manifest(dir: &Path) -> Option<(Kind, Manifest)> {
+    if let Some(manifest) = cargo_manifest(dir) {
+        return Some(manifest);
+    }
+    if let Some(manifest) = npm_manifest(dir) {
+        return Some((Kind::Npm, manifest));
+    }
+    python_manifest(dir).map(|manifest| (Kind::Python, manifest))
+}
+
+/// `[package]` name and version from `Cargo.toml`; a manifest with only a
+/// `[workspace]` table is a workspace root. Inherited (`version.workspace =
+/// true`) values are left unset.
+pub fn cargo_manifest(dir: &Path) -> Option<(Kind, Manifest)> {
+    let text = fs::read_to_string(dir.join("Cargo.toml")).ok()?;
+    let table: toml::Table = text.parse().unwrap_or_default();
+    let field = |package: &toml::Table, key: &str| {
+        package.get(key).and_then(|value| value.as_str()).map(str::to_string)
+    };
+    match table.get("package").and_then(|package| package.as_table()) {
+        Some(package) => Some((
+            Kind::Cargo,
+            Manifest {
+                name: field(package, "name"),
+                version: field(package, "version"),
+            },
+        )),
+        None if table.contains_key("workspace") => Some((Kind::CargoWorkspace, Manifest::default())),
+        None => Some((Kind::Cargo, Manifest::default())),
+    }
+}
+
+/// `name` and `version` from `package.json`.
+pub fn npm_manifest(dir: &Path) -> Option<Manifest> {
+    let text = fs::read_to_string(dir.join("package.json")).ok()?;
+    let value: serde_json::Value = serde_json::from_str(&text).unwrap_or_default();
+    let field = |key: &str| value.get(key).and_then(|value| value.as_str()).map(str::to_string);
+    Some(Manifest {
+        name: field("name"),
+        version: field("version"),
+    })
+}
+
+/// `[project]` (PEP 621) or `[tool.poetry]` name and version from
+/// `pyproject.toml`; `setup.py` and `setup.cfg` only mark the directory.
+fn python_manifest(dir: &Path) -> Option<Manifest> {
+    if let Ok(text) = fs::read_to_string(dir.join("pyproject.toml")) {
+        let table: toml::Table = text.parse().unwrap_or_default();
+        let section = table.get("project").or_else(|| table.get("tool").and_then(|tool| tool.get("poetry")));
+        let field = |key: &str| {
+            section
+                .and_then(|section| section.get(key))
+                .and_then(|value| value.as_str())
+                .map(str::to_string)
+        };
+        return Some(Manifest {
+            name: field("name"),
+            version: field("version"),
+        });
+    }
+    ["setup.py", "setup.cfg"]
+        .iter()
+        .any(|marker| dir.join(marker).is_file())
+        .then(Manifest::default)
+}
+
+/// `[crate name version]` for a package directory, `[workspace]` for a
+/// virtual manifest, or `[N crates]` for a directory whose subdirectories
+/// are packages (the usual `crates/*` workspace layout).
+pub fn crate_tag(dir: &Path) -> Option<String> {
+    if let Some((kind, manifest)) = cargo_manifest(dir) {
+        if kind == Kind::CargoWorkspace {
+            return Some("[workspace]".to_string());
+        }
+        let label = [manifest.name, manifest.version]
+            .into_iter()
+            .flatten()
+            .fold("crate".to_string(), |label, part| format!("{label} {part}"));
+        return Some(format!("[{label}]"));
+    }
+    let crates = fs::read_dir(dir)
+        .ok()?
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_ok_and(|kind| kind.is_dir()))
+        .filter(|entry| matches!(cargo_manifest(&entry.path()), Some((Kind::Cargo, _))))
+        .count();
+    match crates {
+        0 => None,
+        1 => Some("[1 crate]".to_string()),
+        count => Some(format!("[{count} crates]")),
+    }
+}
+
+/// `[name@version]` for a directory with a `package.json`, dropping
+/// whichever part the manifest leaves out (`[package]` when both are).
+pub fn npm_tag(dir: &Path) -> Option<String> {
+    let manifest = npm_manifest(dir)?;
+    let label = match (manifest.name, manifest.version) {
+        (Some(name), Some(version)) => format!("{name}@{version}"),
+        (Some(name), None) => name,
+        (None, Some(version)) => format!("@{version}"),
+        (None, None) => "package".to_string(),
+    };
+    Some(format!("[{label}]"))
+}
+
+/// Apparent size of everything under `dir`, without following symlinks.
+/// Unreadable subdirectories count as empty.
+pub fn tree_size(dir: &Path) -> u64 {
+    let mut total = 0;
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        if interrupt::requested() {
+            break;
+        }
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.filter_map(Result::ok) {
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            if metadata.is_dir() {
+                pending.push(entry.path());
+            } else {
+                total += metadata.len();
+            }
+        }
+    }
+    total
+}
+
+/// `project: nuls 0.2.0 (Cargo) at /path/to/root`, naming the root directory
+/// when the manifest has no name.
+pub fn render_header(project: &Project) -> String {
+    let name = project.manifest.name.clone().unwrap_or_else(|| {
+        project
+            .root
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| project.root.display().to_string())
+    });
+    let version = project
+        .manifest
+        .version
+        .as_ref()
+        .map(|version| format!(" {}", palette::paint(version, palette::SIZE)))
+        .unwrap_or_default();
+    format!(
+        "{} {}{version} {} {} {}",
+        palette::paint("project:", palette::HEADER),
+        palette::paint(name, palette::DIR),
+        palette::paint(format!("({})", project.kind.label()), palette::TYPE),
+        palette::paint("at", palette::GIT_CLEAN),
+        palette::paint(project.root.display().to_string(), palette::FILE)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detect_walks_up_to_the_nearest_marker() {
+        let tmp = tempfile::TempDir::new().expect("tempdir");
+        let root = tmp.path();
+        fs::write(root.join("Cargo.toml"), "[workspace]\nmembers = [\"app\"]\n").expect("write");
+        fs::create_dir_all(root.join("app/src")).expect("mkdir");
+        fs::write(root.join("app/Cargo.toml"), "[package]\nname = \"app\"\nversion = \"1.2.0\"\n").expect("write");
+        fs::create_dir(root.join("web")).expect("mkdir");
+        fs::write(root.join("web/package.json"), r#"{"name": "web", "version": "0.1.0"}"#).expect("write");
+
+        let project = detect(&root.join("app/src")).expect("inside a crate");
+        assert_eq!(project.kind, Kind::Cargo);
+        assert_eq!(project.root, root.join("app").canonicalize().expect("canonical"));
+        assert_eq!(project.manifest.version.as_deref(), Some("1.2.0"));
+
+        assert_eq!(detect(root).map(|project| project.kind), Some(Kind::CargoWorkspace));
+        let web = detect(&root.join("web")).expect("inside a package");
+        assert_eq!((web.kind, web.manifest.name.as_deref()), (Kind::Npm, Some("web")));
+    }
+
+    #[test]
+    fn crate_tags_name_packages_and_count_nested_crates() {
+        let tmp = tempfile::TempDir::new().expect("tempdir");
+        let root = tmp.path();
+        for (dir, manifest) in [
+            ("crates/core", "[package]\nname = \"core\"\nversion = \"0.3.1\"\n"),
+            ("crates/cli", "[package]\nname = \"cli\"\nversion.workspace = true\n"),
+        ] {
+            fs::create_dir_all(root.join(dir)).expect("mkdir");
+            fs::write(root.join(dir).join("Cargo.toml"), manifest).expect("write");
+        }
+        fs::create_dir(root.join("docs")).expect("mkdir");
+
+        assert_eq!(crate_tag(&root.join("crates/core")).as_deref(), Some("[crate core 0.3.1]"));
+        assert_eq!(crate_tag(&root.join("crates/cli")).as_deref(), Some("[crate cli]"));
+        assert_eq!(crate_tag(&root.join("crates")).as_deref(), Some("[2 crates]"));
+        assert_eq!(crate_tag(&root.join("docs")), None);
+    }
+
+    #[test]
+    fn npm_tags_join_name_and_version() {
+        let tmp = tempfile::TempDir::new().expect("tempdir");
+        let root = tmp.path();
+        fs::write(root.join("package.json"), r#"{"name": "@scope/web", "version": "2.0.1"}"#).expect("write");
+        assert_eq!(npm_tag(root).as_deref(), Some("[@scope/web@2.0.1]"));
+        fs::write(root.join("package.json"), r#"{"private": true}"#).expect("write");
+        assert_eq!(npm_tag(root).as_deref(), Some("[package]"));
+        fs::remove_file(root.join("package.json")).expect("remove");
+        assert_eq!(npm_tag(root), None);
+    }
+}
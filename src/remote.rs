@@ -0,0 +1,69 @@
+//! Detection of network filesystems, where every metadata call is a round
+//! trip to the server. Listings there read names and types only unless
+//! `--full` asks for the rest.
+
+use std::path::Path;
+
+/// Whether `path` lives on NFS, SMB/CIFS, AFS, Coda, 9P (including WSL's
+/// Windows drives), Ceph, or Lustre.
+#[cfg(target_os = "linux")]
+pub fn is_remote(path: &Path) -> bool {
+    const REMOTE_MAGIC: [u32; 9] = [
+        0x0000_6969, // NFS
+        0x0000_517B, // SMB
+        0xFE53_4D42, // SMB2
+        0xFF53_4D42, // CIFS
+        0x5346_414F, // AFS
+        0x7375_7245, // Coda
+        0x0102_1997, // 9P
+        0x00C3_6400, // Ceph
+        0x0BD0_0BD0, // Lustre
+    ];
+    // The magic is a 32-bit value in a word that is wider on some targets.
+    rustix::fs::statfs(path).is_ok_and(|stat| REMOTE_MAGIC.contains(&(stat.f_type as u32)))
+}
+
+/// Whether `path` is a UNC path (`\\server\share`) or on a mapped network drive.
+#[cfg(windows)]
+pub fn is_remote(path: &Path) -> bool {
+    use std::path::{Component, Prefix};
+
+    #[link(name = "kernel32")]
+    unsafe extern "system" {
+        fn GetDriveTypeW(root_path_name: *const u16) -> u32;
+    }
+    const DRIVE_REMOTE: u32 = 4;
+
+    let Ok(abs) = std::path::absolute(path) else {
+        return false;
+    };
+    let Some(Component::Prefix(prefix)) = abs.components().next() else {
+        return false;
+    };
+    match prefix.kind() {
+        Prefix::UNC(..) | Prefix::VerbatimUNC(..) => true,
+        Prefix::Disk(letter) | Prefix::VerbatimDisk(letter) => {
+            let root: Vec<u16> = format!("{}:\\", char::from(letter)).encode_utf16().chain([0]).collect();
+            // SAFETY: `root` is a NUL-terminated drive root such as `C:\`.
+            unsafe { GetDriveTypeW(root.as_ptr()) == DRIVE_REMOTE }
+        }
+        _ => false,
+    }
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+pub fn is_remote(_path: &Path) -> bool {
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn local_directories_are_not_remote() {
+        let tmp = tempfile::tempdir().expect("tempdir");
+        assert!(!is_remote(tmp.path()));
+        assert!(!is_remote(&tmp.path().join("missing")));
+    }
+}
@@ -0,0 +1,192 @@
+//! `--rename-preview 's/PATTERN/REPLACEMENT/FLAGS'`: what each name would
+//! become under a sed-style substitution, shown in a column. Nothing is
+//! renamed; new names that collide with another entry are flagged so a bulk
+//! rename can be checked before it is run.
+
+use regex::{Regex, RegexBuilder};
+use std::collections::HashMap;
+
+/// A parsed `s/PATTERN/REPLACEMENT/FLAGS` expression. The pattern uses
+/// extended syntax, as with `sed -E`.
+#[derive(Clone, Debug)]
+pub struct Substitution {
+    pattern: Regex,
+    /// The replacement in the `regex` crate's `${N}` spelling.
+    replacement: String,
+    /// `g`: replace every match, not just the first.
+    global: bool,
+}
+
+impl Substitution {
+    /// Parses `s/PATTERN/REPLACEMENT/FLAGS`. Any punctuation character may
+    /// stand in for `/`, and a backslash before it makes it literal. The
+    /// flags are `g` (every match) and `i` (ignore case).
+    pub fn parse(expr: &str) -> Result<Substitution, String> {
+        let rest = expr
+            .strip_prefix('s')
+            .ok_or_else(|| format!("expected s/PATTERN/REPLACEMENT/, got {expr:?}"))?;
+        let delim = rest
+            .chars()
+            .next()
+            .filter(|delim| !delim.is_alphanumeric() && !delim.is_whitespace() && *delim != '\\')
+            .ok_or_else(|| format!("expected a delimiter such as / after s in {expr:?}"))?;
+        let parts = split_unescaped(&rest[delim.len_utf8()..], delim);
+        let [pattern, replacement, flags] = parts.as_slice() else {
+            return Err(format!("expected s{delim}PATTERN{delim}REPLACEMENT{delim}, got {expr:?}"));
+        };
+        let (mut global, mut ignore_case) = (false, false);
+        for flag in flags.chars() {
+            match flag {
+                'g' => global = true,
+                'i' | 'I' => ignore_case = true,
+                _ => return Err(format!("unknown flag {flag:?} in {expr:?} (expected g or i)")),
+            }
+        }
+        let pattern = RegexBuilder::new(pattern)
+            .case_insensitive(ignore_case)
+            .build()
+            .map_err(|err| format!("invalid pattern in {expr:?}: {err}"))?;
+        Ok(Substitution {
+            pattern,
+            replacement: regex_replacement(replacement),
+            global,
+        })
+    }
+
+    /// `name` with the substitution applied, when that changes it.
+    pub fn apply(&self, name: &str) -> Option<String> {
+        let renamed = if self.global {
+            self.pattern.replace_all(name, self.replacement.as_str())
+        } else {
+            self.pattern.replace(name, self.replacement.as_str())
+        };
+        (renamed != name).then(|| renamed.into_owned())
+    }
+}
+
+/// Splits `text` on `delim`, dropping the backslash from `\<delim>` and
+/// keeping every other escape for the regex or replacement to read.
+fn split_unescaped(text: &str, delim: char) -> Vec<String> {
+    let mut parts = vec![String::new()];
+    let mut chars = text.chars();
+    while let Some(ch) = chars.next() {
+        let part = parts.last_mut().expect("at least one part");
+        match ch {
+            '\\' => match chars.next() {
+                Some(next) if next == delim => part.push(next),
+                Some(next) => {
+                    part.push('\\');
+                    part.push(next);
+                }
+                None => part.push('\\'),
+            },
+            _ if ch == delim => parts.push(String::new()),
+            _ => part.push(ch),
+        }
+    }
+    parts
+}
+
+/// Turns sed's `&` and `\1` into `${0}` and `${1}`, and escapes the `$`
+/// the `regex` crate would otherwise expand.
+fn regex_replacement(sed: &str) -> String {
+    let mut out = String::with_capacity(sed.len());
+    let mut chars = sed.chars();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '&' => out.push_str("${0}"),
+            '$' => out.push_str("$$"),
+            '\\' => match chars.next() {
+                Some(digit @ '0'..='9') => {
+                    out.push_str("${");
+                    out.push(digit);
+                    out.push('}');
+                }
+                Some('$') => out.push_str("$$"),
+                Some(other) => out.push(other),
+                None => out.push('\\'),
+            },
+            _ => out.push(ch),
+        }
+    }
+    out
+}
+
+/// The new name of one entry, for the preview column.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Preview {
+    pub name: String,
+    /// Another entry already has, or would end up with, this name.
+    pub clash: bool,
+}
+
+/// Previews `sub` over `names`, in order. `exists` says whether a name not
+/// among `names` (a hidden or filtered-out entry) is taken on disk.
+pub fn preview(sub: &Substitution, names: &[&str], exists: impl Fn(&str) -> bool) -> Vec<Option<Preview>> {
+    let renamed: Vec<Option<String>> = names.iter().map(|name| sub.apply(name)).collect();
+    let mut final_names: HashMap<&str, usize> = HashMap::new();
+    for (name, new) in names.iter().zip(&renamed) {
+        *final_names.entry(new.as_deref().unwrap_or(name)).or_default() += 1;
+    }
+    renamed
+        .iter()
+        .map(|new| {
+            let name = new.as_deref()?;
+            let clash = final_names[name] > 1 || (!names.contains(&name) && exists(name));
+            Some(Preview {
+                name: name.to_string(),
+                clash,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sub(expr: &str) -> Substitution {
+        Substitution::parse(expr).expect("valid expression")
+    }
+
+    #[test]
+    fn substitutions_read_like_sed() {
+        assert_eq!(sub("s/IMG_/photo-/").apply("IMG_0042.jpg").as_deref(), Some("photo-0042.jpg"));
+        assert_eq!(sub("s/a/A/").apply("banana").as_deref(), Some("bAnana"));
+        assert_eq!(sub("s/a/A/g").apply("banana").as_deref(), Some("bAnAnA"));
+        assert_eq!(sub("s/TXT$/md/i").apply("notes.txt").as_deref(), Some("notes.md"));
+        assert_eq!(
+            sub(r"s/(\w+)-(\d+)/\2_\1/").apply("report-2024.csv").as_deref(),
+            Some("2024_report.csv")
+        );
+        assert_eq!(sub("s/.*/[&]/").apply("x").as_deref(), Some("[x]"));
+        assert_eq!(sub(r"s/x/\&$1/").apply("x").as_deref(), Some("&$1"));
+        assert_eq!(sub(r"s|/|\||").apply("a/b").as_deref(), Some("a|b"));
+        assert_eq!(sub(r"s/\//-/").apply("a/b").as_deref(), Some("a-b"));
+        assert_eq!(sub("s/zzz/y/").apply("abc"), None);
+
+        assert!(Substitution::parse("y/a/b/").is_err());
+        assert!(Substitution::parse("s/a/b").is_err());
+        assert!(Substitution::parse("s/a/b/q").is_err());
+        assert!(Substitution::parse("s/(/b/").is_err());
+        assert!(Substitution::parse("sab").is_err());
+    }
+
+    #[test]
+    fn collisions_are_flagged() {
+        let names = ["a.txt", "b.txt", "a.md", "c.txt", "d.txt"];
+        let previews = preview(&sub(r"s/\.txt$/.md/"), &names, |name| name == "d.md");
+        let shown: Vec<Option<(&str, bool)>> = previews
+            .iter()
+            .map(|preview| preview.as_ref().map(|preview| (preview.name.as_str(), preview.clash)))
+            .collect();
+        assert_eq!(
+            shown,
+            [Some(("a.md", true)), Some(("b.md", false)), None, Some(("c.md", false)), Some(("d.md", true))]
+        );
+
+        // Renaming onto a name that is itself being renamed away is fine.
+        let previews = preview(&sub("s/^(.)/x&/"), &["a", "xa"], |_| true);
+        assert_eq!(previews[0].as_ref().map(|preview| preview.clash), Some(false));
+    }
+}
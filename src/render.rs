@@ -0,0 +1,123 @@
+//! Output modes of `list`. Each `Renderer` gets the sorted rows and the
+//! shared `Output` context (cell formatting, columns, where the rows live on
+//! disk) and owns its layout; `for_args` picks one from the flags.
+
+use crate::export::{self, SortMeta};
+use crate::{
+    Column, EntryRow, ListArgs, RowFormat, Session, TableFit, render_commas, render_oneline, render_table, render_tabs,
+    table_fit, terminal_width,
+};
+use std::path::{Path, PathBuf};
+
+/// What every output mode may draw on.
+pub struct Output<'a> {
+    /// The listed path.
+    pub base: &'a Path,
+    pub format: &'a RowFormat<'a>,
+    /// Columns for the modes that show more than names.
+    pub columns: &'a [Column],
+    pub sort: SortMeta,
+    /// Entries dropped by `--limit` or `--recent`.
+    pub omitted: usize,
+    pub path_of: &'a dyn Fn(&EntryRow) -> PathBuf,
+}
+
+pub trait Renderer {
+    fn render(&self, rows: Vec<EntryRow>, output: &Output) -> Result<String, String>;
+
+    /// Whether the output is a complete document that nothing may be printed
+    /// around: no project header, archive members, or footers.
+    fn standalone(&self) -> bool {
+        false
+    }
+
+    /// Whether notes about the table itself (sizes left unread) follow it.
+    fn takes_notes(&self) -> bool {
+        false
+    }
+}
+
+/// The renderer the output flags ask for, the bordered table by default.
+pub fn for_args<'a>(cli: &ListArgs, session: &'a Session) -> Box<dyn Renderer + 'a> {
+    if cli.json {
+        Box::new(Json)
+    } else if cli.csv {
+        Box::new(Csv)
+    } else if cli.oneline {
+        Box::new(Oneline)
+    } else if cli.commas {
+        Box::new(Commas)
+    } else if cli.tabs {
+        Box::new(Tabs)
+    } else {
+        Box::new(Table { fit: table_fit(session) })
+    }
+}
+
+/// The bordered table, fitted to `fit` when set.
+pub struct Table<'a> {
+    pub fit: Option<TableFit<'a>>,
+}
+
+impl Renderer for Table<'_> {
+    fn render(&self, rows: Vec<EntryRow>, output: &Output) -> Result<String, String> {
+        Ok(render_table(rows, output.columns, output.format, self.fit))
+    }
+
+    fn takes_notes(&self) -> bool {
+        true
+    }
+}
+
+/// `--tabs`
+pub struct Tabs;
+
+impl Renderer for Tabs {
+    fn render(&self, rows: Vec<EntryRow>, output: &Output) -> Result<String, String> {
+        Ok(render_tabs(&rows, output.columns, output.format))
+    }
+}
+
+/// `--oneline`
+pub struct Oneline;
+
+impl Renderer for Oneline {
+    fn render(&self, rows: Vec<EntryRow>, output: &Output) -> Result<String, String> {
+        Ok(render_oneline(&rows, output.format))
+    }
+}
+
+/// `--commas`, wrapped at the terminal width.
+pub struct Commas;
+
+impl Renderer for Commas {
+    fn render(&self, rows: Vec<EntryRow>, _output: &Output) -> Result<String, String> {
+        Ok(render_commas(&rows, terminal_width()))
+    }
+}
+
+/// `--json`
+pub struct Json;
+
+impl Renderer for Json {
+    fn render(&self, rows: Vec<EntryRow>, output: &Output) -> Result<String, String> {
+        export::render_json(output.base, &rows, output.sort.clone(), output.omitted, output.path_of)
+    }
+
+    fn standalone(&self) -> bool {
+        true
+    }
+}
+
+/// `--csv`
+pub struct Csv;
+
+impl Renderer for Csv {
+    fn render(&self, rows: Vec<EntryRow>, output: &Output) -> Result<String, String> {
+        Ok(export::render_csv(&rows, output.sort.clone(), output.path_of))
+    }
+
+    fn standalone(&self) -> bool {
+        true
+    }
+}
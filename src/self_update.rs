@@ -0,0 +1,290 @@
+//! `--self-update`: replaces the running binary with the newest GitHub
+//! release built for this platform. Downloads go through `curl`, the way git
+//! info goes through `git`, so nuls carries no TLS stack of its own, and a
+//! download is only unpacked once its SHA-256 matches the checksum published
+//! with the release.
+
+use crate::diag;
+use flate2::read::GzDecoder;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::env::consts::{ARCH, EXE_SUFFIX, OS};
+use std::fs;
+use std::io::{Cursor, Read};
+use std::path::Path;
+use std::process::Command;
+
+const LATEST_RELEASE: &str = "https://api.github.com/repos/cesarferreira/nuls/releases/latest";
+
+#[derive(Debug, Deserialize)]
+struct Release {
+    tag_name: String,
+    assets: Vec<Asset>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Asset {
+    name: String,
+    browser_download_url: String,
+}
+
+/// Updates the binary at `current_exe` when a newer release exists and
+/// returns what happened, for printing.
+pub fn run() -> Result<String, String> {
+    let current = env!("CARGO_PKG_VERSION");
+    let release: Release = serde_json::from_slice(&fetch(LATEST_RELEASE)?)
+        .map_err(|err| format!("unexpected reply from GitHub: {err}"))?;
+    let latest = release.tag_name.trim_start_matches('v');
+    if !is_newer(latest, current) {
+        return Ok(format!("nuls {current} is up to date (latest release: {})", release.tag_name));
+    }
+    let asset = pick_asset(&release.assets, OS, ARCH)
+        .ok_or_else(|| format!("release {} has no build for {ARCH} {OS}", release.tag_name))?;
+    let sums = checksum_asset(&release.assets, &asset.name)
+        .ok_or_else(|| format!("release {} publishes no checksum for {}; not updating", release.tag_name, asset.name))?;
+    let expected = find_checksum(&String::from_utf8_lossy(&fetch(&sums.browser_download_url)?), &asset.name)
+        .ok_or_else(|| format!("{} lists no checksum for {}; not updating", sums.name, asset.name))?;
+    diag::status(&format!("downloading {}", asset.name));
+    let download = fetch(&asset.browser_download_url)?;
+    let actual = sha256_hex(&download);
+    if actual != expected {
+        return Err(format!("checksum mismatch for {}: expected {expected}, got {actual}; not updating", asset.name));
+    }
+    let binary = unpack(&asset.name, &download)?;
+    let exe = std::env::current_exe().map_err(|err| format!("cannot locate the running binary: {err}"))?;
+    replace_exe(&exe, &binary)?;
+    Ok(format!("updated nuls {current} -> {latest} ({})", exe.display()))
+}
+
+/// The body at `url`, following redirects; HTTPS only.
+fn fetch(url: &str) -> Result<Vec<u8>, String> {
+    let output = Command::new("curl")
+        .args(["--fail", "--silent", "--show-error", "--location", "--proto", "=https", "--max-time", "300"])
+        .args(["--header", "Accept: application/vnd.github+json"])
+        .arg(url)
+        .output()
+        .map_err(|err| format!("cannot run curl (needed for --self-update): {err}"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("cannot download {url}: {}", stderr.trim()));
+    }
+    Ok(output.stdout)
+}
+
+/// Whether `latest` is a higher `major.minor.patch` than `current`; a tag
+/// that does not parse is never newer.
+fn is_newer(latest: &str, current: &str) -> bool {
+    fn parse(version: &str) -> Option<(u64, u64, u64)> {
+        let mut parts = version.split('.').map(|part| part.parse::<u64>().ok());
+        match (parts.next(), parts.next(), parts.next(), parts.next()) {
+            (Some(major), Some(minor), Some(patch), None) => Some((major?, minor?, patch?)),
+            _ => None,
+        }
+    }
+    matches!((parse(latest), parse(current)), (Some(latest), Some(current)) if latest > current)
+}
+
+/// Spellings of each platform in release asset names.
+fn aliases(name: &str) -> &'static [&'static str] {
+    match name {
+        "linux" => &["linux"],
+        "macos" => &["darwin", "macos", "apple"],
+        "windows" => &["windows"],
+        "x86_64" => &["x86_64", "amd64", "x64"],
+        "aarch64" => &["aarch64", "arm64"],
+        _ => &[],
+    }
+}
+
+/// Archive and binary formats `unpack` can read.
+fn is_unpackable(name: &str) -> bool {
+    let ext = name.rsplit_once('.').map(|(_, ext)| ext);
+    name.ends_with(".tar.gz") || name.ends_with(".tgz") || matches!(ext, Some("zip" | "exe") | None)
+}
+
+/// The asset built for `os` and `arch`; static musl builds win on Linux.
+fn pick_asset<'a>(assets: &'a [Asset], os: &str, arch: &str) -> Option<&'a Asset> {
+    let matches = |asset: &&Asset| {
+        let name = asset.name.to_ascii_lowercase();
+        let has = |key: &str| aliases(key).iter().any(|alias| name.contains(alias));
+        has(os) && has(arch) && is_unpackable(&name)
+    };
+    let mut candidates: Vec<&Asset> = assets.iter().filter(matches).collect();
+    candidates.sort_by_key(|asset| !asset.name.contains("musl"));
+    candidates.first().copied()
+}
+
+/// `<name>.sha256`, or failing that a combined sums file (`SHA256SUMS`,
+/// `checksums.txt`, ...).
+fn checksum_asset<'a>(assets: &'a [Asset], name: &str) -> Option<&'a Asset> {
+    let own = format!("{name}.sha256").to_ascii_lowercase();
+    assets
+        .iter()
+        .find(|asset| asset.name.to_ascii_lowercase() == own)
+        .or_else(|| {
+            assets.iter().find(|asset| {
+                let lower = asset.name.to_ascii_lowercase();
+                lower.contains("sha256sum") || lower.starts_with("checksums")
+            })
+        })
+}
+
+/// The hex digest listed for `name` in `sha256sum` output; a file holding a
+/// lone digest counts as `name`'s.
+fn find_checksum(text: &str, name: &str) -> Option<String> {
+    text.lines().find_map(|line| {
+        let mut fields = line.split_whitespace();
+        let digest = fields.next()?.to_ascii_lowercase();
+        let listed = fields.next().map(|file| file.trim_start_matches('*'));
+        let valid = digest.len() == 64 && digest.bytes().all(|byte| byte.is_ascii_hexdigit());
+        (valid && listed.is_none_or(|file| file == name)).then_some(digest)
+    })
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes).iter().fold(String::new(), |mut hex, byte| {
+        hex.push_str(&format!("{byte:02x}"));
+        hex
+    })
+}
+
+/// The `nuls` executable inside a downloaded archive, or the download itself
+/// when the asset is the bare binary.
+fn unpack(name: &str, download: &[u8]) -> Result<Vec<u8>, String> {
+    let binary = format!("nuls{EXE_SUFFIX}");
+    let is_binary = |path: &Path| path.file_name().is_some_and(|file| file == binary.as_str());
+    let mut found = Vec::new();
+    if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+        let mut archive = tar::Archive::new(GzDecoder::new(download));
+        for entry in archive.entries().map_err(|err| format!("cannot read {name}: {err}"))? {
+            let mut entry = entry.map_err(|err| format!("cannot read {name}: {err}"))?;
+            if entry.header().entry_type().is_file() && entry.path().is_ok_and(|path| is_binary(&path)) {
+                entry.read_to_end(&mut found).map_err(|err| format!("cannot read {name}: {err}"))?;
+                return Ok(found);
+            }
+        }
+    } else if name.ends_with(".zip") {
+        let mut archive =
+            zip::ZipArchive::new(Cursor::new(download)).map_err(|err| format!("cannot read {name}: {err}"))?;
+        for idx in 0..archive.len() {
+            let mut entry = archive.by_index(idx).map_err(|err| format!("cannot read {name}: {err}"))?;
+            if entry.is_file() && entry.enclosed_name().is_some_and(|path| is_binary(&path)) {
+                entry.read_to_end(&mut found).map_err(|err| format!("cannot read {name}: {err}"))?;
+                return Ok(found);
+            }
+        }
+    } else {
+        return Ok(download.to_vec());
+    }
+    Err(format!("{name} holds no {binary}"))
+}
+
+/// Writes `binary` beside `exe` and renames it over `exe`, so an interrupted
+/// update leaves the old binary working. Windows cannot replace a running
+/// executable, but can rename it: the old one is moved to `nuls.old.exe`
+/// first and removed by the next update.
+fn replace_exe(exe: &Path, binary: &[u8]) -> Result<(), String> {
+    let staged = exe.with_file_name(format!(".nuls-update-{}{EXE_SUFFIX}", std::process::id()));
+    let permissions = fs::metadata(exe)
+        .map_err(|err| format!("cannot read {}: {err}", exe.display()))?
+        .permissions();
+    let installed = fs::write(&staged, binary)
+        .and_then(|()| fs::set_permissions(&staged, permissions))
+        .and_then(|()| {
+            if cfg!(windows) {
+                let old = exe.with_extension("old.exe");
+                let _ = fs::remove_file(&old);
+                fs::rename(exe, &old)?;
+                fs::rename(&staged, exe).inspect_err(|_| {
+                    let _ = fs::rename(&old, exe);
+                })
+            } else {
+                fs::rename(&staged, exe)
+            }
+        });
+    installed.map_err(|err| {
+        let _ = fs::remove_file(&staged);
+        format!("cannot replace {}: {err}", exe.display())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn asset(name: &str) -> Asset {
+        Asset {
+            name: name.to_string(),
+            browser_download_url: format!("https://example.invalid/{name}"),
+        }
+    }
+
+    #[test]
+    fn only_higher_versions_are_newer() {
+        assert!(is_newer("0.3.0", "0.2.0"));
+        assert!(is_newer("0.10.0", "0.9.9"));
+        assert!(!is_newer("0.2.0", "0.2.0"));
+        assert!(!is_newer("0.1.9", "0.2.0"));
+        assert!(!is_newer("0.3.0-rc.1", "0.2.0"));
+        assert!(!is_newer("nightly", "0.2.0"));
+    }
+
+    #[test]
+    fn assets_are_matched_to_the_platform() {
+        let assets: Vec<Asset> = [
+            "nuls-x86_64-unknown-linux-gnu.tar.gz",
+            "nuls-x86_64-unknown-linux-musl.tar.gz",
+            "nuls-x86_64-unknown-linux-musl.tar.gz.sha256",
+            "nuls-aarch64-apple-darwin.tar.gz",
+            "nuls-x86_64-pc-windows-msvc.zip",
+            "SHA256SUMS",
+        ]
+        .into_iter()
+        .map(asset)
+        .collect();
+        let name = |os, arch| pick_asset(&assets, os, arch).map(|asset| asset.name.as_str());
+        assert_eq!(name("linux", "x86_64"), Some("nuls-x86_64-unknown-linux-musl.tar.gz"));
+        assert_eq!(name("macos", "aarch64"), Some("nuls-aarch64-apple-darwin.tar.gz"));
+        assert_eq!(name("windows", "x86_64"), Some("nuls-x86_64-pc-windows-msvc.zip"));
+        assert_eq!(name("freebsd", "x86_64"), None);
+        let sums = |name| checksum_asset(&assets, name).map(|asset| asset.name.as_str());
+        let musl = "nuls-x86_64-unknown-linux-musl.tar.gz";
+        assert_eq!(sums(musl), Some("nuls-x86_64-unknown-linux-musl.tar.gz.sha256"));
+        assert_eq!(sums("nuls-aarch64-apple-darwin.tar.gz"), Some("SHA256SUMS"));
+    }
+
+    #[test]
+    fn checksums_are_read_from_sums_files() {
+        let digest = sha256_hex(b"nuls");
+        let other = sha256_hex(b"other");
+        let sums = format!("{other}  nuls-a.tar.gz\n{digest} *nuls-b.tar.gz\n");
+        assert_eq!(find_checksum(&sums, "nuls-b.tar.gz"), Some(digest.clone()));
+        assert_eq!(find_checksum(&sums, "nuls-c.tar.gz"), None);
+        assert_eq!(find_checksum(&format!("{digest}\n"), "anything"), Some(digest));
+        assert_eq!(find_checksum("not-a-digest  nuls-b.tar.gz", "nuls-b.tar.gz"), None);
+    }
+
+    #[test]
+    fn the_binary_is_unpacked_and_swapped_in() {
+        let mut tarball = tar::Builder::new(flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast()));
+        let binary = format!("nuls-x86_64/nuls{EXE_SUFFIX}");
+        for (path, body) in [("nuls-x86_64/README.md", &b"docs"[..]), (&binary, b"new")] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(body.len() as u64);
+            header.set_mode(0o755);
+            header.set_cksum();
+            tarball.append_data(&mut header, path, body).expect("append");
+        }
+        let download = tarball.into_inner().expect("tar").finish().expect("gzip");
+        assert_eq!(unpack("nuls-x86_64.tar.gz", &download).expect("unpacked"), b"new");
+        assert!(unpack("nuls-x86_64.tar.gz", b"garbage").is_err());
+
+        let tmp = tempfile::tempdir().expect("tempdir");
+        let exe = tmp.path().join(format!("nuls{EXE_SUFFIX}"));
+        fs::write(&exe, b"old").expect("write");
+        replace_exe(&exe, b"new").expect("replaced");
+        assert_eq!(fs::read(&exe).expect("read"), b"new");
+        let leftovers = fs::read_dir(tmp.path()).expect("read_dir").count();
+        assert_eq!(leftovers, if cfg!(windows) { 2 } else { 1 });
+    }
+}
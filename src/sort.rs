@@ -0,0 +1,361 @@
+//! Listing order. A `SortSpec` chains sort criteria, each consulted only for
+//! rows the ones before it leave tied, then the tie-break; `--reverse` flips
+//! the whole result, except that directories stay on top when they are
+//! pinned there. The CLI builds one from `--sort`, `--tie-break`, and
+//! `--dirs-first-with-time`. A `.nulsorder` file in the listed directory
+//! puts the names it lists first when no sort was asked for.
+
+use crate::export::{SortMeta, value_name};
+use crate::{EntryRow, SortKey, TieBreak};
+use clap::ValueEnum;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use std::rc::Rc;
+use std::time::SystemTime;
+
+type Comparator = Rc<dyn Fn(&EntryRow, &EntryRow) -> Ordering>;
+
+/// A direction spelled out for one key of `--sort` (`size:asc`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Direction {
+    /// A to Z, oldest, smallest, fewest entries
+    Asc,
+    /// Z to A, newest, largest, most entries
+    Desc,
+}
+
+impl Direction {
+    /// `ascending`, the key's A-to-Z comparison, turned this way.
+    fn apply(self, ascending: Ordering) -> Ordering {
+        match self {
+            Direction::Asc => ascending,
+            Direction::Desc => ascending.reverse(),
+        }
+    }
+}
+
+#[derive(Clone)]
+enum Criterion {
+    /// A key in its own default order, or in `Direction` when one is given.
+    Key(SortKey, Option<Direction>),
+    /// A comparison that is not a `--sort` key, such as `.nulsorder` ranks.
+    Custom(Comparator),
+}
+
+impl Criterion {
+    fn compare(&self, a: &EntryRow, b: &EntryRow) -> Ordering {
+        match self {
+            Criterion::Key(SortKey::Name, None) => b.is_dir().cmp(&a.is_dir()),
+            Criterion::Key(SortKey::Modified, None) => compare_modified_desc(&a.modified_time, &b.modified_time),
+            Criterion::Key(SortKey::Size, None) => b.size_bytes.cmp(&a.size_bytes),
+            Criterion::Key(SortKey::Entries, None) => b.child_count.cmp(&a.child_count),
+            Criterion::Key(key, Some(direction)) => compare_directed(*key, *direction, a, b),
+            Criterion::Custom(compare) => compare(a, b),
+        }
+    }
+}
+
+/// `key` in `direction`. Directories are not grouped, even for `name`, and
+/// rows without a time or entry count go last either way.
+fn compare_directed(key: SortKey, direction: Direction, a: &EntryRow, b: &EntryRow) -> Ordering {
+    match key {
+        SortKey::Name => direction.apply(compare_names(a, b)),
+        SortKey::Modified => compare_known_first(a.modified_time, b.modified_time, direction),
+        SortKey::Size => direction.apply(a.size_bytes.cmp(&b.size_bytes)),
+        SortKey::Entries => compare_known_first(a.child_count, b.child_count, direction),
+    }
+}
+
+fn compare_known_first<T: Ord>(a: Option<T>, b: Option<T>, direction: Direction) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => direction.apply(a.cmp(&b)),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+/// `--sort` as given: keys by priority, each with its direction if spelled
+/// out (`size:desc,name:asc`).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SortOrder {
+    keys: Vec<(SortKey, Option<Direction>)>,
+}
+
+impl SortOrder {
+    /// `key` alone, in its default order.
+    pub fn of(key: SortKey) -> SortOrder {
+        SortOrder { keys: vec![(key, None)] }
+    }
+
+    pub fn parse(text: &str) -> Result<SortOrder, String> {
+        let keys = text
+            .split(',')
+            .map(|part| {
+                let (key, direction) = match part.trim().split_once(':') {
+                    Some((key, direction)) => (key, Some(direction)),
+                    None => (part.trim(), None),
+                };
+                let key = SortKey::from_str(key, true)
+                    .map_err(|_| format!("unknown sort key '{key}' (expected name, modified, size, or entries)"))?;
+                let direction = direction
+                    .map(|direction| {
+                        Direction::from_str(direction, true)
+                            .map_err(|_| format!("unknown direction '{direction}' for {part} (expected asc or desc)"))
+                    })
+                    .transpose()?;
+                Ok((key, direction))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        Ok(SortOrder { keys })
+    }
+
+    /// The key consulted first.
+    pub fn primary(&self) -> SortKey {
+        self.keys[0].0
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = SortKey> + '_ {
+        self.keys.iter().map(|(key, _)| *key)
+    }
+
+    /// Orders by each key in turn, ties broken by name.
+    pub fn spec(&self) -> SortSpec {
+        SortSpec {
+            criteria: self.keys.iter().map(|&(key, direction)| Criterion::Key(key, direction)).collect(),
+            order: self.clone(),
+            ..SortSpec::by(self.primary())
+        }
+    }
+}
+
+/// The flag's spelling: `name`, or `size:desc,name:asc`.
+impl fmt::Display for SortOrder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (idx, (key, direction)) in self.keys.iter().enumerate() {
+            if idx > 0 {
+                f.write_str(",")?;
+            }
+            f.write_str(&value_name(*key))?;
+            if let Some(direction) = direction {
+                write!(f, ":{}", value_name(*direction))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// How to order rows; rows comparing equal keep the order they came in.
+#[derive(Clone)]
+pub struct SortSpec {
+    criteria: Vec<Criterion>,
+    /// The keys as `--sort` spells them, for export metadata.
+    order: SortOrder,
+    /// Names came first by `explicit_order`.
+    explicit_order: bool,
+    reverse: bool,
+    tie_break: TieBreak,
+    dirs_first: bool,
+}
+
+impl SortSpec {
+    /// Order by `key`, ties broken by name.
+    pub fn by(key: SortKey) -> SortSpec {
+        SortSpec {
+            criteria: vec![Criterion::Key(key, None)],
+            order: SortOrder::of(key),
+            explicit_order: false,
+            reverse: false,
+            tie_break: TieBreak::Name,
+            dirs_first: false,
+        }
+    }
+
+    pub fn reverse(mut self, reverse: bool) -> SortSpec {
+        self.reverse = reverse;
+        self
+    }
+
+    pub fn tie_break(mut self, tie_break: TieBreak) -> SortSpec {
+        self.tie_break = tie_break;
+        self
+    }
+
+    /// Puts rows named in `names` first, in that order, ahead of every
+    /// criterion; the rest follow them sorted as before.
+    pub fn explicit_order(mut self, names: &[String]) -> SortSpec {
+        let mut ranks = HashMap::new();
+        for (rank, name) in names.iter().enumerate() {
+            ranks.entry(name.clone()).or_insert(rank);
+        }
+        let by_rank = move |a: &EntryRow, b: &EntryRow| {
+            compare_known_first(ranks.get(&a.name_plain), ranks.get(&b.name_plain), Direction::Asc)
+        };
+        self.criteria.insert(0, Criterion::Custom(Rc::new(by_rank)));
+        self.explicit_order = true;
+        self
+    }
+
+    /// Puts directories before files ahead of every criterion, whatever
+    /// `reverse` says.
+    pub fn dirs_first(mut self, dirs_first: bool) -> SortSpec {
+        self.dirs_first = dirs_first;
+        self
+    }
+
+    pub fn compare(&self, a: &EntryRow, b: &EntryRow) -> Ordering {
+        let cmp = self
+            .criteria
+            .iter()
+            .fold(Ordering::Equal, |cmp, criterion| cmp.then_with(|| criterion.compare(a, b)))
+            .then_with(|| compare_tie_break(a, b, self.tie_break));
+        let cmp = if self.reverse { cmp.reverse() } else { cmp };
+        if self.dirs_first {
+            b.is_dir().cmp(&a.is_dir()).then(cmp)
+        } else {
+            cmp
+        }
+    }
+
+    pub fn sort(&self, rows: &mut [EntryRow]) {
+        rows.sort_by(|a, b| self.compare(a, b));
+    }
+
+    /// What `--json` and `--csv` record about this ordering.
+    pub fn meta(&self) -> SortMeta {
+        SortMeta {
+            key: self.order.to_string(),
+            reverse: self.reverse,
+            tie_break: value_name(self.tie_break),
+            dirs_first: self.dirs_first,
+            explicit_order: self.explicit_order,
+        }
+    }
+}
+
+fn compare_tie_break(a: &EntryRow, b: &EntryRow, tie_break: TieBreak) -> Ordering {
+    match tie_break {
+        TieBreak::Name => compare_names(a, b),
+        TieBreak::None => Ordering::Equal,
+    }
+}
+
+/// The names listed in `dir`'s `.nulsorder`, one per line, in order; blank
+/// lines and `#` comments are skipped, as is a trailing `/` on a directory.
+/// `None` when there is no such file.
+pub fn read_order_file(dir: &Path) -> Option<Vec<String>> {
+    let text = std::fs::read_to_string(dir.join(".nulsorder")).ok()?;
+    Some(
+        text.lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| line.strip_suffix('/').unwrap_or(line).to_string())
+            .collect(),
+    )
+}
+
+/// Case-insensitive, then exact so `B` and `b` keep a fixed order.
+fn compare_names(a: &EntryRow, b: &EntryRow) -> Ordering {
+    a.name_plain
+        .to_lowercase()
+        .cmp(&b.name_plain.to_lowercase())
+        .then_with(|| a.name_plain.cmp(&b.name_plain))
+}
+
+fn compare_modified_desc(a: &Option<SystemTime>, b: &Option<SystemTime>) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => b.cmp(a), // newest first
+        (Some(_), None) => Ordering::Less, // real timestamps before unknown
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::test_row;
+    use std::time::Duration;
+
+    #[test]
+    fn compare_modified_orders_newest_first_logic() {
+        let now = SystemTime::now();
+        let older = Some(now - Duration::from_secs(10));
+        let newer = Some(now - Duration::from_secs(1));
+        assert_eq!(compare_modified_desc(&newer, &older), Ordering::Less);
+        assert_eq!(compare_modified_desc(&older, &newer), Ordering::Greater);
+        assert_eq!(compare_modified_desc(&Some(now), &None), Ordering::Less);
+        assert_eq!(compare_modified_desc(&None, &Some(now)), Ordering::Greater);
+    }
+
+    #[test]
+    fn pinned_directories_stay_first_when_reversed() {
+        let now = SystemTime::now();
+        let ago = |secs| Some(now - Duration::from_secs(secs));
+        let mut rows = vec![
+            test_row("old.txt", false, ago(30)),
+            test_row("docs", true, ago(20)),
+            test_row("new.txt", false, ago(1)),
+            test_row("src", true, ago(5)),
+        ];
+        let spec = SortSpec::by(SortKey::Modified).dirs_first(true);
+        spec.sort(&mut rows);
+        let names: Vec<&str> = rows.iter().map(|row| row.name_plain.as_str()).collect();
+        assert_eq!(names, ["src", "docs", "new.txt", "old.txt"]);
+        spec.reverse(true).sort(&mut rows);
+        let names: Vec<&str> = rows.iter().map(|row| row.name_plain.as_str()).collect();
+        assert_eq!(names, ["docs", "src", "old.txt", "new.txt"]);
+    }
+
+    #[test]
+    fn explicit_order_comes_first_and_the_rest_follow() {
+        let mut rows: Vec<EntryRow> = ["appendix.md", "intro.md", "assets", "setup.md", "zeta.md"]
+            .into_iter()
+            .map(|name| test_row(name, name == "assets", None))
+            .collect();
+        let order = ["setup.md", "intro.md", "missing.md", "setup.md", "zeta.md"].map(String::from);
+        SortSpec::by(SortKey::Name).explicit_order(&order).sort(&mut rows);
+        let names: Vec<&str> = rows.iter().map(|row| row.name_plain.as_str()).collect();
+        assert_eq!(names, ["setup.md", "intro.md", "zeta.md", "assets", "appendix.md"]);
+    }
+
+    #[test]
+    fn git_markers_never_break_name_ties() {
+        // Names are compared bare, so `foo (+1 -1)` does not sort after
+        // `Foo` for its marker; the exact spelling decides, either way round.
+        for modified in ["foo", "Foo"] {
+            let mut rows: Vec<EntryRow> = ["foo", "Foo"].into_iter().map(|name| test_row(name, false, None)).collect();
+            for row in rows.iter_mut().filter(|row| row.name_plain == modified) {
+                row.git = Some(crate::GitStatus { added: Some(1), deleted: Some(1), ..Default::default() });
+            }
+            SortSpec::by(SortKey::Name).sort(&mut rows);
+            let names: Vec<&str> = rows.iter().map(|row| row.name_plain.as_str()).collect();
+            assert_eq!(names, ["Foo", "foo"]);
+        }
+    }
+
+    #[test]
+    fn sort_orders_take_a_direction_per_key() {
+        let order = SortOrder::parse("size:desc,NAME:asc").expect("parse ok");
+        assert_eq!(order.to_string(), "size:desc,name:asc");
+        assert_eq!(SortOrder::parse("modified").expect("parse ok"), SortOrder::of(SortKey::Modified));
+        assert!(SortOrder::parse("size:up").unwrap_err().contains("unknown direction 'up'"));
+        assert!(SortOrder::parse("colour").unwrap_err().contains("unknown sort key 'colour'"));
+
+        let mut rows: Vec<EntryRow> = ["b", "dir", "A", "c"]
+            .into_iter()
+            .map(|name| test_row(name, name == "dir", None))
+            .collect();
+        for (row, size) in rows.iter_mut().zip([10, 10, 10, 99]) {
+            row.size_bytes = size;
+        }
+        order.spec().tie_break(TieBreak::None).sort(&mut rows);
+        let names: Vec<&str> = rows.iter().map(|row| row.name_plain.as_str()).collect();
+        assert_eq!(names, ["c", "A", "b", "dir"]);
+        SortOrder::parse("size:asc,name:desc").expect("parse ok").spec().sort(&mut rows);
+        let names: Vec<&str> = rows.iter().map(|row| row.name_plain.as_str()).collect();
+        assert_eq!(names, ["dir", "b", "A", "c"]);
+    }
+}
@@ -0,0 +1,284 @@
+//! `nuls stat PATH`: a vertical card with everything nuls knows about one
+//! entry, themed like the listing table.
+
+use crate::config::RecencyThresholds;
+use crate::i18n::Strings;
+use crate::width::display_width;
+use crate::{
+    Align, BorderKind, GitOptions, allocated_size, color_modified, format_git,
+    format_relative_time, format_size, horizontal_border, interrupt, load_git_info, palette, progress, render_row,
+};
+use chrono::{DateTime, Local};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+use std::time::SystemTime;
+
+/// Label, plain value, and colored value for one line of the card.
+type Field = (&'static str, String, String);
+
+pub struct StatOptions<'a> {
+    pub hash: bool,
+    pub recency: RecencyThresholds,
+    pub now: SystemTime,
+    pub strings: &'a Strings,
+    pub git: &'a GitOptions,
+}
+
+/// Describes `path` itself (symlinks are not followed) as a bordered
+/// two-column card.
+pub fn render_stat(path: &Path, options: &StatOptions) -> Result<String, String> {
+    let metadata =
+        fs::symlink_metadata(path).map_err(|err| format!("cannot read metadata for {}: {err}", path.display()))?;
+    let file_type = metadata.file_type();
+    let strings = options.strings;
+    let mut fields: Vec<Field> = Vec::new();
+
+    fields.push(field("path", path.display().to_string(), palette::FILE));
+    let kind = if file_type.is_symlink() {
+        "symlink"
+    } else if file_type.is_dir() {
+        strings.dir
+    } else {
+        strings.file
+    };
+    fields.push(field("type", kind.to_string(), palette::TYPE));
+    if file_type.is_symlink() {
+        let target = fs::read_link(path).map_err(|err| format!("cannot read link {}: {err}", path.display()))?;
+        let target_text = target.display().to_string();
+        if fs::metadata(path).is_ok() {
+            fields.push(field("target", target_text, palette::LINK));
+        } else {
+            fields.push(field("target", format!("{target_text} ({})", strings.broken_link), palette::WARN));
+        }
+    }
+
+    let size = metadata.len();
+    fields.push(field("size", format!("{} ({size} bytes)", format_size(size)), palette::SIZE));
+    if let Some(allocated) = allocated_size(path, &metadata) {
+        fields.push(field("disk", format_size(allocated), palette::SIZE));
+    }
+    fields.extend(platform_fields(&metadata));
+
+    let times = [
+        ("modified", metadata.modified().ok()),
+        ("accessed", metadata.accessed().ok()),
+        change_time(&metadata),
+    ];
+    for (label, time) in times {
+        fields.push(match time {
+            Some(ts) => time_field(label, ts, options),
+            None => field(label, strings.unknown.to_string(), palette::GIT_CLEAN),
+        });
+    }
+
+    if let Some(git) = git_field(path, options.git)? {
+        fields.push(git);
+    }
+    if options.hash {
+        let digest = if file_type.is_file() {
+            sha256_file(path)?
+        } else {
+            "-".to_string()
+        };
+        fields.push(field("sha256", digest, palette::GIT_CLEAN));
+    }
+
+    Ok(render_card(&fields))
+}
+
+fn field(label: &'static str, value: String, color: &str) -> Field {
+    let colored = palette::paint(&value, color);
+    (label, value, colored)
+}
+
+/// Absolute local time followed by the relative phrase, colored by recency.
+fn time_field(label: &'static str, ts: SystemTime, options: &StatOptions) -> Field {
+    let (relative, recency) = format_relative_time(ts, options.now, &options.recency, options.strings);
+    let local: DateTime<Local> = ts.into();
+    let absolute = local.format("%Y-%m-%d %H:%M:%S %z").to_string();
+    let plain = format!("{absolute} ({relative})");
+    let colored = format!("{} ({})", palette::paint(&absolute, palette::MODIFIED), color_modified(&relative, recency));
+    (label, plain, colored)
+}
+
+/// Status of `path` in its parent's repository: `clean`, the usual `(+a -d)`
+/// summary, or no line at all outside a work tree.
+fn git_field(path: &Path, git: &GitOptions) -> Result<Option<Field>, String> {
+    // Resolve the parent rather than `path` so dangling symlinks still work.
+    let abs = match (path.parent(), path.file_name()) {
+        (Some(parent), Some(name)) => {
+            let parent = if parent.as_os_str().is_empty() { Path::new(".") } else { parent };
+            parent.canonicalize().map(|parent| parent.join(name))
+        }
+        _ => path.canonicalize(),
+    }
+    .map_err(|err| format!("cannot canonicalize {}: {err}", path.display()))?;
+    let (Some(parent), Some(name)) = (abs.parent(), abs.file_name()) else {
+        return Ok(None);
+    };
+    // A repository git cannot describe (e.g. no commits yet) just omits the line.
+    let Ok(Some(info)) = load_git_info(parent, git) else {
+        return Ok(None);
+    };
+    let summary = info
+        .entries
+        .get(name.to_string_lossy().as_ref())
+        .and_then(format_git)
+        .filter(|(plain, _)| !plain.is_empty());
+    Ok(Some(match summary {
+        Some((plain, colored)) => ("git", plain, colored),
+        None => field("git", "clean".to_string(), palette::GIT_CLEAN),
+    }))
+}
+
+/// Hex SHA-256 of the file contents, read in fixed-size chunks, or
+/// `(interrupted)` if Ctrl-C arrives first.
+fn sha256_file(path: &Path) -> Result<String, String> {
+    let mut file = fs::File::open(path).map_err(|err| format!("cannot open {}: {err}", path.display()))?;
+    let total = file.metadata().map(|metadata| metadata.len()).unwrap_or(0);
+    let bar = progress::bytes("hashing", total);
+    let mut hasher = Sha256::new();
+    let mut buf = [0u8; 64 * 1024];
+    loop {
+        let read = file
+            .read(&mut buf)
+            .map_err(|err| format!("cannot read {}: {err}", path.display()))?;
+        if read == 0 {
+            break;
+        }
+        if interrupt::requested() {
+            bar.finish_and_clear();
+            return Ok("(interrupted)".to_string());
+        }
+        hasher.update(&buf[..read]);
+        bar.inc(read as u64);
+    }
+    bar.finish_and_clear();
+    Ok(hasher.finalize().iter().map(|byte| format!("{byte:02x}")).collect())
+}
+
+fn render_card(fields: &[Field]) -> String {
+    let rows: Vec<Vec<(String, String, Align)>> = fields
+        .iter()
+        .map(|(label, plain, colored)| {
+            vec![
+                (label.to_string(), palette::paint(label, palette::HEADER), Align::Left),
+                (plain.clone(), colored.clone(), Align::Left),
+            ]
+        })
+        .collect();
+    let widths: Vec<usize> = (0..2)
+        .map(|col| rows.iter().map(|cells| display_width(&cells[col].0)).max().unwrap_or(0))
+        .collect();
+
+    let mut out = String::new();
+    out.push_str(&horizontal_border(&widths, BorderKind::Top));
+    out.push('\n');
+    for cells in &rows {
+        out.push_str(&render_row(cells, &widths));
+        out.push('\n');
+    }
+    out.push_str(&horizontal_border(&widths, BorderKind::Bottom));
+    out.push('\n');
+    out
+}
+
+/// `drwxr-xr-x`-style permission string for a Unix `st_mode`, including the
+/// setuid, setgid, and sticky bits.
+#[cfg(unix)]
+pub fn format_mode(mode: u32) -> String {
+    let kind = match mode & 0o170_000 {
+        0o040_000 => 'd',
+        0o120_000 => 'l',
+        0o020_000 => 'c',
+        0o060_000 => 'b',
+        0o010_000 => 'p',
+        0o140_000 => 's',
+        _ => '-',
+    };
+    let mut text = String::from(kind);
+    let specials = [(0o4000, 's'), (0o2000, 's'), (0o1000, 't')];
+    for (shift, (special_bit, special_char)) in [6, 3, 0].into_iter().zip(specials) {
+        let bits = (mode >> shift) & 0o7;
+        text.push(if bits & 0o4 != 0 { 'r' } else { '-' });
+        text.push(if bits & 0o2 != 0 { 'w' } else { '-' });
+        text.push(match (bits & 0o1 != 0, mode & special_bit != 0) {
+            (true, true) => special_char,
+            (false, true) => special_char.to_ascii_uppercase(),
+            (true, false) => 'x',
+            (false, false) => '-',
+        });
+    }
+    text
+}
+
+#[cfg(unix)]
+fn platform_fields(metadata: &fs::Metadata) -> Vec<Field> {
+    use crate::owner::Owners;
+    use std::os::unix::fs::MetadataExt;
+    let owners = Owners::load();
+    let mode = metadata.mode();
+    vec![
+        field(
+            "permissions",
+            format!("{} ({:04o})", format_mode(mode), mode & 0o7777),
+            palette::WARN,
+        ),
+        field(
+            "owner",
+            format!("{} ({})", owners.user_label(metadata.uid()), metadata.uid()),
+            palette::DOTFILE,
+        ),
+        field(
+            "group",
+            format!("{} ({})", owners.group_label(metadata.gid()), metadata.gid()),
+            palette::DOTFILE,
+        ),
+        field("inode", metadata.ino().to_string(), palette::INDEX),
+        field("links", metadata.nlink().to_string(), palette::INDEX),
+        field("device", format!("{:#x}", metadata.dev()), palette::INDEX),
+    ]
+}
+
+#[cfg(not(unix))]
+fn platform_fields(metadata: &fs::Metadata) -> Vec<Field> {
+    let access = if metadata.permissions().readonly() {
+        "read-only"
+    } else {
+        "read-write"
+    };
+    vec![field("permissions", access.to_string(), palette::WARN)]
+}
+
+/// Unix reports the inode change time; elsewhere creation time is the third timestamp.
+#[cfg(unix)]
+fn change_time(metadata: &fs::Metadata) -> (&'static str, Option<SystemTime>) {
+    use std::os::unix::fs::MetadataExt;
+    let changed = u64::try_from(metadata.ctime()).ok().map(|secs| {
+        SystemTime::UNIX_EPOCH + std::time::Duration::new(secs, metadata.ctime_nsec().clamp(0, 999_999_999) as u32)
+    });
+    ("changed", changed)
+}
+
+#[cfg(not(unix))]
+fn change_time(metadata: &fs::Metadata) -> (&'static str, Option<SystemTime>) {
+    ("created", metadata.created().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn mode_strings_cover_special_bits() {
+        assert_eq!(format_mode(0o040_755), "drwxr-xr-x");
+        assert_eq!(format_mode(0o100_644), "-rw-r--r--");
+        assert_eq!(format_mode(0o104_755), "-rwsr-xr-x");
+        assert_eq!(format_mode(0o102_745), "-rwxr-Sr-x");
+        assert_eq!(format_mode(0o041_777), "drwxrwxrwt");
+        assert_eq!(format_mode(0o120_777), "lrwxrwxrwx");
+    }
+}
@@ -0,0 +1,106 @@
+//! `nuls --show-theme`: a made-up listing that puts every palette role on
+//! screen at once (each recency bucket, git state, and file class), so a
+//! theme can be judged without hunting for real files in each state.
+
+use crate::annotate::Annotation;
+use crate::{EntryRow, EntryType, GitStatus, HardLink, SecurityFlags, Session, palette};
+use std::time::{Duration, SystemTime};
+
+/// How a sample row's name is classified and colored.
+#[derive(Clone, Copy)]
+enum Class {
+    Dir,
+    File,
+    Exec,
+    Dotfile,
+    Doc,
+    Junk,
+    Security,
+    BrokenLink,
+    HardLink,
+}
+
+/// Git state shown next to a sample row's name.
+#[derive(Clone, Copy)]
+enum Git {
+    None,
+    Untracked,
+    Subtree,
+    Changed,
+    Dirty,
+}
+
+/// One sample per recency bucket, oldest last, with the file classes and
+/// git states spread across them. Ages are each bucket's lower bound, so
+/// they follow the configured thresholds.
+pub fn sample_rows(session: &Session) -> Vec<EntryRow> {
+    let limits = &session.recency;
+    let ago = |secs: u64| session.now.checked_sub(Duration::from_secs(secs));
+    let ahead = |secs: u64| session.now.checked_add(Duration::from_secs(secs));
+    let samples = [
+        ("src", Class::Dir, Some(session.now), Git::Subtree, "just now · directory · git changes beneath"),
+        ("main.rs", Class::File, ago(limits.just_now), Git::Changed, "seconds · file · git +/-"),
+        ("build.sh", Class::Exec, ago(limits.seconds), Git::Untracked, "minutes · executable · git untracked"),
+        (".env", Class::Dotfile, ago(limits.minutes), Git::Dirty, "hours · dotfile · git dirty"),
+        ("README.md", Class::Doc, ago(limits.hours), Git::None, "days · docs and config"),
+        ("draft.txt~", Class::Junk, ago(limits.days), Git::None, "weeks · backup/junk"),
+        ("sudo-helper", Class::Security, ago(limits.weeks), Git::None, "months · security flag"),
+        ("latest", Class::BrokenLink, ago(limits.months), Git::None, "years · broken symlink"),
+        ("copy.bin", Class::HardLink, ahead(3_600), Git::None, "future · hard link"),
+        ("lost+found", Class::Dir, None, Git::None, "unknown time"),
+    ];
+    samples
+        .into_iter()
+        .map(|(name, class, modified, git, note)| sample_row(name, class, modified, git, note))
+        .collect()
+}
+
+/// The one role a listing row cannot show: clean git state, which is also
+/// the color of footers and placeholders.
+pub fn caption() -> String {
+    palette::paint("(clean) git state, footers, and placeholders", palette::GIT_CLEAN)
+}
+
+fn sample_row(name: &str, class: Class, modified: Option<SystemTime>, git: Git, note: &str) -> EntryRow {
+    let is_dir = matches!(class, Class::Dir);
+    EntryRow {
+        name_plain: name.to_string(),
+        entry_type: if is_dir { EntryType::Dir } else { EntryType::File },
+        is_executable: matches!(class, Class::Exec | Class::Security),
+        broken_link: matches!(class, Class::BrokenLink),
+        size_bytes: if is_dir { 4_096 } else { 1_536 },
+        modified_time: modified,
+        git: match git {
+            Git::None => None,
+            Git::Untracked => Some(status(None, None, false, true)),
+            Git::Subtree => Some(GitStatus {
+                files: Some(7),
+                ..status(Some(120), Some(30), true, false)
+            }),
+            Git::Changed => Some(status(Some(12), Some(3), true, false)),
+            Git::Dirty => Some(status(None, None, true, false)),
+        },
+        security: SecurityFlags {
+            setuid: matches!(class, Class::Security),
+            ..SecurityFlags::default()
+        },
+        hard_link: matches!(class, Class::HardLink).then_some(HardLink {
+            device: 0,
+            inode: 0,
+            links: 2,
+        }),
+        link_tag: matches!(class, Class::HardLink).then(|| "[hardlink #1 · 2 links]".to_string()),
+        annotation: Some(Annotation::Text(note.to_string())),
+        ..EntryRow::default()
+    }
+}
+
+fn status(added: Option<u64>, deleted: Option<u64>, dirty: bool, untracked: bool) -> GitStatus {
+    GitStatus {
+        added,
+        deleted,
+        dirty,
+        untracked,
+        ..GitStatus::default()
+    }
+}
@@ -0,0 +1,44 @@
+//! `--trash N`: moves entries picked by their index-column number to the
+//! desktop trash instead of deleting them, so junk spotted in a listing can
+//! be cleared and still recovered from the file manager.
+
+use crate::{EntryRow, diag};
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+/// Trashes the rows numbered `picks` (the numbers the same listing shows)
+/// and drops them from `rows`. Every number is checked before anything is
+/// moved; a move that fails stops there, and the entries trashed before it
+/// stay trashed.
+pub fn trash_picked(
+    rows: &mut Vec<EntryRow>,
+    picks: &[usize],
+    path_of: impl Fn(&EntryRow) -> PathBuf,
+) -> Result<(), String> {
+    for &pick in picks {
+        let row = rows.get(pick).ok_or_else(|| match rows.len() {
+            0 => format!("--trash: no entry {pick}; the listing is empty"),
+            len => format!("--trash: no entry {pick}; the listing is numbered 0 to {}", len - 1),
+        })?;
+        if row.name_plain == "." || row.name_plain == ".." {
+            return Err(format!("--trash: refusing to trash {}", row.name_plain));
+        }
+    }
+    let picked: HashSet<usize> = picks.iter().copied().collect();
+    let mut trashed = HashSet::new();
+    let mut result = Ok(());
+    for (idx, row) in rows.iter().enumerate().filter(|(idx, _)| picked.contains(idx)) {
+        if let Err(err) = ::trash::delete(path_of(row)) {
+            result = Err(format!("cannot move {} to the trash: {err}", row.name_plain));
+            break;
+        }
+        diag::note(&format!("moved {} to the trash", row.name_plain));
+        trashed.insert(idx);
+    }
+    let mut idx = 0;
+    rows.retain(|_| {
+        idx += 1;
+        !trashed.contains(&(idx - 1))
+    });
+    result
+}
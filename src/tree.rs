@@ -0,0 +1,82 @@
+//! `nuls tree`: the directory and its subdirectories as an indented tree,
+//! with the listing's colors and sizes.
+
+use crate::{
+    CollectOptions, Session, SizeStyle, SortKey, TieBreak, TreeArgs, collect_entries, interrupt, palette, progress,
+    sort_rows, width,
+};
+use crate::i18n::Strings;
+use indicatif::ProgressBar;
+use std::path::Path;
+
+#[derive(Debug, Default, PartialEq, Eq)]
+struct TreeCounts {
+    dirs: usize,
+    files: usize,
+}
+
+/// State threaded through the recursive walk.
+struct Walk<'a> {
+    options: CollectOptions<'static>,
+    strings: &'a Strings,
+    max_depth: Option<usize>,
+    out: String,
+    counts: TreeCounts,
+    progress: ProgressBar,
+}
+
+pub fn render_tree(args: &TreeArgs, session: &Session) -> Result<String, String> {
+    let mut walk = Walk {
+        options: CollectOptions::plain(args.include_hidden),
+        max_depth: args.level,
+        out: format!("{}\n", palette::paint(args.path.display().to_string(), palette::DIR)),
+        counts: TreeCounts::default(),
+        progress: progress::spinner("scanning"),
+        strings: session.strings,
+    };
+    let result = walk.visit(&args.path, "", 1);
+    walk.progress.finish_and_clear();
+    result?;
+
+    let Walk { mut out, counts, .. } = walk;
+    let dir_label = if counts.dirs == 1 { "directory" } else { "directories" };
+    let file_label = if counts.files == 1 { "file" } else { "files" };
+    out.push_str(&format!(
+        "\n{}\n",
+        palette::paint(
+            format!("{} {dir_label}, {} {file_label}", counts.dirs, counts.files),
+            palette::GIT_CLEAN
+        )
+    ));
+    Ok(out)
+}
+
+impl Walk<'_> {
+    /// Appends the children of `dir` at `depth`, directories first. Symlinked
+    /// directories are shown but not followed, so cycles cannot occur.
+    fn visit(&mut self, dir: &Path, prefix: &str, depth: usize) -> Result<(), String> {
+        self.progress.set_message(dir.display().to_string());
+        let mut rows = collect_entries(dir, &self.options, None)?;
+        self.progress.inc(rows.len() as u64);
+        sort_rows(&mut rows, SortKey::Name, false, TieBreak::Name);
+        for (idx, row) in rows.iter().enumerate() {
+            let last = idx + 1 == rows.len();
+            let branch = if last { "└── " } else { "├── " };
+            self.out.push_str(&palette::paint(format!("{prefix}{branch}"), palette::BORDER));
+            let (name_plain, name_colored) = row.name_cell();
+            self.out.push_str(&width::isolate_bidi(&name_plain, &name_colored));
+            if row.is_dir() {
+                self.counts.dirs += 1;
+                self.out.push('\n');
+                if self.max_depth.is_none_or(|max| depth < max) && !interrupt::requested() {
+                    let child_prefix = format!("{prefix}{}", if last { "    " } else { "│   " });
+                    self.visit(&dir.join(&row.name_plain), &child_prefix, depth + 1)?;
+                }
+            } else {
+                self.counts.files += 1;
+                self.out.push_str(&format!("  {}\n", row.size_cell(self.strings, SizeStyle::default()).1));
+            }
+        }
+        Ok(())
+    }
+}
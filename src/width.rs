@@ -0,0 +1,145 @@
+//! How many terminal cells text takes, and how to cut it to fit. Every
+//! renderer that pads, wraps, or truncates names (the table, `--ext-summary`,
+//! `stat`, `-m`, `tree`, and the bookmark list) measures through here, so
+//! wide CJK characters, emoji, and ZWJ sequences keep the borders in line.
+
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+/// Column width of `text` in terminal cells: two for wide and emoji
+/// characters (a whole ZWJ or modifier sequence counts as one emoji), none
+/// for combining marks, variation selectors, and bidi controls.
+pub fn display_width(text: &str) -> usize {
+    text.width()
+}
+
+/// Cuts a cell to `width` cells ending in `…`. Escape sequences in the
+/// colored text are all kept, so the part left keeps its colors and any
+/// color opened before the cut is still reset. The cut never splits a
+/// character from the marks or joined emoji that follow it.
+pub fn truncate(plain: &str, colored: &str, width: usize) -> (String, String) {
+    if display_width(plain) <= width {
+        return (plain.to_string(), colored.to_string());
+    }
+    let keep = fitting_chars(plain, width.saturating_sub(1));
+    let short: String = plain.chars().take(keep).chain(std::iter::once('…')).collect();
+    let mut out = String::new();
+    let mut visible = 0;
+    let mut chars = colored.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            out.push(c);
+            for c in chars.by_ref() {
+                out.push(c);
+                if c == 'm' {
+                    break;
+                }
+            }
+        } else {
+            if visible < keep {
+                out.push(c);
+            } else if visible == keep {
+                out.push('…');
+            }
+            visible += 1;
+        }
+    }
+    (short, out)
+}
+
+/// How many leading chars of `text` fit in `width` cells, cutting only
+/// where a new character starts: never before a zero-width mark or selector,
+/// nor right after a zero-width joiner.
+fn fitting_chars(text: &str, width: usize) -> usize {
+    let mut fits = 0;
+    let mut previous = None;
+    for (count, (offset, c)) in text.char_indices().enumerate() {
+        let boundary = c.width().unwrap_or(0) > 0 && previous != Some('\u{200D}');
+        if boundary {
+            if display_width(&text[..offset]) > width {
+                return fits;
+            }
+            fits = count;
+        }
+        previous = Some(c);
+    }
+    if display_width(text) <= width { text.chars().count() } else { fits }
+}
+
+/// Invisible marks that steer bidi layout: LRM/RLM, ALM, the embeddings and
+/// overrides, and the isolates.
+fn is_bidi_control(c: char) -> bool {
+    matches!(c, '\u{200E}' | '\u{200F}' | '\u{061C}' | '\u{202A}'..='\u{202E}' | '\u{2066}'..='\u{2069}')
+}
+
+/// Letters from right-to-left scripts (Hebrew, Arabic, Syriac, Thaana, N'Ko,
+/// and their supplements and presentation forms).
+fn is_rtl(c: char) -> bool {
+    matches!(
+        c,
+        '\u{0590}'..='\u{08FF}'
+            | '\u{FB1D}'..='\u{FDFF}'
+            | '\u{FE70}'..='\u{FEFF}'
+            | '\u{10800}'..='\u{10FFF}'
+            | '\u{1E800}'..='\u{1EFFF}'
+    )
+}
+
+/// Whether `plain` holds right-to-left text or bidi controls; see `isolate_bidi`.
+pub fn needs_isolation(plain: &str) -> bool {
+    plain.chars().any(|c| is_rtl(c) || is_bidi_control(c))
+}
+
+/// Wraps a cell holding right-to-left text or bidi controls in FSI…PDI so
+/// the terminal lays it out on its own; otherwise an Arabic or Hebrew name
+/// can pull the borders and neighboring cells into its reordering, and an
+/// override left open in a name would flip the rest of the line.
+pub fn isolate_bidi(plain: &str, colored: &str) -> String {
+    if needs_isolation(plain) {
+        format!("\u{2068}{colored}\u{2069}")
+    } else {
+        colored.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wide_and_emoji_characters_take_two_cells() {
+        assert_eq!(display_width("notes.txt"), 9);
+        assert_eq!(display_width("日本語.txt"), 10);
+        assert_eq!(display_width("🦀.rs"), 5);
+        assert_eq!(display_width("👩\u{200D}💻 work"), 7);
+        assert_eq!(display_width("👍\u{1F3FD}"), 2);
+        assert_eq!(display_width("☺\u{FE0F}"), 2);
+        assert_eq!(display_width("cafe\u{301}"), 4);
+    }
+
+    #[test]
+    fn truncation_counts_cells_and_keeps_sequences_whole() {
+        assert_eq!(truncate("日本語.txt", "日本語.txt", 6).0, "日本…");
+        assert_eq!(truncate("日本語.txt", "日本語.txt", 5).0, "日本…");
+        assert_eq!(truncate("👩\u{200D}💻 work", "x", 4).0, "👩\u{200D}💻 …");
+        assert_eq!(truncate("👩\u{200D}💻 work", "x", 2).0, "…");
+        assert_eq!(truncate("cafe\u{301}s.txt", "x", 6).0, "cafe\u{301}s…");
+    }
+
+    #[test]
+    fn truncated_cells_keep_their_colors() {
+        let colored = "\x1b[34mreadme\x1b[0m \x1b[32m(+1)\x1b[0m";
+        let (plain, cut) = truncate("readme (+1)", colored, 8);
+        assert_eq!(plain, "readme …");
+        assert_eq!(cut, "\x1b[34mreadme\x1b[0m \x1b[32m…\x1b[0m");
+        assert_eq!(truncate("short", "short", 8), ("short".to_string(), "short".to_string()));
+    }
+
+    #[test]
+    fn rtl_and_bidi_controls_are_isolated() {
+        assert_eq!(isolate_bidi("notes.txt", "notes.txt"), "notes.txt");
+        assert_eq!(isolate_bidi("שלום.txt", "שלום.txt"), "\u{2068}שלום.txt\u{2069}");
+        assert_eq!(isolate_bidi("evil\u{202E}txt.exe", "x"), "\u{2068}x\u{2069}");
+        assert_eq!(display_width("evil\u{202E}txt.exe"), 11);
+        assert_eq!(display_width("\u{2068}مرحبا\u{2069}"), 5);
+    }
+}
@@ -0,0 +1,1292 @@
+//! Golden-output tests: run the real binary against tempdir fixtures and
+//! snapshot the plain (`--color never`) table.
+
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::{Duration, SystemTime};
+
+use tempfile::TempDir;
+
+/// 2020-01-01T00:00:00Z; fixture mtimes are whole days after this.
+const BASE_MTIME: u64 = 1_577_836_800;
+/// The `--now` passed to every run: one week after `BASE_MTIME`.
+const NOW: u64 = BASE_MTIME + 7 * 86_400;
+
+fn set_mtime(path: &Path, days_after_base: u64) {
+    let time = SystemTime::UNIX_EPOCH + Duration::from_secs(BASE_MTIME + days_after_base * 86_400);
+    File::open(path)
+        .and_then(|file| file.set_modified(time))
+        .unwrap_or_else(|err| panic!("cannot set mtime on {}: {err}", path.display()));
+}
+
+fn write_file(dir: &Path, name: &str, size: usize, days_after_base: u64) {
+    let path = dir.join(name);
+    fs::write(&path, vec![b'x'; size]).expect("write fixture");
+    set_mtime(&path, days_after_base);
+}
+
+#[cfg(unix)]
+fn make_executable(path: &Path) {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o755)).expect("chmod fixture");
+}
+
+#[cfg(not(unix))]
+fn make_executable(_path: &Path) {}
+
+/// A small directory with mixed files, dirs, an executable, and a dotfile.
+fn fixture() -> TempDir {
+    let tmp = TempDir::new().expect("tempdir");
+    let root = tmp.path();
+
+    fs::create_dir(root.join("src")).expect("mkdir");
+    write_file(&root.join("src"), "lib.rs", 10, 1);
+    fs::create_dir(root.join("docs")).expect("mkdir");
+
+    write_file(root, "alpha.txt", 1536, 3);
+    write_file(root, "Beta.md", 12, 5);
+    write_file(root, "run.sh", 40, 2);
+    make_executable(&root.join("run.sh"));
+    write_file(root, ".hidden", 3, 4);
+
+    // Directory mtimes last, since creating children bumps them.
+    set_mtime(&root.join("src"), 6);
+    set_mtime(&root.join("docs"), 0);
+    tmp
+}
+
+/// The binary with plain output and no user config or environment defaults.
+fn nuls_command(dir: &Path) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_nuls"));
+    command
+        .args(["--color", "never", "--lang", "en"])
+        .env_remove("NULS_CONFIG")
+        .env_remove("NULS_NOW")
+        .env_remove("NULS_BOOKMARKS")
+        .env_remove("NULS_SIZES")
+        .env_remove("NULS_DEFAULT_ARGS")
+        .env_remove("COLUMNS")
+        .env("XDG_STATE_HOME", dir.join(".no-state"))
+        .env("TZ", "UTC")
+        .env("XDG_CONFIG_HOME", dir.join(".no-config"))
+        .current_dir(dir);
+    command
+}
+
+fn nuls(dir: &Path, args: &[&str]) -> String {
+    let output = nuls_command(dir)
+        .args(["--now", &NOW.to_string()])
+        .args(args)
+        .output()
+        .expect("run nuls");
+    assert!(
+        output.status.success(),
+        "nuls failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    normalize(&String::from_utf8(output.stdout).expect("utf-8 output"))
+}
+
+/// Masks directory sizes, which vary by filesystem.
+fn normalize(output: &str) -> String {
+    output
+        .lines()
+        .map(|line| {
+            let mut cells: Vec<String> = line.split('│').map(str::to_string).collect();
+            if cells.len() >= 6 && cells[3].trim() == "dir" {
+                let width = cells[4].chars().count();
+                cells[4] = format!("{:>width$}", "- ", width = width);
+            }
+            cells.join("│")
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[test]
+fn default_listing_puts_dirs_first() {
+    let tmp = fixture();
+    insta::assert_snapshot!(nuls(tmp.path(), &[]));
+}
+
+#[cfg(unix)]
+#[test]
+#[cfg_attr(not(feature = "git"), ignore = "needs the git feature")]
+fn ascii_status_spells_out_what_colors_mean() {
+    let tmp = fixture();
+    let root = tmp.path();
+    git(root, &["init", "-q"]);
+    git(root, &["add", "."]);
+    git(root, &["commit", "-q", "-m", "fixture"]);
+    fs::write(root.join("Beta.md"), "edited\n").expect("edit");
+    fs::write(root.join("new.txt"), "").expect("write");
+    set_mtime(&root.join("Beta.md"), 5);
+    set_mtime(&root.join("new.txt"), 5);
+    insta::assert_snapshot!(nuls(root, &["--ascii-status"]));
+}
+
+#[test]
+fn rtl_names_are_isolated_from_neighboring_cells() {
+    let tmp = fixture();
+    fs::write(tmp.path().join("שלום.txt"), "").expect("write");
+    let table = nuls(tmp.path(), &[]);
+    assert!(table.contains("│ \u{2068}שלום.txt\u{2069}  "), "{table}");
+    let width = |line: &str| line.chars().filter(|c| !matches!(c, '\u{2068}' | '\u{2069}')).count();
+    let widths: Vec<usize> = table.lines().map(width).collect();
+    assert!(widths.windows(2).all(|pair| pair[0] == pair[1]), "{table}");
+}
+
+/// Names whose cell widths differ from their char counts: wide CJK, an
+/// emoji, a ZWJ sequence, a skin-tone modifier, and a combining accent.
+const WIDE_NAMES: [&str; 6] = [
+    "日本語.txt",
+    "🦀.rs",
+    "👩\u{200D}💻 notes",
+    "👍\u{1F3FD}.md",
+    "cafe\u{301}.md",
+    "notes.日本",
+];
+
+fn wide_fixture() -> TempDir {
+    let tmp = fixture();
+    for (days, name) in WIDE_NAMES.iter().enumerate() {
+        write_file(tmp.path(), name, 100 * (days + 1), days as u64);
+    }
+    tmp
+}
+
+/// Every bordered line of each table in `output` is as many cells wide as
+/// the table's top border.
+fn assert_borders_line_up(output: &str) {
+    let mut top = None;
+    for line in output.lines().filter(|line| line.starts_with(['┌', '│', '├', '└'])) {
+        let width = unicode_width::UnicodeWidthStr::width(line);
+        if line.starts_with('┌') {
+            top = Some(width);
+        }
+        assert_eq!(Some(width), top, "{output}");
+    }
+    assert!(top.is_some(), "{output}");
+}
+
+#[test]
+fn wide_names_keep_the_table_aligned() {
+    let tmp = wide_fixture();
+    let table = nuls(tmp.path(), &[]);
+    assert_borders_line_up(&table);
+    insta::assert_snapshot!(table);
+}
+
+#[test]
+fn wide_names_keep_every_layout_aligned() {
+    let tmp = wide_fixture();
+    let root = tmp.path();
+    let layouts: [&[&str]; 4] = [
+        &["--ext-summary"],
+        &["-l", "-g"],
+        &["--group-by", "modified-day"],
+        &["stat", "👩\u{200D}💻 notes"],
+    ];
+    for args in layouts {
+        assert_borders_line_up(&nuls(root, args));
+    }
+    for columns in ["60", "45", "38"] {
+        let output = nuls_command(root)
+            .args(["--now", &NOW.to_string()])
+            .env("COLUMNS", columns)
+            .output()
+            .expect("run nuls");
+        let table = String::from_utf8(output.stdout).expect("utf-8 output");
+        assert_borders_line_up(&table);
+        let width = unicode_width::UnicodeWidthStr::width(table.lines().next().unwrap_or_default());
+        assert!(width <= columns.parse().expect("number"), "{table}");
+    }
+    let output = nuls_command(root).arg("-m").env("COLUMNS", "24").output().expect("run nuls");
+    let commas = String::from_utf8(output.stdout).expect("utf-8 output");
+    assert!(commas.lines().all(|line| unicode_width::UnicodeWidthStr::width(line) <= 24), "{commas}");
+    let tree = nuls(root, &["tree"]);
+    assert!(WIDE_NAMES.iter().all(|name| tree.contains(&format!("── {name}  "))), "{tree}");
+}
+
+#[test]
+fn column_alignment_comes_from_config() {
+    let tmp = fixture();
+    let config = tmp.path().join(".no-config/align.toml");
+    fs::create_dir_all(config.parent().expect("parent")).expect("config dir");
+    fs::write(&config, "[table.align]\nname = \"right\"\ntype = \"center\"\nsize = \"left\"\n").expect("write");
+    let output = nuls_command(tmp.path())
+        .env("NULS_CONFIG", &config)
+        .args(["--now", &NOW.to_string()])
+        .output()
+        .expect("run nuls");
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    insta::assert_snapshot!(normalize(&String::from_utf8(output.stdout).expect("utf-8 output")));
+}
+
+#[test]
+fn config_aliases_expand_to_their_flags() {
+    let tmp = fixture();
+    let config = tmp.path().join(".no-config/alias.toml");
+    fs::create_dir_all(config.parent().expect("parent")).expect("config dir");
+    fs::write(&config, "[alias]\nrecent = \"-a --sort modified\"\n").expect("write");
+    let run = |args: &[&str]| {
+        nuls_command(tmp.path())
+            .env("NULS_CONFIG", &config)
+            .args(["--now", &NOW.to_string()])
+            .args(args)
+            .output()
+            .expect("run nuls")
+    };
+    let aliased = run(&["+recent", "-1"]);
+    assert!(aliased.status.success(), "{}", String::from_utf8_lossy(&aliased.stderr));
+    assert_eq!(aliased.stdout, run(&["-a", "--sort", "modified", "-1"]).stdout);
+    let unknown = run(&["+missing"]);
+    assert!(!unknown.status.success());
+    assert!(String::from_utf8_lossy(&unknown.stderr).contains("unknown alias '+missing'"));
+}
+
+#[test]
+fn directory_defaults_apply_beneath_configured_paths() {
+    let tmp = fixture();
+    let config = tmp.path().join(".no-config/directories.toml");
+    fs::create_dir_all(config.parent().expect("parent")).expect("config dir");
+    let root = tmp.path().canonicalize().expect("canonicalize");
+    let text = format!("[directories]\n{:?} = \"-a --sort modified\"\n", root.to_string_lossy());
+    fs::write(&config, text).expect("write");
+    let run = |config: Option<&Path>, args: &[&str]| {
+        let mut command = nuls_command(tmp.path());
+        if let Some(config) = config {
+            command.env("NULS_CONFIG", config);
+        }
+        command.args(["--now", &NOW.to_string()]).args(args).output().expect("run nuls")
+    };
+    let defaulted = run(Some(&config), &["-1"]);
+    assert!(defaulted.status.success(), "{}", String::from_utf8_lossy(&defaulted.stderr));
+    assert_eq!(defaulted.stdout, run(None, &["-a", "--sort", "modified", "-1"]).stdout);
+    assert_eq!(run(Some(&config), &["list", "-1"]).stdout, defaulted.stdout);
+    let overridden = run(Some(&config), &["-1", "--sort", "name"]);
+    assert_eq!(overridden.stdout, run(None, &["-a", "--sort", "name", "-1"]).stdout);
+    assert_ne!(overridden.stdout, defaulted.stdout);
+}
+
+#[test]
+fn default_args_env_goes_before_the_real_args() {
+    let tmp = fixture();
+    let run = |defaults: Option<&str>, args: &[&str]| {
+        let mut command = nuls_command(tmp.path());
+        if let Some(defaults) = defaults {
+            command.env("NULS_DEFAULT_ARGS", defaults);
+        }
+        command.args(["--now", &NOW.to_string()]).args(args).output().expect("run nuls")
+    };
+    let defaulted = run(Some("-a --sort modified"), &["-1"]);
+    assert!(defaulted.status.success(), "{}", String::from_utf8_lossy(&defaulted.stderr));
+    assert_eq!(defaulted.stdout, run(None, &["-a", "--sort", "modified", "-1"]).stdout);
+    assert_eq!(run(Some("-a --sort modified"), &["list", "-1"]).stdout, defaulted.stdout);
+    let overridden = run(Some("-a --sort modified"), &["-1", "--sort", "name"]);
+    assert_eq!(overridden.stdout, run(None, &["-a", "--sort", "name", "-1"]).stdout);
+    let broken = run(Some("--annotate 'wc"), &[]);
+    assert!(!broken.status.success());
+    assert!(String::from_utf8_lossy(&broken.stderr).contains("NULS_DEFAULT_ARGS"));
+}
+
+#[test]
+fn narrow_terminals_shrink_columns_in_priority_order() {
+    let tmp = fixture();
+    let listing = |columns: &str, config: Option<&str>| {
+        let mut command = nuls_command(tmp.path());
+        if let Some(text) = config {
+            let path = tmp.path().join(".no-config/shrink.toml");
+            fs::create_dir_all(path.parent().expect("parent")).expect("config dir");
+            fs::write(&path, text).expect("write config");
+            command.env("NULS_CONFIG", path);
+        }
+        let output = command
+            .env("COLUMNS", columns)
+            .args(["--now", &NOW.to_string()])
+            .output()
+            .expect("run nuls");
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        normalize(&String::from_utf8(output.stdout).expect("utf-8 output"))
+    };
+    let fits = |table: &str, width: usize| table.lines().all(|line| line.chars().count() <= width);
+    let no_type = listing("45", None);
+    assert!(fits(&no_type, 45) && !no_type.contains("│ type ") && no_type.contains(" days ago "), "{no_type}");
+    let compact = listing("38", None);
+    assert!(fits(&compact, 38) && compact.contains("│ 4d "), "{compact}");
+    let cut = listing("30", None);
+    assert!(cut.contains("│ alpha.t… │") && cut.contains("│ Beta.md  │"), "names stop at 8 cells: {cut}");
+    let sized = listing("45", Some("[table]\nshrink = [\"size\", \"name\"]\n"));
+    assert!(fits(&sized, 45) && sized.contains("│ type ") && !sized.contains("│ size "), "{sized}");
+}
+
+#[test]
+fn hidden_files_need_all_flag() {
+    let tmp = fixture();
+    let listing = nuls(tmp.path(), &[]);
+    assert!(!listing.contains(".hidden"));
+    insta::assert_snapshot!(nuls(tmp.path(), &["-a"]));
+}
+
+#[test]
+fn sort_modified_newest_first() {
+    let tmp = fixture();
+    insta::assert_snapshot!(nuls(tmp.path(), &["-t"]));
+}
+
+#[test]
+fn sort_modified_reversed() {
+    let tmp = fixture();
+    insta::assert_snapshot!(nuls(tmp.path(), &["-tr"]));
+}
+
+#[test]
+fn hybrid_time_style() {
+    let tmp = fixture();
+    insta::assert_snapshot!(nuls(tmp.path(), &["-t", "--time-style", "hybrid"]));
+}
+
+#[test]
+fn now_env_var_matches_flag() {
+    let tmp = fixture();
+    let output = nuls_command(tmp.path())
+        .env("NULS_NOW", NOW.to_string())
+        .output()
+        .expect("run nuls");
+    let from_env = normalize(&String::from_utf8(output.stdout).expect("utf-8 output"));
+    assert_eq!(from_env, nuls(tmp.path(), &[]));
+}
+
+#[test]
+fn path_argument_lists_subdirectory() {
+    let tmp = fixture();
+    insta::assert_snapshot!(nuls(tmp.path(), &["src"]));
+}
+
+fn git(dir: &Path, args: &[&str]) {
+    let status = Command::new("git")
+        .args([
+            "-c",
+            "user.name=nuls",
+            "-c",
+            "user.email=nuls@example.com",
+            "-c",
+            "commit.gpgsign=false",
+            "-c",
+            "init.defaultBranch=main",
+        ])
+        .args(args)
+        .current_dir(dir)
+        .env_remove("GIT_DIR")
+        .env_remove("GIT_WORK_TREE")
+        .status()
+        .expect("run git");
+    assert!(status.success(), "git {args:?} failed");
+}
+
+#[test]
+#[cfg_attr(not(feature = "git"), ignore = "needs the git feature")]
+fn git_annotations() {
+    let tmp = fixture();
+    let root = tmp.path();
+    git(root, &["init", "-q"]);
+    git(root, &["add", "."]);
+    git(root, &["commit", "-q", "-m", "fixture"]);
+
+    fs::write(root.join("Beta.md"), "line one\nline two\n").expect("edit");
+    set_mtime(&root.join("Beta.md"), 5);
+    write_file(&root.join("src"), "new.rs", 5, 1);
+    set_mtime(&root.join("src"), 6);
+
+    insta::assert_snapshot!(nuls(root, &["-g"]));
+    assert!(nuls(root, &["-g", "-d", "src"]).contains("│ src (+? · 1 file) │"));
+}
+
+#[cfg(unix)]
+#[test]
+fn dereference_uses_symlink_target_metadata() {
+    use std::os::unix::fs::symlink;
+
+    let tmp = fixture();
+    let root = tmp.path();
+    symlink(root.join("alpha.txt"), root.join("link-to-alpha")).expect("symlink");
+    symlink(root.join("missing"), root.join("dangling")).expect("symlink");
+
+    let listing = nuls(root, &["-L"]);
+    let row = |name: &str| {
+        listing
+            .lines()
+            .find(|line| line.contains(name))
+            .unwrap_or_else(|| panic!("no row for {name}"))
+            .to_string()
+    };
+    assert!(row("link-to-alpha").contains("1.5 KB"));
+    assert!(row("link-to-alpha").contains("4 days ago"));
+    assert!(row("dangling").contains("broken"));
+}
+
+#[test]
+fn tilde_contracts_full_paths_under_home() {
+    let tmp = fixture();
+    let home = tmp.path().canonicalize().expect("canonical tempdir");
+    let output = nuls_command(tmp.path())
+        .args(["--now", &NOW.to_string(), "--full-path", "--tilde", "src"])
+        .env("HOME", &home)
+        .output()
+        .expect("run nuls");
+    let listing = String::from_utf8(output.stdout).expect("utf-8 output");
+    assert!(listing.contains("│ ~/src/lib.rs │"), "{listing}");
+
+    let full = nuls(tmp.path(), &["--full-path", "src"]);
+    assert!(full.contains(&home.join("src").join("lib.rs").display().to_string()));
+}
+
+#[test]
+fn oneline_prints_bare_names() {
+    let tmp = fixture();
+    insta::assert_snapshot!(nuls(tmp.path(), &["-1"]));
+}
+
+#[test]
+fn commas_stream_wraps_to_columns() {
+    let tmp = fixture();
+    let output = nuls_command(tmp.path())
+        .arg("-m")
+        .env("COLUMNS", "20")
+        .output()
+        .expect("run nuls");
+    insta::assert_snapshot!(String::from_utf8(output.stdout).expect("utf-8 output"));
+}
+
+#[test]
+fn directory_flag_lists_the_entry_itself() {
+    let tmp = fixture();
+    insta::assert_snapshot!(nuls(tmp.path(), &["-d", "src"]));
+}
+
+#[test]
+fn stat_card_describes_one_entry() {
+    let tmp = fixture();
+    let card = nuls(tmp.path(), &["stat", "alpha.txt", "--hash"]);
+    let value = |label: &str| {
+        card.lines()
+            .find_map(|line| {
+                let cells: Vec<&str> = line.split('│').map(str::trim).collect();
+                (cells.len() == 4 && cells[1] == label).then(|| cells[2].to_string())
+            })
+            .unwrap_or_else(|| panic!("no {label} line in:\n{card}"))
+    };
+    assert_eq!(value("type"), "file");
+    assert_eq!(value("size"), "1.5 KB (1536 bytes)");
+    assert_eq!(value("modified"), "2020-01-04 00:00:00 +0000 (4 days ago)");
+    assert_eq!(
+        value("sha256"),
+        "4685348c9decbdf928fb1f37fe3e57c84f812ad5b2ba0e9032bed4337a86a503"
+    );
+    #[cfg(unix)]
+    assert!(value("permissions").starts_with("-rw"), "{card}");
+}
+
+#[test]
+fn tree_nests_subdirectories() {
+    let tmp = fixture();
+    insta::assert_snapshot!(nuls(tmp.path(), &["tree", "-a"]));
+}
+
+#[test]
+fn diff_marks_added_removed_and_changed() {
+    let tmp = TempDir::new().expect("tempdir");
+    let root = tmp.path();
+    for side in ["old", "new"] {
+        fs::create_dir(root.join(side)).expect("mkdir");
+        write_file(&root.join(side), "same.txt", 5, 1);
+    }
+    write_file(&root.join("old"), "removed.txt", 7, 1);
+    write_file(&root.join("old"), "edited.txt", 10, 1);
+    write_file(&root.join("new"), "edited.txt", 2048, 2);
+    write_file(&root.join("new"), "added.txt", 3, 2);
+    insta::assert_snapshot!(nuls(root, &["diff", "old", "new"]));
+    assert_eq!(nuls(root, &["diff", "old", "old"]), "no differences");
+}
+
+#[cfg(unix)]
+#[test]
+fn permission_filters_narrow_the_listing() {
+    use std::os::unix::fs::MetadataExt;
+    let tmp = fixture();
+    assert_eq!(nuls(tmp.path(), &["-1", "--executable-only"]), "run.sh");
+
+    let uid = fs::metadata(tmp.path()).expect("metadata").uid().to_string();
+    assert_eq!(
+        nuls(tmp.path(), &["-1", "--owned-by", &uid, "--readable"]),
+        nuls(tmp.path(), &["-1"])
+    );
+
+    let output = nuls_command(tmp.path())
+        .args(["--owned-by", "no-such-user-here"])
+        .output()
+        .expect("run nuls");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("unknown user"));
+}
+
+#[cfg(all(target_os = "linux", not(feature = "xattr")))]
+#[test]
+fn security_without_xattr_warns_that_capabilities_are_left_out() {
+    let tmp = fixture();
+    let output = nuls_command(tmp.path()).args(["-1", "--security"]).output().expect("run nuls");
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("built without xattr support (the `xattr` feature)"), "{stderr}");
+    let plain = nuls_command(tmp.path()).arg("-1").output().expect("run nuls");
+    assert!(plain.stderr.is_empty(), "{}", String::from_utf8_lossy(&plain.stderr));
+}
+
+#[test]
+fn show_self_prepends_dot_rows() {
+    let tmp = fixture();
+    set_mtime(tmp.path(), 2);
+    insta::assert_snapshot!(nuls(tmp.path(), &["--show-self", "-t", "src"]));
+}
+
+#[cfg(unix)]
+#[test]
+fn annotate_adds_a_note_column() {
+    let tmp = fixture();
+    insta::assert_snapshot!(nuls(tmp.path(), &["--annotate", "test -d {} && echo dir || wc -c < {}"]));
+}
+
+#[test]
+fn peek_archives_lists_members_under_the_archive() {
+    let tmp = fixture();
+    let mut builder = tar::Builder::new(File::create(tmp.path().join("bundle.tar")).expect("create tar"));
+    for (name, body, days) in [("notes/todo.txt", &b"ship it"[..], 1), ("logo.svg", &b"<svg/>"[..], 2)] {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(body.len() as u64);
+        header.set_mtime(BASE_MTIME + days * 86_400);
+        header.set_mode(0o644);
+        builder.append_data(&mut header, name, body).expect("append member");
+    }
+    builder.finish().expect("finish tar");
+    drop(builder);
+    set_mtime(&tmp.path().join("bundle.tar"), 4);
+    insta::assert_snapshot!(nuls(tmp.path(), &["--peek-archives"]));
+}
+
+#[test]
+fn bookmarks_resolve_at_names() {
+    let tmp = fixture();
+    let state = TempDir::new().expect("tempdir");
+    let store = state.path().join("bookmarks.toml");
+    let run = |args: &[&str]| {
+        let output = nuls_command(tmp.path())
+            .env("NULS_BOOKMARKS", &store)
+            .args(["--now", &NOW.to_string()])
+            .args(args)
+            .output()
+            .expect("run nuls");
+        (output.status.success(), String::from_utf8_lossy(&output.stdout).into_owned())
+    };
+
+    assert!(run(&["bookmark", "add", "code", "src"]).0);
+    let (ok, listing) = run(&["-1", "@code"]);
+    assert!(ok);
+    assert_eq!(listing, "lib.rs\n");
+    let (_, list) = run(&["bookmark", "list"]);
+    assert!(list.starts_with("@code  ") && list.trim_end().ends_with("src"), "{list}");
+    assert!(!run(&["@missing"]).0);
+    assert!(run(&["bookmark", "remove", "code"]).0);
+    assert!(!run(&["@code"]).0);
+}
+
+#[test]
+fn recent_walks_the_tree_and_skips_vcs_dirs() {
+    let tmp = fixture();
+    fs::create_dir(tmp.path().join(".git")).expect("mkdir");
+    write_file(&tmp.path().join(".git"), "HEAD", 20, 6);
+    insta::assert_snapshot!(nuls(tmp.path(), &["-a", "--recent", "5"]));
+}
+
+#[test]
+fn min_entries_keeps_only_fuller_directories() {
+    let tmp = fixture();
+    write_file(&tmp.path().join("docs"), "guide.md", 5, 1);
+    write_file(&tmp.path().join("docs"), "faq.md", 5, 1);
+    set_mtime(&tmp.path().join("docs"), 0);
+    insta::assert_snapshot!(nuls(tmp.path(), &["--min-entries", "1", "--sort", "entries"]));
+}
+
+#[test]
+fn group_by_day_inserts_heading_rows() {
+    let tmp = fixture();
+    write_file(tmp.path(), "today.log", 8, 7);
+    insta::assert_snapshot!(nuls(tmp.path(), &["--group-by", "modified-day"]));
+}
+
+#[test]
+fn hide_junk_drops_backup_and_metadata_files() {
+    let tmp = fixture();
+    for name in ["notes.bak", ".DS_Store", "alpha.txt~"] {
+        write_file(tmp.path(), name, 1, 1);
+    }
+    assert_eq!(
+        nuls(tmp.path(), &["-a", "-1", "--hide-junk"]),
+        "docs\nsrc\n.hidden\nalpha.txt\nBeta.md\nrun.sh"
+    );
+}
+
+#[test]
+fn cargo_tags_crates_and_sizes_target() {
+    let tmp = fixture();
+    let root = tmp.path();
+    fs::create_dir_all(root.join("app")).expect("mkdir");
+    fs::create_dir_all(root.join("target/debug")).expect("mkdir");
+    fs::write(root.join("Cargo.toml"), "[workspace]\nmembers = [\"app\"]\n").expect("write manifest");
+    fs::write(root.join("app/Cargo.toml"), "[package]\nname = \"app\"\nversion = \"0.4.0\"\n").expect("write manifest");
+    write_file(&root.join("target/debug"), "app", 3000, 1);
+    for path in ["Cargo.toml", "app", "target/debug", "target"] {
+        set_mtime(&root.join(path), 1);
+    }
+    insta::assert_snapshot!(nuls(root, &["--cargo"]));
+    // The snapshot masks directory sizes; target/ alone carries its contents' total.
+    let tabs = nuls(root, &["--cargo", "--tabs"]);
+    assert!(tabs.contains("target [build output]\tdir\t2.9 KB\t"), "{tabs}");
+}
+
+#[test]
+fn npm_tags_packages_and_sizes_node_modules() {
+    let tmp = fixture();
+    let root = tmp.path();
+    fs::create_dir_all(root.join("web")).expect("mkdir");
+    fs::create_dir_all(root.join("node_modules/left-pad")).expect("mkdir");
+    fs::write(root.join("web/package.json"), r#"{"name": "web", "version": "1.0.0"}"#).expect("write manifest");
+    write_file(&root.join("node_modules/left-pad"), "index.js", 2048, 1);
+    write_file(&root.join("node_modules"), ".package-lock.json", 1024, 1);
+    let tabs = nuls(root, &["--npm", "--tabs"]);
+    assert!(tabs.contains("\tweb [web@1.0.0]\tdir\t"), "{tabs}");
+    assert!(tabs.contains("\tnode_modules [dependencies · 2 entries]\tdir\t3.0 KB\t"), "{tabs}");
+}
+
+#[test]
+fn csv_and_json_follow_the_active_sort() {
+    let tmp = fixture();
+    let root = tmp.path().canonicalize().expect("canonical root");
+    let csv = nuls(&root, &["--csv", "--sort", "modified", "--reverse"]);
+    let mut lines = csv.lines();
+    assert_eq!(lines.next(), Some("# sort=modified reverse=true tie-break=name dirs-first=false explicit-order=false"));
+    assert_eq!(lines.next(), Some("name,path,type,size,disk,modified,executable,entries,git"));
+    let names: Vec<&str> = lines.map(|line| line.split(',').next().unwrap_or_default()).collect();
+    assert_eq!(names, ["docs", "run.sh", "alpha.txt", "Beta.md", "src"]);
+    assert!(
+        csv.contains(&format!("\nBeta.md,{},file,12,,2020-01-06T00:00:00Z,false,,\n", root.join("Beta.md").display())),
+        "{csv}"
+    );
+
+    let json: serde_json::Value =
+        serde_json::from_str(&nuls(&root, &["--json", "-S", "--limit", "2"])).expect("valid JSON");
+    assert_eq!(json["sort"]["key"], "size");
+    assert_eq!(json["omitted"], 3);
+    let first = &json["entries"][0];
+    let name = first["name"].as_str().expect("name");
+    assert_eq!(first["path"], root.join(name).display().to_string());
+
+    let json: serde_json::Value =
+        serde_json::from_str(&nuls(&root, &["--json", "--dirs-first-with-time"])).expect("valid JSON");
+    assert_eq!((&json["sort"]["key"], &json["sort"]["dirs_first"]), (&"modified".into(), &true.into()));
+    let csv = nuls(&root, &["--csv", "--dirs-first-with-time"]);
+    assert!(csv.starts_with("# sort=modified reverse=false tie-break=name dirs-first=true explicit-order=false\n"));
+}
+
+#[cfg(unix)]
+#[test]
+fn csv_and_json_export_symlinks_at_their_own_path() {
+    use std::os::unix::fs::symlink;
+
+    let tmp = fixture();
+    let root = tmp.path().canonicalize().expect("canonical root");
+    symlink(root.join("src/lib.rs"), root.join("link")).expect("symlink");
+    let link = root.join("link").display().to_string();
+
+    let json: serde_json::Value = serde_json::from_str(&nuls(&root, &["--json"])).expect("valid JSON");
+    let entries = json["entries"].as_array().expect("entries");
+    let row = entries.iter().find(|entry| entry["name"] == "link").expect("link row");
+    assert_eq!(row["path"], link);
+
+    let csv = nuls(&root, &["--csv"]);
+    assert!(csv.lines().any(|line| line.starts_with(&format!("link,{link},"))), "{csv}");
+}
+
+#[test]
+fn top_keeps_the_head_of_the_full_sort() {
+    let tmp = fixture();
+    for sort in [&["-S"][..], &["-t"], &["--sort", "name"], &["-S", "-r"]] {
+        let full = nuls(tmp.path(), &[&["-1"][..], sort].concat());
+        let top = nuls(tmp.path(), &[&["-1", "--top", "3"][..], sort].concat());
+        let head: Vec<&str> = full.lines().take(3).collect();
+        assert_eq!(top.lines().take(3).collect::<Vec<_>>(), head, "{sort:?}");
+        assert_eq!(top.lines().nth(3), Some("… 2 more entries not shown (--limit)"), "{sort:?}");
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn statx_lists_what_std_metadata_lists() {
+    let tmp = fixture();
+    for args in [&[][..], &["-a", "-S"], &["--dir-counts", "--hide-junk"], &["--json"]] {
+        let std = nuls(tmp.path(), args);
+        assert_eq!(nuls(tmp.path(), &[args, &["--statx"]].concat()), std, "{args:?}");
+    }
+}
+
+#[test]
+fn show_theme_samples_every_palette_role() {
+    let tmp = TempDir::new().expect("tempdir");
+    insta::assert_snapshot!(nuls(tmp.path(), &["--show-theme"]));
+}
+
+#[test]
+#[cfg_attr(not(feature = "git"), ignore = "needs the git feature")]
+fn git_works_in_linked_worktrees_and_degrades_without_one() {
+    let tmp = fixture();
+    let root = tmp.path();
+    git(root, &["init", "-q"]);
+    git(root, &["add", "."]);
+    assert!(nuls(root, &["-g", "-1"]).contains("run.sh"), "a repository with no commits still lists");
+    git(root, &["commit", "-q", "-m", "fixture"]);
+
+    let linked = tmp.path().join("linked");
+    git(root, &["worktree", "add", "-q", linked.to_str().expect("utf-8 path")]);
+    fs::write(linked.join("Beta.md"), "changed\n").expect("edit");
+    assert!(nuls(&linked, &["-g"]).contains("Beta.md (+1 -1)"));
+
+    let output = nuls_command(&root.join(".git")).args(["-g", "-1"]).output().expect("run nuls");
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("no work tree to compare"), "{stderr}");
+    let quiet = nuls_command(&root.join(".git")).args(["-g", "-1", "--quiet"]).output().expect("run nuls");
+    assert_eq!((quiet.stdout, quiet.stderr.as_slice()), (output.stdout, &b""[..]));
+}
+
+#[test]
+fn verbose_and_timings_report_on_stderr() {
+    let tmp = fixture();
+    let plain = nuls(tmp.path(), &["-1"]);
+    let output = nuls_command(tmp.path())
+        .args(["--now", &NOW.to_string(), "-1", "--verbose"])
+        .output()
+        .expect("run nuls");
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim_end(), plain);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.starts_with("timing: collect ") && stderr.contains(" ms, git ") && stderr.contains(", render "));
+
+    let output = nuls_command(tmp.path()).args(["-1", "--timings"]).output().expect("run nuls");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let phases: Vec<&str> = stderr.lines().skip(1).filter_map(|line| line.split("  ").nth(1)).collect();
+    assert_eq!(phases, ["read_dir", "stat", "git status", "numstat", "sort", "render", "total"], "{stderr}");
+}
+
+#[test]
+fn hide_older_than_drops_stale_entries() {
+    let tmp = fixture();
+    assert_eq!(nuls(tmp.path(), &["-1", "--hide-older-than", "3d"]), "src\nBeta.md");
+    assert_eq!(nuls(tmp.path(), &["-1", "--hide-older-than", "3d", "--recent", "5"]), "Beta.md");
+    let output = nuls_command(tmp.path()).args(["--hide-older-than", "3 fortnights"]).output().expect("run nuls");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("unknown duration unit 'fortnights'"));
+}
+
+#[test]
+fn rename_preview_shows_new_names_and_flags_clashes() {
+    let tmp = fixture();
+    insta::assert_snapshot!(nuls(tmp.path(), &["--rename-preview", r"s/^(\w+)\.(txt|sh)$/\1.md/"]));
+    let clashing = nuls(tmp.path(), &["-a", "--rename-preview", "s/^(alpha|run)\\..*/.hidden/"]);
+    assert_eq!(clashing.matches(".hidden (taken)").count(), 2);
+    assert!(fs::metadata(tmp.path().join("alpha.txt")).is_ok());
+    let output = nuls_command(tmp.path()).args(["--rename-preview", "s/a/b"]).output().expect("run nuls");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("expected s/PATTERN/REPLACEMENT/"));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+#[cfg_attr(not(feature = "trash"), ignore = "needs the trash feature")]
+fn trash_moves_numbered_entries_to_the_trash() {
+    let tmp = fixture();
+    let data = TempDir::new_in(tmp.path().parent().expect("temp root")).expect("data dir");
+    let run = |args: &[&str]| {
+        nuls_command(tmp.path())
+            .env("XDG_DATA_HOME", data.path())
+            .args(args)
+            .output()
+            .expect("run nuls")
+    };
+
+    let output = run(&["-1", "--trash", "2,4"]);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "docs\nsrc\nBeta.md\n");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("moved run.sh to the trash"));
+    assert!(data.path().join("Trash/files/alpha.txt").exists());
+    assert!(data.path().join("Trash/info/run.sh.trashinfo").exists());
+    assert!(!tmp.path().join("alpha.txt").exists());
+
+    let output = run(&["--trash", "3"]);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("no entry 3; the listing is numbered 0 to 2"));
+    let output = run(&["-a", "--show-self", "--trash", "0"]);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("refusing to trash ."));
+    assert!(tmp.path().exists());
+}
+
+#[test]
+fn delta_compares_sizes_with_the_last_snapshot() {
+    let tmp = fixture();
+    let first = nuls(tmp.path(), &["--delta"]);
+    assert!(first.ends_with("no earlier snapshot of this directory; sizes recorded for the next --delta"));
+    assert!(tmp.path().join(".no-state/nuls/sizes.toml").is_file());
+
+    write_file(tmp.path(), "alpha.txt", 4_096, 3);
+    write_file(tmp.path(), "Beta.md", 2, 5);
+    write_file(tmp.path(), "app.log", 100, 5);
+    fs::remove_file(tmp.path().join("run.sh")).expect("remove run.sh");
+    let later = (NOW + 2 * 3_600).to_string();
+    insta::assert_snapshot!(nuls(tmp.path(), &["--delta", "--now", &later]));
+}
+
+#[test]
+fn history_ranks_listed_directories() {
+    let tmp = fixture();
+    assert!(nuls(tmp.path(), &["--history"]).starts_with("no history yet"));
+    nuls(tmp.path(), &["docs"]);
+    assert!(!tmp.path().join(".no-state/nuls/history.toml").exists());
+
+    let config = tmp.path().join(".no-config/nuls/config.toml");
+    fs::create_dir_all(config.parent().expect("parent")).expect("config dir");
+    fs::write(&config, "history = true\n").expect("write config");
+    let day = |days: u64| (NOW - days * 86_400).to_string();
+    for (args, days) in [(["src"], 9), (["src"], 8), (["src"], 8), (["docs"], 2), (["."], 0)] {
+        nuls(tmp.path(), &[&args[..], &["--now", &day(days)]].concat());
+    }
+    nuls(tmp.path(), &["-d", "docs"]);
+    let root = tmp.path().canonicalize().expect("canonicalize");
+    let root = root.to_str().expect("utf-8 path");
+    let table = nuls(tmp.path(), &["--history"]);
+    let rows: Vec<Vec<&str>> = table
+        .lines()
+        .skip(3)
+        .filter(|line| line.starts_with('│'))
+        .map(|line| line.split('│').map(str::trim).filter(|cell| !cell.is_empty()).collect())
+        .collect();
+    let (src, docs) = (format!("{root}/src"), format!("{root}/docs"));
+    assert_eq!(
+        rows,
+        [
+            ["0", root, "1", "just now"],
+            ["1", &src, "3", "1 week ago"],
+            ["2", &docs, "1", "2 days ago"]
+        ]
+    );
+    assert_eq!(nuls(tmp.path(), &["--history=SRC"]), format!("{root}/src"));
+    let output = nuls_command(tmp.path()).arg("--history=nowhere").output().expect("run nuls");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("no listed directory matches 'nowhere'"));
+}
+
+#[test]
+fn totals_sum_and_average_numeric_columns() {
+    let tmp = fixture();
+    insta::assert_snapshot!(nuls(tmp.path(), &["--totals", "--dir-counts"]));
+}
+
+#[test]
+fn size_fixed_width_lines_up_decimal_points() {
+    let tmp = TempDir::new().expect("tempdir");
+    write_file(tmp.path(), "page.bin", 4_096, 2);
+    write_file(tmp.path(), "note.txt", 512, 2);
+    let big = tmp.path().join("video.mp4");
+    fs::File::create(&big).and_then(|file| file.set_len(12 * 1024 * 1024)).expect("sparse file");
+    set_mtime(&big, 2);
+    insta::assert_snapshot!(nuls(tmp.path(), &["--size-fixed-width", "-S"]));
+}
+
+#[test]
+fn elide_common_drops_columns_every_row_shares() {
+    let tmp = TempDir::new().expect("tempdir");
+    write_file(tmp.path(), "a.log", 10, 2);
+    write_file(tmp.path(), "b.log", 10, 2);
+    write_file(tmp.path(), "c.log", 20, 2);
+    insta::assert_snapshot!(nuls(tmp.path(), &["--elide-common"]));
+}
+
+#[test]
+fn cd_on_exit_writes_the_listed_directory() {
+    let tmp = fixture();
+    let target = tmp.path().join("cd-target");
+    let target_arg = target.to_str().expect("utf-8 path");
+    let root = tmp.path().canonicalize().expect("canonical root");
+    nuls(tmp.path(), &["--cd-on-exit", target_arg, "src"]);
+    assert_eq!(fs::read_to_string(&target).expect("read target"), root.join("src").to_string_lossy());
+    nuls(tmp.path(), &["--cd-on-exit", target_arg, "-d", "alpha.txt"]);
+    assert_eq!(fs::read_to_string(&target).expect("read target"), root.to_string_lossy());
+}
+
+#[test]
+fn order_file_puts_listed_names_first() {
+    let tmp = fixture();
+    fs::write(tmp.path().join(".nulsorder"), "# reading order\nrun.sh\n\nsrc/\nmissing.md\n").expect("write");
+    assert_eq!(nuls(tmp.path(), &["-1"]), "run.sh\nsrc\ndocs\nalpha.txt\nBeta.md");
+    assert_eq!(nuls(tmp.path(), &["-1", "-r"]), "Beta.md\nalpha.txt\ndocs\nsrc\nrun.sh");
+    assert_eq!(nuls(tmp.path(), &["-1", "--sort", "name"]), "docs\nsrc\nalpha.txt\nBeta.md\nrun.sh");
+    assert_eq!(nuls(tmp.path(), &["-1", "-t"]), "src\nBeta.md\nalpha.txt\nrun.sh\ndocs");
+
+    let json: serde_json::Value = serde_json::from_str(&nuls(tmp.path(), &["--json"])).expect("valid JSON");
+    assert_eq!(json["sort"]["key"], "name");
+    assert_eq!(json["sort"]["explicit_order"], true);
+    assert_eq!(json["entries"][0]["name"], "run.sh");
+    let csv = nuls(tmp.path(), &["--csv", "--sort", "name"]);
+    let comment = csv.lines().next().unwrap_or_default();
+    assert_eq!(comment, "# sort=name reverse=false tie-break=name dirs-first=false explicit-order=false");
+    let csv = nuls(tmp.path(), &["--csv"]);
+    let comment = csv.lines().next().unwrap_or_default();
+    assert_eq!(comment, "# sort=name reverse=false tie-break=name dirs-first=false explicit-order=true");
+}
+
+#[test]
+fn stdin_lists_piped_paths_by_line_or_nul() {
+    let tmp = fixture();
+    let run = |input: &str| {
+        let mut child = nuls_command(tmp.path())
+            .args(["--stdin", "-1", "--sort", "size"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .expect("run nuls");
+        child.stdin.take().expect("stdin").write_all(input.as_bytes()).expect("write paths");
+        child.wait_with_output().expect("wait for nuls")
+    };
+
+    let output = run("./alpha.txt\nsrc/lib.rs\r\n\nmissing.txt\n");
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "alpha.txt\nsrc/lib.rs\n");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("cannot read metadata for missing.txt"));
+
+    let output = run(".hidden\0src/lib.rs\0");
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "src/lib.rs\n.hidden\n");
+}
+
+#[test]
+#[cfg_attr(not(feature = "git"), ignore = "needs the git feature")]
+fn sparse_checkout_shows_paths_left_off_disk() {
+    let tmp = fixture();
+    let root = tmp.path();
+    write_file(&root.join("docs"), "guide.md", 5, 1);
+    set_mtime(&root.join("docs"), 0);
+    git(root, &["init", "-q"]);
+    git(root, &["add", "."]);
+    git(root, &["commit", "-q", "-m", "fixture"]);
+    git(root, &["sparse-checkout", "set", "src"]);
+    assert!(!root.join("docs").exists());
+
+    let table = nuls(root, &["-g"]);
+    assert!(table.contains("│ 0 │ docs (1 not checked out) │ dir  │      - │ -          │"), "{table}");
+}
+
+#[test]
+#[cfg_attr(not(feature = "git"), ignore = "needs the git feature")]
+fn tracked_only_lists_what_git_ls_files_knows() {
+    let tmp = fixture();
+    let root = tmp.path();
+    fs::create_dir(root.join("target")).expect("mkdir");
+    write_file(&root.join("target"), "app", 99, 1);
+    git(root, &["init", "-q"]);
+    git(root, &["add", "alpha.txt", "src/lib.rs"]);
+
+    assert_eq!(nuls(root, &["-1", "-a", "--tracked-only"]), "src\nalpha.txt");
+    assert_eq!(nuls(root, &["-1", "--tracked-only", "--recent", "5"]), "alpha.txt\nsrc/lib.rs");
+    assert_eq!(nuls(&root.join("src"), &["-1", "--tracked-only"]), "lib.rs");
+    let output = nuls_command(&root.join("docs")).args(["--tracked-only"]).output().expect("run nuls");
+    assert!(output.status.success());
+
+    let outside = TempDir::new().expect("tempdir");
+    let output = nuls_command(outside.path()).args(["--tracked-only"]).output().expect("run nuls");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("is not in a git work tree"));
+}
+
+#[test]
+#[cfg_attr(not(feature = "git"), ignore = "needs the git feature")]
+fn assume_unchanged_files_are_flagged() {
+    let tmp = fixture();
+    let root = tmp.path();
+    git(root, &["init", "-q"]);
+    git(root, &["add", "."]);
+    git(root, &["commit", "-q", "-m", "fixture"]);
+    git(root, &["update-index", "--assume-unchanged", "Beta.md", "src/lib.rs"]);
+    fs::write(root.join("Beta.md"), "hidden edit\n").expect("edit");
+
+    let table = nuls(root, &["-g"]);
+    assert!(table.contains("│ src (1 assume-unchanged) "), "{table}");
+    assert!(table.contains("│ Beta.md (assume-unchanged) │"), "{table}");
+}
+
+#[test]
+#[cfg_attr(not(feature = "git"), ignore = "needs the git feature")]
+fn git_follows_git_dir_and_git_work_tree() {
+    let tmp = fixture();
+    let root = tmp.path();
+    git(root, &["init", "-q"]);
+    git(root, &["add", "."]);
+    git(root, &["commit", "-q", "-m", "fixture"]);
+    fs::write(root.join("Beta.md"), "edited\n").expect("edit");
+    let store = tempfile::tempdir().expect("tempdir");
+    let git_dir = store.path().join("repo.git");
+    fs::rename(root.join(".git"), &git_dir).expect("move .git");
+
+    let list = |cwd: &Path, git_dir: &Path, args: &[&str], work_tree: Option<&Path>| {
+        let mut command = nuls_command(cwd);
+        command.env("GIT_DIR", git_dir).args(["--now", &NOW.to_string(), "-g"]).args(args);
+        match work_tree {
+            Some(tree) => command.env("GIT_WORK_TREE", tree),
+            None => command.env_remove("GIT_WORK_TREE"),
+        };
+        let output = command.output().expect("run nuls");
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        String::from_utf8(output.stdout).expect("utf-8 output")
+    };
+    let root_arg = root.to_str().expect("utf-8 path");
+    let table = list(root, &git_dir, &[], None);
+    assert!(table.contains("│ Beta.md (+1 -1) "), "{table}");
+    // Relative to where nuls was started, not to the listed directory.
+    let table = list(store.path(), Path::new("repo.git"), &[root_arg], Some(root));
+    assert!(table.contains("│ Beta.md (+1 -1) "), "{table}");
+
+    // A path outside that work tree keeps its own repository.
+    let other = fixture();
+    git(other.path(), &["init", "-q"]);
+    fs::write(other.path().join("Beta.md"), "other\n").expect("edit");
+    git(other.path(), &["add", "."]);
+    git(other.path(), &["commit", "-q", "-m", "other"]);
+    let table = list(root, &git_dir, &[other.path().to_str().expect("utf-8 path")], None);
+    assert!(table.contains("│ Beta.md "), "{table}");
+    assert!(!table.contains("│ Beta.md ("), "{table}");
+}
+
+#[test]
+#[cfg_attr(not(feature = "git"), ignore = "needs the git feature")]
+fn staged_changes_keep_their_line_counts() {
+    let tmp = fixture();
+    let root = tmp.path();
+    git(root, &["init", "-q"]);
+    git(root, &["add", "alpha.txt"]);
+    let table = nuls(root, &["-g"]);
+    assert!(table.contains("│ alpha.txt (+1 -0) "), "staged before the first commit: {table}");
+
+    git(root, &["add", "."]);
+    git(root, &["commit", "-q", "-m", "fixture"]);
+    let original = fs::read(root.join("Beta.md")).expect("read");
+    fs::write(root.join("Beta.md"), "staged\n").expect("edit");
+    git(root, &["add", "Beta.md"]);
+    fs::write(root.join("Beta.md"), &original).expect("undo");
+    fs::write(root.join("alpha.txt"), "one\ntwo\n").expect("edit");
+    git(root, &["add", "alpha.txt"]);
+    fs::write(root.join("alpha.txt"), "one\ntwo\nthree\n").expect("edit");
+
+    let table = nuls(root, &["-g"]);
+    assert!(table.contains("│ Beta.md (+1 -1) "), "staged, then undone on disk: {table}");
+    assert!(table.contains("│ alpha.txt (+3 -1) "), "staged and unstaged edits net out: {table}");
+}
+
+#[test]
+#[cfg_attr(not(feature = "git"), ignore = "needs the git feature")]
+fn changed_binary_files_are_marked() {
+    let tmp = fixture();
+    let root = tmp.path();
+    fs::write(root.join("logo.png"), b"\x89PNG\0\x01").expect("write");
+    git(root, &["init", "-q"]);
+    git(root, &["add", "."]);
+    git(root, &["commit", "-q", "-m", "fixture"]);
+    fs::write(root.join("logo.png"), b"\x89PNG\0\x02").expect("edit");
+    git(root, &["add", "logo.png"]);
+
+    let table = nuls(root, &["-g"]);
+    assert!(table.contains("│ logo.png (bin) "), "{table}");
+}
+
+#[test]
+#[cfg_attr(not(feature = "git"), ignore = "needs the git feature")]
+fn git_diff_base_counts_changes_since_the_branch_point() {
+    let tmp = fixture();
+    let root = tmp.path();
+    git(root, &["init", "-q"]);
+    git(root, &["add", "."]);
+    git(root, &["commit", "-q", "-m", "fixture"]);
+    git(root, &["branch", "base"]);
+    fs::write(root.join("alpha.txt"), "one\ntwo\n").expect("edit");
+    git(root, &["commit", "-q", "-am", "on the branch"]);
+    fs::write(root.join("src/lib.rs"), "").expect("edit");
+
+    let head = nuls(root, &["-g"]);
+    assert!(head.contains("│ alpha.txt "), "{head}");
+    let table = nuls(root, &["-g", "--git-diff-base", "base"]);
+    assert!(table.contains("│ alpha.txt (+2 -1) "), "{table}");
+    assert!(table.contains("│ src (+0 -1 · 1 file) "), "{table}");
+
+    let output = nuls_command(root).args(["-g", "--git-diff-base", "nope"]).output().expect("run nuls");
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--git-diff-base nope: not a commit"));
+}
+
+#[test]
+#[cfg_attr(not(feature = "git"), ignore = "needs the git feature")]
+fn git_status_is_automatic_inside_a_work_tree() {
+    let tmp = fixture();
+    let root = tmp.path();
+    assert!(!nuls(root, &[]).contains("(+?)"), "no repository yet");
+    git(root, &["init", "-q"]);
+    assert!(nuls(root, &[]).contains("│ Beta.md (+?) "));
+    git(root, &["add", "."]);
+    git(root, &["commit", "-q", "-m", "fixture"]);
+    fs::write(root.join("src/new.rs"), "").expect("write");
+    fs::write(root.join("Beta.md"), "").expect("write");
+    assert!(nuls(&root.join("src"), &[]).contains("│ new.rs (+?) "), "found from a subdirectory");
+    assert!(!nuls(root, &["--git=never"]).contains("(+?)"));
+    let inside_git_dir = nuls_command(&root.join(".git")).output().expect("run nuls");
+    assert!(inside_git_dir.stderr.is_empty(), "no note unless -g is given");
+
+    fs::create_dir(root.join(".no-config")).expect("config dir");
+    fs::write(root.join("config.toml"), "git = \"never\"\n").expect("write config");
+    let listing = |args: &[&str]| {
+        let output = nuls_command(root)
+            .env("NULS_CONFIG", root.join("config.toml"))
+            .args(["--now", &NOW.to_string()])
+            .args(args)
+            .output()
+            .expect("run nuls");
+        String::from_utf8(output.stdout).expect("utf-8 output")
+    };
+    assert!(!listing(&[]).contains("(+?)"), "config turns it off");
+    let forced = listing(&["-g"]);
+    assert!(forced.contains("│ Beta.md (+0 -1) "), "a bare -g still forces it: {forced}");
+}
+
+#[cfg(unix)]
+#[test]
+#[cfg_attr(not(feature = "git"), ignore = "needs the git feature")]
+fn a_hung_git_times_out_without_losing_the_listing() {
+    let tmp = fixture();
+    let bin = tempfile::tempdir().expect("tempdir");
+    let fake_git = bin.path().join("git");
+    fs::write(&fake_git, "#!/bin/sh\nexec sleep 30\n").expect("write fake git");
+    make_executable(&fake_git);
+    let path = format!("{}:{}", bin.path().display(), std::env::var("PATH").unwrap_or_default());
+
+    for args in [&["-g"][..], &["--git-age"][..]] {
+        let started = std::time::Instant::now();
+        let output = nuls_command(tmp.path())
+            .env("PATH", &path)
+            .args(["--now", &NOW.to_string(), "--git-timeout", "1s"])
+            .args(args)
+            .output()
+            .expect("run nuls");
+        assert!(started.elapsed() < std::time::Duration::from_secs(20), "{args:?} waited for git");
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains("timed out after 1s; listing without git info"), "{stderr}");
+        assert!(String::from_utf8_lossy(&output.stdout).contains("│ Beta.md "));
+    }
+}
+
+#[test]
+#[cfg_attr(not(feature = "git"), ignore = "needs the git feature")]
+fn git_age_colors_names_by_last_commit() {
+    let tmp = fixture();
+    let root = tmp.path();
+    git(root, &["init", "-q"]);
+    let commit = |paths: &[&str], date: &str| {
+        git(root, &[&["add", "--"][..], paths].concat());
+        let status = Command::new("git")
+            .args(["-c", "user.name=nuls", "-c", "user.email=nuls@example.com", "-c", "commit.gpgsign=false"])
+            .args(["commit", "-q", "-m", "dated"])
+            .env("GIT_AUTHOR_DATE", date)
+            .env("GIT_COMMITTER_DATE", date)
+            .current_dir(root)
+            .status()
+            .expect("run git");
+        assert!(status.success());
+    };
+    commit(&["alpha.txt"], "2015-06-01T00:00:00Z");
+    commit(&["src"], "2020-01-07T12:00:00Z");
+
+    // Colors are the point here, so skip `nuls_command`'s `--color never`.
+    let output = Command::new(env!("CARGO_BIN_EXE_nuls"))
+        .args(["--now", &NOW.to_string(), "--color", "always", "--lang", "en", "-1", "--git-age"])
+        .env("XDG_CONFIG_HOME", root.join(".no-config"))
+        .env_remove("NULS_CONFIG")
+        .current_dir(root)
+        .output()
+        .expect("run nuls");
+    let text = String::from_utf8_lossy(&output.stdout);
+    assert!(text.contains("\x1b[38;5;244malpha.txt\x1b[0m"), "years old: {text:?}");
+    assert!(text.contains("\x1b[38;5;114msrc\x1b[0m"), "hours old: {text:?}");
+    assert!(text.contains("\x1b[38;5;214mBeta.md"), "never committed keeps its class color: {text:?}");
+
+    fs::write(root.join("notes.txt~"), "").expect("write");
+    let text = nuls(root, &["-1", "--git-age", "--ascii-status"]);
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(
+        lines,
+        ["docs/", "src/ (committed 12h ago)", "alpha.txt (committed 4y ago)", "Beta.md", "notes.txt~ (junk)", "run.sh*"]
+    );
+}
+
+#[test]
+#[cfg_attr(not(feature = "git"), ignore = "needs the git feature")]
+fn git_author_names_the_last_committer() {
+    let tmp = fixture();
+    let root = tmp.path();
+    git(root, &["init", "-q"]);
+    git(root, &["add", "."]);
+    git(root, &["commit", "-q", "-m", "fixture"]);
+    fs::write(root.join("Beta.md"), "edited\n").expect("edit");
+    set_mtime(&root.join("Beta.md"), 5);
+    git(root, &["add", "Beta.md"]);
+    git(root, &["-c", "user.name=Ada Lovelace", "commit", "-q", "-m", "edit"]);
+    insta::assert_snapshot!(nuls(root, &["--git-author"]));
+}
+
+#[test]
+fn man_page_documents_every_flag() {
+    let tmp = TempDir::new().expect("tempdir");
+    let man = nuls(tmp.path(), &["--man"]);
+    assert!(man.contains(".TH nuls 1") && man.contains(".SH OPTIONS"), "{man}");
+    // `--help` is colored whatever `--color` says; drop the escapes.
+    let mut help = String::new();
+    let mut chars = nuls(tmp.path(), &["--help"]).chars().collect::<Vec<_>>().into_iter();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            chars.by_ref().find(|&c| c == 'm');
+        } else {
+            help.push(c);
+        }
+    }
+    let flags: Vec<&str> = help
+        .lines()
+        .filter(|line| line.trim_start().starts_with('-'))
+        .flat_map(|line| line.split([' ', ',', '[', '=']).filter(|word| word.starts_with("--")).take(1))
+        .collect();
+    assert!(flags.len() > 50 && flags.contains(&"--statx"), "{flags:?}");
+    for flag in flags {
+        assert!(man.contains(&flag.replace('-', "\\-")), "{flag} is missing from the man page");
+    }
+
+    let dir = tmp.path().join("man");
+    fs::create_dir(&dir).expect("mkdir");
+    nuls(tmp.path(), &[&format!("--man={}", dir.display())]);
+    let mut pages: Vec<String> = fs::read_dir(&dir)
+        .expect("read_dir")
+        .map(|entry| entry.expect("entry").file_name().to_string_lossy().to_string())
+        .collect();
+    pages.sort();
+    assert!(pages.len() > 5 && pages.contains(&"nuls-stat.1".to_string()), "{pages:?}");
+    // Nested pages (nuls-bookmark-add.1) are listed by their parent's page.
+    for name in pages.iter().map(|page| page.trim_end_matches(".1")).filter(|name| name.matches('-').count() == 1) {
+        assert!(man.contains(&name.replace('-', "\\-")), "{name} is not listed in nuls(1)");
+    }
+}